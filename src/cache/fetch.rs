@@ -0,0 +1,434 @@
+//! On-disk cache for fetched pages
+//!
+//! Entries are stored under the final URL (after redirects), the content
+//! format and the max length; when the requested URL redirected, a `.link`
+//! file under the requested URL points at that entry, and lookups go through
+//! it. Entries keep the HTTP validators (`ETag`, `Last-Modified`) and
+//! `Cache-Control` header so stale entries can be revalidated with a
+//! conditional request instead of being downloaded again.
+//!
+//! Only anonymous responses belong here: the fetcher neither stores nor looks
+//! up requests sending credentials, cookies or custom headers, and
+//! `Cache-Control: private` responses are never stored. Files are readable by
+//! their owner only.
+
+use crate::config::cache_dir;
+use crate::error::Result;
+use crate::fetch::{ContentFormat, FetchResponse};
+use reqwest::header::{HeaderMap, CACHE_CONTROL, ETAG, LAST_MODIFIED};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Extension for files holding a cached page
+const ENTRY_EXT: &str = "json";
+
+/// Extension for files pointing a requested URL at the entry for its final URL
+const LINK_EXT: &str = "link";
+
+/// A cached page together with its HTTP caching metadata
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedPage {
+    /// ETag header from the last response
+    pub etag: Option<String>,
+
+    /// Last-Modified header from the last response
+    pub last_modified: Option<String>,
+
+    /// Cache-Control header from the last response
+    pub cache_control: Option<String>,
+
+    /// Unix timestamp (seconds) when the entry was stored or revalidated
+    pub stored_at: u64,
+
+    /// The fetched response
+    pub response: FetchResponse,
+}
+
+impl CachedPage {
+    /// Create a cache entry from a response and its headers
+    pub fn new(response: FetchResponse, headers: &HeaderMap) -> Self {
+        let mut page = Self {
+            etag: None,
+            last_modified: None,
+            cache_control: None,
+            stored_at: 0,
+            response,
+        };
+        page.update_headers(headers);
+        page
+    }
+
+    /// Refresh the caching metadata from a (possibly 304) response
+    pub fn update_headers(&mut self, headers: &HeaderMap) {
+        let header = |name| {
+            headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(|s| s.to_string())
+        };
+
+        if let Some(etag) = header(ETAG) {
+            self.etag = Some(etag);
+        }
+        if let Some(last_modified) = header(LAST_MODIFIED) {
+            self.last_modified = Some(last_modified);
+        }
+        if let Some(cache_control) = header(CACHE_CONTROL) {
+            self.cache_control = Some(cache_control);
+        }
+        self.stored_at = now_secs();
+    }
+
+    /// Check whether the entry can be served without revalidation
+    pub fn is_fresh(&self) -> bool {
+        if self.has_directive("no-cache") {
+            return false;
+        }
+
+        match self.max_age() {
+            Some(max_age) => now_secs().saturating_sub(self.stored_at) < max_age,
+            None => false,
+        }
+    }
+
+    /// Check whether the response is allowed to be stored at all
+    pub fn is_storable(&self) -> bool {
+        if self.has_directive("no-store") || self.has_directive("private") {
+            return false;
+        }
+
+        self.etag.is_some() || self.last_modified.is_some() || self.max_age().unwrap_or(0) > 0
+    }
+
+    /// Parse `max-age` from the Cache-Control header
+    fn max_age(&self) -> Option<u64> {
+        self.directives().find_map(|d| {
+            d.strip_prefix("max-age=")
+                .and_then(|v| v.trim_matches('"').parse().ok())
+        })
+    }
+
+    fn has_directive(&self, name: &str) -> bool {
+        self.directives().any(|d| d == name)
+    }
+
+    fn directives(&self) -> impl Iterator<Item = String> + '_ {
+        self.cache_control
+            .as_deref()
+            .unwrap_or("")
+            .split(',')
+            .map(|d| d.trim().to_lowercase())
+            .filter(|d| !d.is_empty())
+    }
+}
+
+/// On-disk cache of fetched pages
+#[derive(Clone)]
+pub struct FetchCache {
+    dir: PathBuf,
+}
+
+impl FetchCache {
+    /// Create a cache stored in the given directory
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Open the cache in the default location under the cache directory
+    pub fn open() -> Result<Self> {
+        Ok(Self::new(cache_dir()?.join("http")))
+    }
+
+    /// Generate a cache key from URL, format and max length
+    fn cache_key(url: &str, format: &ContentFormat, max_length: usize) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(format!("{}\n{}\n{}", format.as_str(), max_length, url));
+        format!("{:x}", hasher.finalize())
+    }
+
+    fn path_for(&self, key: &str, ext: &str) -> PathBuf {
+        self.dir.join(format!("{}.{}", key, ext))
+    }
+
+    /// Look up a cached page by requested or final URL
+    pub fn get(&self, url: &str, format: &ContentFormat, max_length: usize) -> Option<CachedPage> {
+        let key = Self::cache_key(url, format, max_length);
+
+        let entry_path = self.path_for(&key, ENTRY_EXT);
+        let entry_path = if entry_path.exists() {
+            entry_path
+        } else {
+            // The URL may have redirected; follow the link to the final URL's entry
+            let target = fs::read_to_string(self.path_for(&key, LINK_EXT)).ok()?;
+            self.path_for(target.trim(), ENTRY_EXT)
+        };
+
+        let content = fs::read_to_string(entry_path).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    /// Store a page under its final URL, linking the requested URL to it
+    pub fn put(
+        &self,
+        requested_url: &str,
+        format: &ContentFormat,
+        max_length: usize,
+        page: &CachedPage,
+    ) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+
+        let key = Self::cache_key(&page.response.final_url, format, max_length);
+        write_private(&self.path_for(&key, ENTRY_EXT), &serde_json::to_vec(page)?)?;
+
+        if requested_url != page.response.final_url {
+            let link_key = Self::cache_key(requested_url, format, max_length);
+            write_private(&self.path_for(&link_key, LINK_EXT), key.as_bytes())?;
+        }
+
+        Ok(())
+    }
+
+    /// Remove all cached pages, returning the number of entries removed
+    pub fn clear(&self) -> Result<usize> {
+        let mut removed = 0;
+
+        for path in self.files()? {
+            if has_ext(&path, ENTRY_EXT) {
+                removed += 1;
+            }
+            fs::remove_file(path)?;
+        }
+
+        Ok(removed)
+    }
+
+    /// Get fetch cache statistics
+    pub fn stats(&self) -> FetchCacheStats {
+        let entries: Vec<PathBuf> = self
+            .files()
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|p| has_ext(p, ENTRY_EXT))
+            .collect();
+
+        FetchCacheStats {
+            entries: entries.len(),
+            size_bytes: entries
+                .iter()
+                .filter_map(|p| fs::metadata(p).ok())
+                .map(|m| m.len())
+                .sum(),
+            location: self.dir.clone(),
+        }
+    }
+
+    /// List the cache files (entries and links)
+    fn files(&self) -> Result<Vec<PathBuf>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut files = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if has_ext(&path, ENTRY_EXT) || has_ext(&path, LINK_EXT) {
+                files.push(path);
+            }
+        }
+        Ok(files)
+    }
+}
+
+/// Fetch cache statistics
+#[derive(Debug, Clone)]
+pub struct FetchCacheStats {
+    pub entries: usize,
+    pub size_bytes: u64,
+    pub location: PathBuf,
+}
+
+impl std::fmt::Display for FetchCacheStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Fetch Cache Statistics:")?;
+        writeln!(f, "  Entries: {}", self.entries)?;
+        writeln!(f, "  Size: {} bytes", self.size_bytes)?;
+        writeln!(f, "  Location: {}", self.location.display())?;
+        Ok(())
+    }
+}
+
+/// Write a cache file only its owner can read
+fn write_private(path: &Path, content: &[u8]) -> Result<()> {
+    fs::write(path, content)?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+    }
+
+    Ok(())
+}
+
+fn has_ext(path: &Path, ext: &str) -> bool {
+    path.extension().and_then(|e| e.to_str()) == Some(ext)
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn create_test_response(url: &str, final_url: &str) -> FetchResponse {
+        FetchResponse {
+            format_version: crate::output::OUTPUT_FORMAT_VERSION,
+            url: url.to_string(),
+            final_url: final_url.to_string(),
+            status: 200,
+            content_type: Some("text/html".to_string()),
+            content: "Hello".to_string(),
+            content_length: 5,
+            title: Some("Test".to_string()),
+        }
+    }
+
+    fn headers(pairs: &[(reqwest::header::HeaderName, &'static str)]) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        for (name, value) in pairs {
+            map.insert(name.clone(), HeaderValue::from_static(value));
+        }
+        map
+    }
+
+    #[test]
+    fn test_cached_page_headers() {
+        let page = CachedPage::new(
+            create_test_response("https://example.com", "https://example.com"),
+            &headers(&[
+                (ETAG, "\"abc\""),
+                (LAST_MODIFIED, "Wed, 21 Oct 2015 07:28:00 GMT"),
+                (CACHE_CONTROL, "public, max-age=60"),
+            ]),
+        );
+
+        assert_eq!(page.etag.as_deref(), Some("\"abc\""));
+        assert!(page.last_modified.is_some());
+        assert_eq!(page.max_age(), Some(60));
+        assert!(page.is_fresh());
+        assert!(page.is_storable());
+    }
+
+    #[test]
+    fn test_cached_page_freshness() {
+        let mut page = CachedPage::new(
+            create_test_response("https://example.com", "https://example.com"),
+            &headers(&[(CACHE_CONTROL, "max-age=60")]),
+        );
+        page.stored_at -= 120;
+        assert!(!page.is_fresh());
+
+        let page = CachedPage::new(
+            create_test_response("https://example.com", "https://example.com"),
+            &headers(&[(ETAG, "\"abc\""), (CACHE_CONTROL, "no-cache, max-age=60")]),
+        );
+        assert!(!page.is_fresh());
+        assert!(page.is_storable());
+    }
+
+    #[test]
+    fn test_cached_page_storable() {
+        let page = CachedPage::new(
+            create_test_response("https://example.com", "https://example.com"),
+            &headers(&[(ETAG, "\"abc\""), (CACHE_CONTROL, "no-store")]),
+        );
+        assert!(!page.is_storable());
+
+        // Meant for one user only
+        let page = CachedPage::new(
+            create_test_response("https://example.com", "https://example.com"),
+            &headers(&[(ETAG, "\"abc\""), (CACHE_CONTROL, "private, max-age=60")]),
+        );
+        assert!(!page.is_storable());
+
+        // Nothing to revalidate with and no freshness lifetime
+        let page = CachedPage::new(
+            create_test_response("https://example.com", "https://example.com"),
+            &HeaderMap::new(),
+        );
+        assert!(!page.is_storable());
+    }
+
+    #[test]
+    fn test_fetch_cache_put_get() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = FetchCache::new(dir.path());
+        let page = CachedPage::new(
+            create_test_response("https://example.com/old", "https://example.com/new"),
+            &headers(&[(ETAG, "\"abc\"")]),
+        );
+
+        cache
+            .put("https://example.com/old", &ContentFormat::Text, 0, &page)
+            .unwrap();
+
+        // Reachable by both the requested and the final URL
+        let by_final = cache.get("https://example.com/new", &ContentFormat::Text, 0);
+        let by_requested = cache.get("https://example.com/old", &ContentFormat::Text, 0);
+        assert_eq!(by_final.unwrap().etag.as_deref(), Some("\"abc\""));
+        assert_eq!(
+            by_requested.unwrap().response.final_url,
+            "https://example.com/new"
+        );
+
+        #[cfg(unix)]
+        for entry in fs::read_dir(dir.path()).unwrap() {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = entry.unwrap().metadata().unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        // Different format or max length is a different entry
+        assert!(cache
+            .get("https://example.com/new", &ContentFormat::Markdown, 0)
+            .is_none());
+        assert!(cache
+            .get("https://example.com/new", &ContentFormat::Text, 100)
+            .is_none());
+    }
+
+    #[test]
+    fn test_fetch_cache_stats_and_clear() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = FetchCache::new(dir.path().join("http"));
+        assert_eq!(cache.stats().entries, 0);
+
+        let page = CachedPage::new(
+            create_test_response("https://a.example", "https://b.example"),
+            &headers(&[(ETAG, "\"abc\"")]),
+        );
+        cache
+            .put("https://a.example", &ContentFormat::Html, 0, &page)
+            .unwrap();
+
+        let stats = cache.stats();
+        assert_eq!(stats.entries, 1);
+        assert!(stats.size_bytes > 0);
+        assert!(format!("{}", stats).contains("Fetch Cache Statistics:"));
+
+        assert_eq!(cache.clear().unwrap(), 1);
+        assert_eq!(cache.stats().entries, 0);
+        assert!(cache
+            .get("https://a.example", &ContentFormat::Html, 0)
+            .is_none());
+    }
+}
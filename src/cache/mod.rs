@@ -1,8 +1,12 @@
 //! Result caching
 
+mod fetch;
+
+pub use fetch::{CachedPage, FetchCache};
+
 use crate::config::CacheConfig;
 use crate::error::Result;
-use crate::providers::SearchResult;
+use crate::providers::{SearchOptions, SearchResult};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::RwLock;
@@ -33,21 +37,34 @@ impl SearchCache {
         }
     }
 
-    /// Generate a cache key from query and options
-    fn cache_key(query: &str, provider: Option<&str>) -> String {
-        match provider {
-            Some(p) => format!("{}:{}", p, query.to_lowercase()),
-            None => query.to_lowercase(),
-        }
+    /// Generate a cache key from the query, the provider asked for (`*` when
+    /// the fallback order decides) and the options that change the results
+    fn cache_key(query: &str, provider: Option<&str>, options: &SearchOptions) -> String {
+        format!(
+            "{}:{}:{:?}:{:?}:{:?}:{:?}:{}",
+            provider.unwrap_or("*"),
+            options.num_results,
+            options.safe_search,
+            options.date_range,
+            options.include_domains,
+            options.exclude_domains,
+            query.to_lowercase()
+        )
     }
 
-    /// Get cached results if available and not expired
-    pub fn get(&self, query: &str, provider: Option<&str>) -> Option<(Vec<SearchResult>, String)> {
+    /// Get cached results and the provider that returned them, if available
+    /// and not expired
+    pub fn get(
+        &self,
+        query: &str,
+        provider: Option<&str>,
+        options: &SearchOptions,
+    ) -> Option<(Vec<SearchResult>, String)> {
         if !self.config.enabled {
             return None;
         }
 
-        let key = Self::cache_key(query, provider);
+        let key = Self::cache_key(query, provider, options);
         let entries = self.entries.read().ok()?;
 
         if let Some(entry) = entries.get(&key) {
@@ -62,13 +79,21 @@ impl SearchCache {
         None
     }
 
-    /// Store results in cache
-    pub fn set(&self, query: &str, provider: &str, results: Vec<SearchResult>) {
+    /// Store the results of a search that asked for `provider` and was
+    /// answered by `provider_used`
+    pub fn set(
+        &self,
+        query: &str,
+        provider: Option<&str>,
+        options: &SearchOptions,
+        provider_used: &str,
+        results: Vec<SearchResult>,
+    ) {
         if !self.config.enabled {
             return;
         }
 
-        let key = Self::cache_key(query, Some(provider));
+        let key = Self::cache_key(query, provider, options);
 
         if let Ok(mut entries) = self.entries.write() {
             // Evict old entries if at capacity
@@ -80,7 +105,7 @@ impl SearchCache {
                 key,
                 CacheEntry {
                     results,
-                    provider: provider.to_string(),
+                    provider: provider_used.to_string(),
                     created_at: Some(Instant::now()),
                     ttl_seconds: self.config.ttl_seconds,
                 },
@@ -159,6 +184,10 @@ mod tests {
         }
     }
 
+    fn options() -> SearchOptions {
+        SearchOptions::new()
+    }
+
     fn create_test_result(title: &str) -> SearchResult {
         SearchResult {
             title: title.to_string(),
@@ -176,9 +205,15 @@ mod tests {
 
         let results = vec![create_test_result("Test")];
 
-        cache.set("test query", "brave", results.clone());
+        cache.set(
+            "test query",
+            Some("brave"),
+            &options(),
+            "brave",
+            results.clone(),
+        );
 
-        let cached = cache.get("test query", Some("brave"));
+        let cached = cache.get("test query", Some("brave"), &options());
         assert!(cached.is_some());
 
         let (cached_results, provider) = cached.unwrap();
@@ -194,8 +229,8 @@ mod tests {
 
         let results = vec![create_test_result("Test")];
 
-        cache.set("test", "brave", results);
-        assert!(cache.get("test", Some("brave")).is_none());
+        cache.set("test", Some("brave"), &options(), "brave", results);
+        assert!(cache.get("test", Some("brave"), &options()).is_none());
     }
 
     #[test]
@@ -210,33 +245,75 @@ mod tests {
 
     #[test]
     fn test_cache_key_generation() {
-        // Test with provider
-        let key1 = SearchCache::cache_key("Test Query", Some("brave"));
-        assert_eq!(key1, "brave:test query");
+        let key = |query, provider, options: &SearchOptions| {
+            SearchCache::cache_key(query, provider, options)
+        };
+        let brave = key("Test Query", Some("brave"), &options());
+
+        // Test case insensitivity
+        assert_eq!(brave, key("TEST QUERY", Some("brave"), &options()));
+        assert!(brave.starts_with("brave:") && brave.ends_with(":test query"));
 
         // Test without provider
-        let key2 = SearchCache::cache_key("Test Query", None);
-        assert_eq!(key2, "test query");
+        assert!(key("Test Query", None, &options()).starts_with("*:"));
+
+        // Options that change the results change the key; the timeout doesn't
+        let fewer = options().with_num_results(3);
+        assert_ne!(brave, key("Test Query", Some("brave"), &fewer));
+        let domains = options().with_include_domains(Some(vec!["rust-lang.org".to_string()]));
+        assert_ne!(brave, key("Test Query", Some("brave"), &domains));
+        let slower = options().with_timeout(Duration::from_secs(99));
+        assert_eq!(brave, key("Test Query", Some("brave"), &slower));
+    }
 
-        // Test case insensitivity
-        let key3 = SearchCache::cache_key("TEST QUERY", Some("brave"));
-        assert_eq!(key3, "brave:test query");
+    #[test]
+    fn test_cache_fallback_searches() {
+        let cache = SearchCache::new(test_config());
+
+        // A search without --provider is stored under no provider, but
+        // remembers which one answered
+        cache.set(
+            "query",
+            None,
+            &options(),
+            "brave",
+            vec![create_test_result("Result")],
+        );
+        let (results, provider) = cache.get("query", None, &options()).unwrap();
+        assert_eq!(results[0].title, "Result");
+        assert_eq!(provider, "brave");
+        assert!(cache.get("query", Some("brave"), &options()).is_none());
+        assert!(cache
+            .get("query", None, &options().with_num_results(3))
+            .is_none());
     }
 
     #[test]
     fn test_cache_clear() {
         let cache = SearchCache::new(test_config());
 
-        cache.set("query1", "brave", vec![create_test_result("Result1")]);
-        cache.set("query2", "google", vec![create_test_result("Result2")]);
+        cache.set(
+            "query1",
+            Some("brave"),
+            &options(),
+            "brave",
+            vec![create_test_result("Result1")],
+        );
+        cache.set(
+            "query2",
+            Some("google"),
+            &options(),
+            "google",
+            vec![create_test_result("Result2")],
+        );
 
         assert_eq!(cache.stats().entries, 2);
 
         cache.clear().unwrap();
 
         assert_eq!(cache.stats().entries, 0);
-        assert!(cache.get("query1", Some("brave")).is_none());
-        assert!(cache.get("query2", Some("google")).is_none());
+        assert!(cache.get("query1", Some("brave"), &options()).is_none());
+        assert!(cache.get("query2", Some("google"), &options()).is_none());
     }
 
     #[test]
@@ -245,17 +322,21 @@ mod tests {
 
         cache.set(
             "same query",
+            Some("brave"),
+            &options(),
             "brave",
             vec![create_test_result("Brave Result")],
         );
         cache.set(
             "same query",
+            Some("google"),
+            &options(),
             "google",
             vec![create_test_result("Google Result")],
         );
 
-        let brave_cached = cache.get("same query", Some("brave"));
-        let google_cached = cache.get("same query", Some("google"));
+        let brave_cached = cache.get("same query", Some("brave"), &options());
+        let google_cached = cache.get("same query", Some("google"), &options());
 
         assert!(brave_cached.is_some());
         assert!(google_cached.is_some());
@@ -268,13 +349,19 @@ mod tests {
     fn test_cache_case_insensitive_query() {
         let cache = SearchCache::new(test_config());
 
-        cache.set("Test Query", "brave", vec![create_test_result("Result")]);
+        cache.set(
+            "Test Query",
+            Some("brave"),
+            &options(),
+            "brave",
+            vec![create_test_result("Result")],
+        );
 
         // Should find with different case
-        let cached = cache.get("test query", Some("brave"));
+        let cached = cache.get("test query", Some("brave"), &options());
         assert!(cached.is_some());
 
-        let cached = cache.get("TEST QUERY", Some("brave"));
+        let cached = cache.get("TEST QUERY", Some("brave"), &options());
         assert!(cached.is_some());
     }
 
@@ -282,10 +369,16 @@ mod tests {
     fn test_cache_miss_wrong_provider() {
         let cache = SearchCache::new(test_config());
 
-        cache.set("query", "brave", vec![create_test_result("Result")]);
+        cache.set(
+            "query",
+            Some("brave"),
+            &options(),
+            "brave",
+            vec![create_test_result("Result")],
+        );
 
         // Should miss with different provider
-        let cached = cache.get("query", Some("google"));
+        let cached = cache.get("query", Some("google"), &options());
         assert!(cached.is_none());
     }
 
@@ -295,10 +388,22 @@ mod tests {
 
         assert_eq!(cache.stats().entries, 0);
 
-        cache.set("query1", "brave", vec![create_test_result("Result1")]);
+        cache.set(
+            "query1",
+            Some("brave"),
+            &options(),
+            "brave",
+            vec![create_test_result("Result1")],
+        );
         assert_eq!(cache.stats().entries, 1);
 
-        cache.set("query2", "brave", vec![create_test_result("Result2")]);
+        cache.set(
+            "query2",
+            Some("brave"),
+            &options(),
+            "brave",
+            vec![create_test_result("Result2")],
+        );
         assert_eq!(cache.stats().entries, 2);
 
         cache.clear().unwrap();
@@ -311,14 +416,38 @@ mod tests {
         config.max_entries = 3;
         let cache = SearchCache::new(config);
 
-        cache.set("query1", "brave", vec![create_test_result("Result1")]);
-        cache.set("query2", "brave", vec![create_test_result("Result2")]);
-        cache.set("query3", "brave", vec![create_test_result("Result3")]);
+        cache.set(
+            "query1",
+            Some("brave"),
+            &options(),
+            "brave",
+            vec![create_test_result("Result1")],
+        );
+        cache.set(
+            "query2",
+            Some("brave"),
+            &options(),
+            "brave",
+            vec![create_test_result("Result2")],
+        );
+        cache.set(
+            "query3",
+            Some("brave"),
+            &options(),
+            "brave",
+            vec![create_test_result("Result3")],
+        );
 
         assert_eq!(cache.stats().entries, 3);
 
         // Adding a 4th entry should trigger eviction
-        cache.set("query4", "brave", vec![create_test_result("Result4")]);
+        cache.set(
+            "query4",
+            Some("brave"),
+            &options(),
+            "brave",
+            vec![create_test_result("Result4")],
+        );
 
         // Should have at most max_entries
         assert!(cache.stats().entries <= 3);
@@ -327,7 +456,13 @@ mod tests {
     #[test]
     fn test_cache_stats_display() {
         let cache = SearchCache::new(test_config());
-        cache.set("query", "brave", vec![create_test_result("Result")]);
+        cache.set(
+            "query",
+            Some("brave"),
+            &options(),
+            "brave",
+            vec![create_test_result("Result")],
+        );
 
         let stats = cache.stats();
         let display = format!("{}", stats);
@@ -348,9 +483,9 @@ mod tests {
             create_test_result("Result3"),
         ];
 
-        cache.set("query", "brave", results);
+        cache.set("query", Some("brave"), &options(), "brave", results);
 
-        let cached = cache.get("query", Some("brave")).unwrap();
+        let cached = cache.get("query", Some("brave"), &options()).unwrap();
         assert_eq!(cached.0.len(), 3);
         assert_eq!(cached.0[0].title, "Result1");
         assert_eq!(cached.0[1].title, "Result2");
@@ -1,6 +1,7 @@
 //! CLI argument parsing for cli-web-search
 
 use clap::{Args, Parser, Subcommand, ValueEnum};
+use serde::{Deserialize, Serialize};
 
 /// A cross-platform CLI web search tool for AI agents
 #[derive(Parser, Debug)]
@@ -14,13 +15,13 @@ pub struct Cli {
     #[arg(short, long, value_enum)]
     pub provider: Option<Provider>,
 
-    /// Output format
-    #[arg(short, long, value_enum, default_value = "text")]
-    pub format: OutputFormat,
+    /// Output format [default: defaults.format from config, else text]
+    #[arg(short, long, value_enum)]
+    pub format: Option<OutputFormat>,
 
-    /// Number of results to return
-    #[arg(short, long, default_value = "10")]
-    pub num_results: usize,
+    /// Number of results to return [default: defaults.num_results, else 10]
+    #[arg(short, long)]
+    pub num_results: Option<usize>,
 
     /// Write output to file
     #[arg(short, long)]
@@ -38,17 +39,21 @@ pub struct Cli {
     #[arg(long, value_delimiter = ',')]
     pub exclude_domains: Option<Vec<String>>,
 
-    /// Safe search level
-    #[arg(long, value_enum, default_value = "moderate")]
-    pub safe_search: SafeSearch,
+    /// Safe search level [default: defaults.safe_search, else moderate]
+    #[arg(long, value_enum)]
+    pub safe_search: Option<SafeSearch>,
 
     /// Bypass result cache
     #[arg(long)]
     pub no_cache: bool,
 
-    /// Request timeout in seconds
-    #[arg(long, default_value = "30")]
-    pub timeout: u64,
+    /// Request timeout in seconds [default: defaults.timeout, else 30]
+    #[arg(long)]
+    pub timeout: Option<u64>,
+
+    /// Print the resolved search options and where each came from, then exit
+    #[arg(long)]
+    pub show_effective_options: bool,
 
     /// Verbose output (-v, -vv, -vvv)
     #[arg(short, long, action = clap::ArgAction::Count)]
@@ -58,6 +63,19 @@ pub struct Cli {
     #[arg(short, long)]
     pub quiet: bool,
 
+    /// Config file to use instead of the user config file
+    #[arg(
+        long,
+        global = true,
+        value_name = "PATH",
+        env = "CLI_WEB_SEARCH_CONFIG"
+    )]
+    pub config: Option<std::path::PathBuf>,
+
+    /// Configuration profile to use
+    #[arg(long, global = true, env = "CLI_WEB_SEARCH_PROFILE")]
+    pub profile: Option<String>,
+
     /// Subcommand to run
     #[command(subcommand)]
     pub command: Option<Commands>,
@@ -70,7 +88,7 @@ pub enum Commands {
     Config(ConfigArgs),
 
     /// List available providers and their status
-    Providers,
+    Providers(ProvidersArgs),
 
     /// Manage result cache
     Cache(CacheArgs),
@@ -78,9 +96,12 @@ pub enum Commands {
     /// Fetch a web page by URL
     Fetch(FetchArgs),
 
+    /// Print the JSON Schema for the config file or a JSON output
+    Schema(SchemaArgs),
+
     /// Start MCP (Model Context Protocol) server mode
     #[cfg(feature = "mcp")]
-    Serve,
+    Serve(ServeArgs),
 }
 
 /// Configuration subcommand arguments
@@ -94,32 +115,182 @@ pub struct ConfigArgs {
 #[derive(Subcommand, Debug)]
 pub enum ConfigCommands {
     /// Interactive configuration setup
-    Init,
+    Init(InitArgs),
 
     /// Set a configuration value
     Set {
         /// Configuration key (e.g., "providers.brave.api_key")
         key: String,
-        /// Value to set
+        /// Value to set (comma-separated for lists, e.g. "brave,tavily")
         value: String,
     },
 
-    /// Get a configuration value
+    /// Get a configuration value or section
     Get {
         /// Configuration key
         key: String,
     },
 
+    /// Remove a configuration value, or a whole provider section
+    Unset {
+        /// Configuration key (e.g., "providers.brave")
+        key: String,
+    },
+
     /// List all configuration
     List,
 
     /// Validate API keys
     Validate,
 
+    /// Check config files for mistakes, permissions and writable directories
+    Doctor,
+
+    /// Upgrade config files to the current format (keeping backups)
+    Migrate {
+        /// Show what would change without writing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+
     /// Show configuration file path
     Path,
 }
 
+/// Arguments for `config init`
+///
+/// Any flag given here pre-answers the matching wizard prompt. Without a
+/// terminal, or with `--non-interactive`, only the flags are applied.
+#[derive(Args, Debug, Default)]
+pub struct InitArgs {
+    /// Don't prompt; configure from flags only
+    #[arg(long)]
+    pub non_interactive: bool,
+
+    /// API key for a provider as PROVIDER=KEY (repeatable)
+    #[arg(long = "api-key", value_name = "PROVIDER=KEY", value_parser = parse_api_key)]
+    pub api_keys: Vec<(String, String)>,
+
+    /// Google Custom Search Engine ID
+    #[arg(long)]
+    pub google_cx: Option<String>,
+
+    /// Enable DuckDuckGo (no API key needed)
+    #[arg(long)]
+    pub duckduckgo: bool,
+
+    /// Provider fallback order (comma-separated)
+    #[arg(long, value_delimiter = ',')]
+    pub fallback_order: Option<Vec<String>>,
+
+    /// Default provider
+    #[arg(long)]
+    pub default_provider: Option<String>,
+
+    /// Default number of results
+    #[arg(long)]
+    pub num_results: Option<usize>,
+
+    /// Default safe search level
+    #[arg(long, value_enum)]
+    pub safe_search: Option<SafeSearch>,
+
+    /// Default request timeout in seconds
+    #[arg(long)]
+    pub timeout: Option<u64>,
+
+    /// Don't check API keys against the provider APIs
+    #[arg(long)]
+    pub skip_validation: bool,
+
+    /// Write the configuration without asking for confirmation
+    #[arg(short, long)]
+    pub yes: bool,
+}
+
+/// Parse a "provider=key" pair
+fn parse_api_key(s: &str) -> Result<(String, String), String> {
+    let (provider, key) = s
+        .split_once('=')
+        .ok_or_else(|| format!("invalid API key '{}': expected PROVIDER=KEY", s))?;
+    if provider.trim().is_empty() || key.trim().is_empty() {
+        return Err(format!("invalid API key '{}': expected PROVIDER=KEY", s));
+    }
+    Ok((provider.trim().to_lowercase(), key.trim().to_string()))
+}
+
+/// Providers subcommand arguments
+#[derive(Args, Debug)]
+pub struct ProvidersArgs {
+    /// Show request counts and rate limits per provider
+    #[arg(long)]
+    pub usage: bool,
+}
+
+/// Schema subcommand arguments
+#[derive(Args, Debug)]
+pub struct SchemaArgs {
+    /// Which document to describe
+    #[arg(value_enum)]
+    pub kind: SchemaKind,
+}
+
+/// Serve subcommand arguments
+#[cfg(feature = "mcp")]
+#[derive(Args, Debug)]
+pub struct ServeArgs {
+    /// Most tool calls to run at once; further calls wait for a free slot
+    #[arg(
+        long,
+        default_value_t = crate::mcp::DEFAULT_MAX_IN_FLIGHT as u64,
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    pub max_in_flight: u64,
+
+    /// How clients connect
+    #[arg(long, value_enum, default_value_t = Transport::Stdio)]
+    pub transport: Transport,
+
+    /// Address to listen on (http transport)
+    #[arg(long, value_name = "ADDR", default_value = "127.0.0.1:8808")]
+    pub bind: std::net::SocketAddr,
+
+    /// Require `Authorization: Bearer TOKEN` on every request (http transport)
+    #[arg(long, value_name = "TOKEN", env = "CLI_WEB_SEARCH_MCP_TOKEN")]
+    pub auth_token: Option<String>,
+
+    /// Also accept requests from this browser origin, e.g.
+    /// https://app.example.com (http transport; repeatable)
+    #[arg(long = "allow-origin", value_name = "ORIGIN")]
+    pub allowed_origins: Vec<String>,
+}
+
+/// MCP transports
+#[cfg(feature = "mcp")]
+#[derive(ValueEnum, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Transport {
+    /// JSON-RPC messages on stdin and stdout
+    #[default]
+    Stdio,
+    /// Streamable HTTP (POST requests, SSE replies) on --bind
+    Http,
+}
+
+/// Documents `schema` can describe
+#[derive(ValueEnum, Clone, Debug, PartialEq, Eq)]
+pub enum SchemaKind {
+    /// config.yaml (and project config files)
+    Config,
+    /// `--format json` search output
+    SearchResponse,
+    /// One entry of a search response's `results`
+    SearchResult,
+    /// `fetch --json --stdout` output
+    FetchResponse,
+    /// JSON error output
+    ErrorResponse,
+}
+
 /// Cache subcommand arguments
 #[derive(Args, Debug)]
 pub struct CacheArgs {
@@ -130,8 +301,16 @@ pub struct CacheArgs {
 /// Cache subcommands
 #[derive(Subcommand, Debug)]
 pub enum CacheCommands {
-    /// Clear the cache
-    Clear,
+    /// Clear the cache (both search and fetch entries unless one is chosen)
+    Clear {
+        /// Clear only cached search results
+        #[arg(long, conflicts_with = "fetch")]
+        search: bool,
+
+        /// Clear only cached fetched pages
+        #[arg(long)]
+        fetch: bool,
+    },
 
     /// Show cache statistics
     Stats,
@@ -167,13 +346,48 @@ pub struct FetchArgs {
     #[arg(long)]
     pub stdout: bool,
 
+    /// Bypass the fetch cache and download the page again
+    #[arg(long)]
+    pub refresh: bool,
+
+    /// Add a request header ("Name: value"), can be repeated
+    #[arg(short = 'H', long = "header", value_parser = parse_header)]
+    pub headers: Vec<(String, String)>,
+
+    /// Send cookies from a Netscape-format cookie file
+    #[arg(long)]
+    pub cookie_file: Option<String>,
+
+    /// Override the User-Agent header
+    #[arg(long)]
+    pub user_agent: Option<String>,
+
+    /// Do not follow HTTP redirects
+    #[arg(long)]
+    pub no_redirects: bool,
+
     /// Suppress non-essential output
     #[arg(short, long)]
     pub quiet: bool,
 }
 
+/// Parse a "Name: value" request header
+fn parse_header(s: &str) -> Result<(String, String), String> {
+    let (name, value) = s
+        .split_once(':')
+        .ok_or_else(|| format!("invalid header '{}': expected 'Name: value'", s))?;
+
+    let name = name.trim();
+    if name.is_empty() || name.contains(char::is_whitespace) {
+        return Err(format!("invalid header name '{}'", name));
+    }
+
+    Ok((name.to_string(), value.trim().to_string()))
+}
+
 /// Fetch output format options
-#[derive(ValueEnum, Clone, Debug, Default, PartialEq, Eq)]
+#[derive(ValueEnum, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum FetchFormat {
     /// Plain text (HTML tags stripped)
     #[default]
@@ -181,6 +395,7 @@ pub enum FetchFormat {
     /// Raw HTML content
     Html,
     /// Markdown format
+    #[serde(alias = "md")]
     Markdown,
 }
 
@@ -223,7 +438,8 @@ impl std::fmt::Display for Provider {
 }
 
 /// Output format options
-#[derive(ValueEnum, Clone, Debug, Default, PartialEq, Eq)]
+#[derive(ValueEnum, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum OutputFormat {
     /// JSON output for programmatic consumption
     Json,
@@ -235,7 +451,8 @@ pub enum OutputFormat {
 }
 
 /// Date range filter options
-#[derive(ValueEnum, Clone, Debug, PartialEq, Eq)]
+#[derive(ValueEnum, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum DateRange {
     /// Past 24 hours
     Day,
@@ -248,7 +465,8 @@ pub enum DateRange {
 }
 
 /// Safe search levels
-#[derive(ValueEnum, Clone, Debug, Default, PartialEq, Eq)]
+#[derive(ValueEnum, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum SafeSearch {
     /// No filtering
     Off,
@@ -274,8 +492,9 @@ mod tests {
     fn test_cli_parse_simple_query() {
         let cli = Cli::parse_from(["cli-web-search", "rust programming"]);
         assert_eq!(cli.query, Some("rust programming".to_string()));
-        assert_eq!(cli.format, OutputFormat::Text);
-        assert_eq!(cli.num_results, 10);
+        // Unset so config defaults can apply
+        assert_eq!(cli.format, None);
+        assert_eq!(cli.num_results, None);
     }
 
     #[test]
@@ -291,8 +510,8 @@ mod tests {
             "test query",
         ]);
         assert_eq!(cli.provider, Some(Provider::Brave));
-        assert_eq!(cli.format, OutputFormat::Json);
-        assert_eq!(cli.num_results, 5);
+        assert_eq!(cli.format, Some(OutputFormat::Json));
+        assert_eq!(cli.num_results, Some(5));
     }
 
     #[test]
@@ -327,13 +546,13 @@ mod tests {
     #[test]
     fn test_cli_parse_output_formats() {
         let cli_json = Cli::parse_from(["cli-web-search", "-f", "json", "query"]);
-        assert_eq!(cli_json.format, OutputFormat::Json);
+        assert_eq!(cli_json.format, Some(OutputFormat::Json));
 
         let cli_md = Cli::parse_from(["cli-web-search", "-f", "markdown", "query"]);
-        assert_eq!(cli_md.format, OutputFormat::Markdown);
+        assert_eq!(cli_md.format, Some(OutputFormat::Markdown));
 
         let cli_text = Cli::parse_from(["cli-web-search", "-f", "text", "query"]);
-        assert_eq!(cli_text.format, OutputFormat::Text);
+        assert_eq!(cli_text.format, Some(OutputFormat::Text));
     }
 
     #[test]
@@ -357,7 +576,7 @@ mod tests {
             ("strict", SafeSearch::Strict),
         ] {
             let cli = Cli::parse_from(["cli-web-search", "--safe-search", flag, "query"]);
-            assert_eq!(cli.safe_search, expected);
+            assert_eq!(cli.safe_search, Some(expected));
         }
     }
 
@@ -389,7 +608,7 @@ mod tests {
     #[test]
     fn test_cli_parse_timeout() {
         let cli = Cli::parse_from(["cli-web-search", "--timeout", "60", "query"]);
-        assert_eq!(cli.timeout, 60);
+        assert_eq!(cli.timeout, Some(60));
     }
 
     #[test]
@@ -415,11 +634,57 @@ mod tests {
         assert!(matches!(
             cli.command,
             Some(Commands::Config(ConfigArgs {
-                command: ConfigCommands::Init
+                command: ConfigCommands::Init(InitArgs {
+                    non_interactive: false,
+                    ..
+                })
             }))
         ));
     }
 
+    #[test]
+    fn test_cli_parse_config_init_flags() {
+        let cli = Cli::parse_from([
+            "cli-web-search",
+            "config",
+            "init",
+            "--non-interactive",
+            "--api-key",
+            "brave=BSA123",
+            "--api-key",
+            "Tavily=tvly-456",
+            "--fallback-order",
+            "brave,tavily",
+            "--safe-search",
+            "strict",
+        ]);
+        match cli.command {
+            Some(Commands::Config(ConfigArgs {
+                command: ConfigCommands::Init(args),
+            })) => {
+                assert!(args.non_interactive);
+                assert_eq!(
+                    args.api_keys,
+                    vec![
+                        ("brave".to_string(), "BSA123".to_string()),
+                        ("tavily".to_string(), "tvly-456".to_string()),
+                    ]
+                );
+                assert_eq!(
+                    args.fallback_order,
+                    Some(vec!["brave".to_string(), "tavily".to_string()])
+                );
+                assert_eq!(args.safe_search, Some(SafeSearch::Strict));
+            }
+            _ => panic!("Expected config init"),
+        }
+
+        assert!(
+            Cli::try_parse_from(["cli-web-search", "config", "init", "--api-key", "brave"])
+                .is_err()
+        );
+    }
+
     #[test]
     fn test_cli_parse_config_set() {
         let cli = Cli::parse_from([
@@ -453,6 +718,35 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_cli_parse_config_unset() {
+        let cli = Cli::parse_from(["cli-web-search", "config", "unset", "providers.brave"]);
+        match cli.command {
+            Some(Commands::Config(ConfigArgs {
+                command: ConfigCommands::Unset { key },
+            })) => {
+                assert_eq!(key, "providers.brave");
+            }
+            _ => panic!("Expected Config Unset command"),
+        }
+    }
+
+    #[test]
+    fn test_cli_parse_profile() {
+        let cli = Cli::parse_from(["cli-web-search", "--profile", "ci", "rust"]);
+        assert_eq!(cli.profile.as_deref(), Some("ci"));
+
+        // Global, so it can follow a subcommand
+        let cli = Cli::parse_from(["cli-web-search", "config", "list", "--profile", "team"]);
+        assert_eq!(cli.profile.as_deref(), Some("team"));
+    }
+
+    #[test]
+    fn test_cli_parse_config_file() {
+        let cli = Cli::parse_from(["cli-web-search", "config", "path", "--config", "ci.yaml"]);
+        assert_eq!(cli.config, Some(std::path::PathBuf::from("ci.yaml")));
+    }
+
     #[test]
     fn test_cli_parse_config_list() {
         let cli = Cli::parse_from(["cli-web-search", "config", "list"]);
@@ -475,10 +769,56 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_cli_parse_config_doctor() {
+        let cli = Cli::parse_from(["cli-web-search", "config", "doctor"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Config(ConfigArgs {
+                command: ConfigCommands::Doctor
+            }))
+        ));
+    }
+
+    #[test]
+    fn test_cli_parse_config_migrate() {
+        let cli = Cli::parse_from(["cli-web-search", "config", "migrate", "--dry-run"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Config(ConfigArgs {
+                command: ConfigCommands::Migrate { dry_run: true }
+            }))
+        ));
+    }
+
+    #[test]
+    fn test_cli_parse_schema_command() {
+        let cli = Cli::parse_from(["cli-web-search", "schema", "search-response"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Schema(SchemaArgs {
+                kind: SchemaKind::SearchResponse
+            }))
+        ));
+        assert!(Cli::try_parse_from(["cli-web-search", "schema"]).is_err());
+    }
+
     #[test]
     fn test_cli_parse_providers_command() {
         let cli = Cli::parse_from(["cli-web-search", "providers"]);
-        assert!(matches!(cli.command, Some(Commands::Providers)));
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Providers(ProvidersArgs { usage: false }))
+        ));
+    }
+
+    #[test]
+    fn test_cli_parse_providers_usage() {
+        let cli = Cli::parse_from(["cli-web-search", "providers", "--usage"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Providers(ProvidersArgs { usage: true }))
+        ));
     }
 
     #[test]
@@ -487,9 +827,29 @@ mod tests {
         assert!(matches!(
             cli.command,
             Some(Commands::Cache(CacheArgs {
-                command: CacheCommands::Clear
+                command: CacheCommands::Clear {
+                    search: false,
+                    fetch: false
+                }
+            }))
+        ));
+    }
+
+    #[test]
+    fn test_cli_parse_cache_clear_selection() {
+        let cli = Cli::parse_from(["cli-web-search", "cache", "clear", "--fetch"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Cache(CacheArgs {
+                command: CacheCommands::Clear {
+                    search: false,
+                    fetch: true
+                }
             }))
         ));
+
+        let both = Cli::try_parse_from(["cli-web-search", "cache", "clear", "--search", "--fetch"]);
+        assert!(both.is_err());
     }
 
     #[test]
@@ -551,6 +911,7 @@ mod tests {
                 assert!(!args.json);
                 assert!(!args.stdout);
                 assert!(!args.quiet);
+                assert!(!args.refresh);
                 assert!(args.output.is_none());
             }
             _ => panic!("Expected Fetch command"),
@@ -606,6 +967,7 @@ mod tests {
             "--json",
             "--stdout",
             "--quiet",
+            "--refresh",
             "https://example.com",
         ]);
         match cli.command {
@@ -613,11 +975,61 @@ mod tests {
                 assert!(args.json);
                 assert!(args.stdout);
                 assert!(args.quiet);
+                assert!(args.refresh);
+            }
+            _ => panic!("Expected Fetch command"),
+        }
+    }
+
+    #[test]
+    fn test_cli_parse_fetch_request_options() {
+        let cli = Cli::parse_from([
+            "cli-web-search",
+            "fetch",
+            "-H",
+            "Authorization: Bearer token",
+            "--header",
+            "X-Custom:value",
+            "--cookie-file",
+            "cookies.txt",
+            "--user-agent",
+            "my-agent/1.0",
+            "--no-redirects",
+            "https://example.com",
+        ]);
+        match cli.command {
+            Some(Commands::Fetch(args)) => {
+                assert_eq!(
+                    args.headers,
+                    vec![
+                        ("Authorization".to_string(), "Bearer token".to_string()),
+                        ("X-Custom".to_string(), "value".to_string()),
+                    ]
+                );
+                assert_eq!(args.cookie_file, Some("cookies.txt".to_string()));
+                assert_eq!(args.user_agent, Some("my-agent/1.0".to_string()));
+                assert!(args.no_redirects);
             }
             _ => panic!("Expected Fetch command"),
         }
     }
 
+    #[test]
+    fn test_cli_parse_fetch_invalid_header() {
+        let result =
+            Cli::try_parse_from(["cli-web-search", "fetch", "-H", "NoColon", "https://x.com"]);
+        assert!(result.is_err());
+
+        let result = Cli::try_parse_from([
+            "cli-web-search",
+            "fetch",
+            "-H",
+            "Bad Name: v",
+            "https://x.com",
+        ]);
+        assert!(result.is_err());
+    }
+
     #[test]
     fn test_fetch_format_default() {
         let format = FetchFormat::default();
@@ -628,6 +1040,32 @@ mod tests {
     #[test]
     fn test_cli_parse_serve_command() {
         let cli = Cli::parse_from(["cli-web-search", "serve"]);
-        assert!(matches!(cli.command, Some(Commands::Serve)));
+        let Some(Commands::Serve(args)) = cli.command else {
+            panic!("expected serve");
+        };
+        assert_eq!(args.max_in_flight, 8);
+        assert_eq!(args.transport, Transport::Stdio);
+        assert_eq!(args.bind.to_string(), "127.0.0.1:8808");
+
+        let cli = Cli::parse_from([
+            "cli-web-search",
+            "serve",
+            "--max-in-flight",
+            "2",
+            "--transport",
+            "http",
+            "--bind",
+            "0.0.0.0:9000",
+            "--allow-origin",
+            "https://app.example.com",
+        ]);
+        let Some(Commands::Serve(args)) = cli.command else {
+            panic!("expected serve");
+        };
+        assert_eq!(args.max_in_flight, 2);
+        assert_eq!(args.transport, Transport::Http);
+        assert_eq!(args.bind.port(), 9000);
+        assert_eq!(args.allowed_origins, ["https://app.example.com"]);
+        assert!(Cli::try_parse_from(["cli-web-search", "serve", "--max-in-flight", "0"]).is_err());
     }
 }
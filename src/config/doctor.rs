@@ -0,0 +1,250 @@
+//! `config doctor`: check every config file and the directories written to
+//!
+//! Unlike loading, which stops at the first file with errors, the doctor
+//! reads every file that takes part (including profile files that aren't
+//! selected) and reports warnings as well as errors.
+
+use super::validate::{self, Diagnostic, FileKind};
+use super::*;
+use crate::error::Result;
+use std::fs;
+use std::path::Path;
+
+/// Findings for one file, directory or the merged config
+#[derive(Debug, Clone)]
+pub struct DoctorSection {
+    pub title: String,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl DoctorSection {
+    fn new(title: impl Into<String>, diagnostics: Vec<Diagnostic>) -> Self {
+        Self {
+            title: title.into(),
+            diagnostics,
+        }
+    }
+}
+
+/// Check the config files, the merged config and the data directories
+pub fn run_doctor(options: &LoadOptions) -> Result<Vec<DoctorSection>> {
+    let user_path = options.user_config_path()?;
+    let mut sections = vec![check_config_file("user config", &user_path, true)];
+
+    for path in profiles::profile_files(&profile_dir(&user_path)) {
+        sections.push(check_file_at("profile", &path, FileKind::Profile, true));
+    }
+
+    if let Some(project) = find_project_config(&std::env::current_dir()?) {
+        if project != user_path {
+            // Project files are usually committed, so their mode is not ours
+            sections.push(check_file_at(
+                "project config",
+                &project,
+                FileKind::Config,
+                false,
+            ));
+        }
+    }
+
+    let has_errors = sections
+        .iter()
+        .any(|section| section.diagnostics.iter().any(Diagnostic::is_error));
+    sections.push(match has_errors {
+        true => DoctorSection::new(
+            "merged configuration (skipped until the errors above are fixed)",
+            Vec::new(),
+        ),
+        false => DoctorSection::new("merged configuration", check_merged(options)),
+    });
+
+    let directories = [
+        ("config directory", profile_dir(&user_path)),
+        ("data directory", data_dir()?),
+        ("cache directory", cache_dir()?),
+    ];
+    for (name, dir) in directories {
+        sections.push(DoctorSection::new(
+            format!("{} {}", name, dir.display()),
+            check_writable(&dir).into_iter().collect(),
+        ));
+    }
+
+    Ok(sections)
+}
+
+fn check_config_file(name: &str, path: &Path, private: bool) -> DoctorSection {
+    match path.exists() {
+        true => check_file_at(name, path, FileKind::Config, private),
+        false => DoctorSection::new(
+            format!("{} {} (not found)", name, path.display()),
+            Vec::new(),
+        ),
+    }
+}
+
+/// Check a file's contents and, for files that may hold keys, its mode
+fn check_file_at(name: &str, path: &Path, kind: FileKind, private: bool) -> DoctorSection {
+    let title = format!("{} {}", name, path.display());
+    let mut diagnostics = match fs::read_to_string(path) {
+        Ok(content) => {
+            let mut diagnostics = check_version(&content, path);
+            if !diagnostics.iter().any(Diagnostic::is_error) {
+                diagnostics.extend(validate::check_file(&content, kind));
+            }
+            diagnostics
+        }
+        Err(e) => vec![Diagnostic::error(&[], format!("cannot read file: {}", e))],
+    };
+    if private {
+        diagnostics.extend(check_permissions(path));
+    }
+    DoctorSection::new(title, diagnostics)
+}
+
+/// Older files still load (they are migrated in memory), but only `config
+/// migrate` brings them up to date
+///
+/// A file whose settings read the same in the current format, such as one
+/// that only lacks `version`, is not worth a warning.
+fn check_version(content: &str, path: &Path) -> Vec<Diagnostic> {
+    match migrate_content(content, &path.display().to_string()) {
+        Ok(Some(migrated)) if !migrated.version_only => vec![Diagnostic::warning(
+            &["version"],
+            format!(
+                "config version {} is older than {} (upgrade with `cli-web-search config \
+                 migrate`)",
+                migrated.from, CONFIG_VERSION
+            ),
+        )],
+        Ok(_) => Vec::new(),
+        Err(e) => vec![Diagnostic::error(&["version"], e.to_string())],
+    }
+}
+
+/// Check the merged config, saying which layer set each offending value
+fn check_merged(options: &LoadOptions) -> Vec<Diagnostic> {
+    match loader::merge_layers(options) {
+        Ok(layered) => validate::check_config(&layered.config)
+            .into_iter()
+            .map(|mut diagnostic| {
+                match layered.source_of(&diagnostic.key()) {
+                    layers::DEFAULT_SOURCE => {}
+                    source => {
+                        diagnostic.message = format!("{} (from {})", diagnostic.message, source)
+                    }
+                }
+                diagnostic
+            })
+            .collect(),
+        Err(e) => vec![Diagnostic::error(&[], e.to_string())],
+    }
+}
+
+/// Warn when a file that may hold API keys is readable by other users
+#[cfg(unix)]
+fn check_permissions(path: &Path) -> Option<Diagnostic> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode = fs::metadata(path).ok()?.permissions().mode() & 0o777;
+    (mode & 0o077 != 0).then(|| {
+        Diagnostic::warning(
+            &[],
+            format!(
+                "file mode is {:o}, so other users may be able to read API keys \
+                 (fix with `chmod 600 {}`)",
+                mode,
+                path.display()
+            ),
+        )
+    })
+}
+
+#[cfg(not(unix))]
+fn check_permissions(_path: &Path) -> Option<Diagnostic> {
+    None
+}
+
+/// Check that files can be created in `dir`
+///
+/// A directory that doesn't exist yet is created on first use, so its
+/// nearest existing parent is checked instead.
+fn check_writable(dir: &Path) -> Option<Diagnostic> {
+    let existing = dir.ancestors().find(|d| d.is_dir())?;
+    let probe = existing.join(format!(".cli-web-search-doctor-{}", std::process::id()));
+    match fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&probe)
+    {
+        Ok(_) => {
+            let _ = fs::remove_file(&probe);
+            None
+        }
+        Err(e) => Some(Diagnostic::error(
+            &[],
+            format!("{} is not writable: {}", existing.display(), e),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_doctor_reports_files() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("config.yaml");
+        fs::write(&path, "provders: {}\n").unwrap();
+        fs::create_dir(dir.path().join("profiles")).unwrap();
+        fs::write(
+            dir.path().join("profiles/ci.yaml"),
+            "fallback_order: [brvae]\n",
+        )
+        .unwrap();
+
+        let options = LoadOptions {
+            config_file: Some(path),
+            profile: None,
+        };
+        let sections = run_doctor(&options).unwrap();
+
+        let described: Vec<String> = sections[0]
+            .diagnostics
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert!(described
+            .contains(&"line 1: provders: unknown key (did you mean providers?)".to_string()));
+        // A missing `version` alone changes nothing about how the file reads
+        assert!(!described.iter().any(|d| d.starts_with("version:")));
+        assert!(sections[1].title.ends_with("ci.yaml"));
+        assert!(sections[1]
+            .diagnostics
+            .iter()
+            .any(|d| d.is_error() && d.key() == "fallback_order"));
+        assert!(sections
+            .iter()
+            .any(|s| s.title.starts_with("merged configuration (skipped")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_permissions_and_writability() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("config.yaml");
+        fs::write(&path, "").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        let warning = check_permissions(&path).unwrap();
+        assert!(warning.message.contains("file mode is 644"));
+
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+        assert_eq!(check_permissions(&path), None);
+
+        assert_eq!(check_writable(&dir.path().join("not/yet/created")), None);
+    }
+}
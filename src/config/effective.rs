@@ -0,0 +1,236 @@
+//! Search options resolved from command-line flags and config defaults
+//!
+//! A flag given on the command line always wins; otherwise the value comes
+//! from the config's `defaults` section (or `default_provider`), which may
+//! itself come from any config layer.
+
+use super::*;
+use crate::cli::{Cli, OutputFormat, SafeSearch};
+use crate::error::{Result, SearchError};
+use clap::ValueEnum;
+
+/// A resolved value and where it came from
+#[derive(Debug, Clone, PartialEq)]
+pub struct Resolved<T> {
+    pub value: T,
+    pub source: String,
+}
+
+/// The options a search actually runs with
+#[derive(Debug, Clone)]
+pub struct EffectiveOptions {
+    /// Provider tried first (`None` when no provider is enabled)
+    pub provider: Resolved<Option<String>>,
+    pub format: Resolved<OutputFormat>,
+    pub num_results: Resolved<usize>,
+    pub safe_search: Resolved<SafeSearch>,
+    pub timeout: Resolved<u64>,
+}
+
+impl EffectiveOptions {
+    /// Resolve each option from its flag, falling back to the config
+    pub fn resolve(cli: &Cli, layered: &LayeredConfig) -> Result<Self> {
+        let config = &layered.config;
+        let defaults = &config.defaults;
+        let configured = |key: &str| match layered.source_of(key) {
+            layers::DEFAULT_SOURCE => layers::DEFAULT_SOURCE.to_string(),
+            source => format!("{} from {}", key, source),
+        };
+
+        let provider = match cli.provider {
+            Some(ref provider) => flag("--provider", Some(provider.to_string())),
+            None => {
+                let provider = config.effective_default_provider();
+                let source = match provider {
+                    Some(ref p) if config.default_provider.as_ref() == Some(p) => {
+                        configured("default_provider")
+                    }
+                    Some(_) if !config.fallback_order.is_empty() => configured("fallback_order"),
+                    Some(_) => "first enabled provider".to_string(),
+                    None => "no provider enabled".to_string(),
+                };
+                Resolved {
+                    value: provider,
+                    source,
+                }
+            }
+        };
+
+        let format = match cli.format {
+            Some(ref format) => flag("--format", format.clone()),
+            None => Resolved {
+                value: parse_enum("defaults.format", &defaults.format)?,
+                source: configured("defaults.format"),
+            },
+        };
+
+        let safe_search = match cli.safe_search {
+            Some(ref level) => flag("--safe-search", level.clone()),
+            None => Resolved {
+                value: parse_enum("defaults.safe_search", &defaults.safe_search)?,
+                source: configured("defaults.safe_search"),
+            },
+        };
+
+        let num_results = match cli.num_results {
+            Some(n) => flag("--num-results", n),
+            None => Resolved {
+                value: defaults.num_results,
+                source: configured("defaults.num_results"),
+            },
+        };
+
+        let timeout = match cli.timeout {
+            Some(secs) => flag("--timeout", secs),
+            None => Resolved {
+                value: defaults.timeout,
+                source: configured("defaults.timeout"),
+            },
+        };
+
+        Ok(Self {
+            provider,
+            format,
+            num_results,
+            safe_search,
+            timeout,
+        })
+    }
+
+    /// `name: value  (source)` lines for `--show-effective-options`
+    pub fn describe(&self) -> Vec<String> {
+        let provider = self.provider.value.as_deref().unwrap_or("(none)");
+        let format = self.format.value.to_possible_value();
+        let safe_search = self.safe_search.value.to_possible_value();
+        vec![
+            line("provider", provider, &self.provider.source),
+            line(
+                "format",
+                format.as_ref().map_or("", |v| v.get_name()),
+                &self.format.source,
+            ),
+            line(
+                "num_results",
+                &self.num_results.value.to_string(),
+                &self.num_results.source,
+            ),
+            line(
+                "safe_search",
+                safe_search.as_ref().map_or("", |v| v.get_name()),
+                &self.safe_search.source,
+            ),
+            line(
+                "timeout",
+                &format!("{}s", self.timeout.value),
+                &self.timeout.source,
+            ),
+        ]
+    }
+}
+
+fn flag<T>(name: &str, value: T) -> Resolved<T> {
+    Resolved {
+        value,
+        source: name.to_string(),
+    }
+}
+
+fn line(name: &str, value: &str, source: &str) -> String {
+    format!("{}: {}  ({})", name, value, source)
+}
+
+/// Parse a config string as one of a flag's values
+fn parse_enum<T: ValueEnum>(key: &str, value: &str) -> Result<T> {
+    T::from_str(value, true).map_err(|_| {
+        SearchError::Config(format!(
+            "Invalid {} '{}' (expected one of: {})",
+            key,
+            value,
+            expected_values::<T>().join(", ")
+        ))
+    })
+}
+
+/// The values a flag accepts, as written on the command line
+pub(super) fn expected_values<T: ValueEnum>() -> Vec<String> {
+    T::value_variants()
+        .iter()
+        .filter_map(|v| v.to_possible_value())
+        .map(|v| v.get_name().to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    fn layered(yaml: &str) -> LayeredConfig {
+        let value = serde_yaml::from_str(yaml).unwrap();
+        LayeredConfig::merge(vec![layers::Layer::new("config.yaml", value)]).unwrap()
+    }
+
+    const CONFIG: &str = r#"
+default_provider: tavily
+providers:
+  brave: {api_key: b}
+  tavily: {api_key: t}
+defaults:
+  num_results: 25
+  format: json
+  safe_search: strict
+"#;
+
+    #[test]
+    fn test_config_defaults_apply_when_flags_are_unset() {
+        let cli = Cli::parse_from(["cli-web-search", "rust"]);
+        let options = EffectiveOptions::resolve(&cli, &layered(CONFIG)).unwrap();
+
+        assert_eq!(options.provider.value.as_deref(), Some("tavily"));
+        assert_eq!(options.provider.source, "default_provider from config.yaml");
+        assert_eq!(options.format.value, OutputFormat::Json);
+        assert_eq!(options.num_results.value, 25);
+        assert_eq!(options.safe_search.value, SafeSearch::Strict);
+        assert_eq!(options.timeout.value, 30);
+        assert_eq!(options.timeout.source, "default");
+    }
+
+    #[test]
+    fn test_flags_win() {
+        let cli = Cli::parse_from([
+            "cli-web-search",
+            "-p",
+            "brave",
+            "-f",
+            "text",
+            "-n",
+            "3",
+            "--timeout",
+            "5",
+            "rust",
+        ]);
+        let options = EffectiveOptions::resolve(&cli, &layered(CONFIG)).unwrap();
+
+        assert_eq!(options.provider.value.as_deref(), Some("brave"));
+        assert_eq!(options.format.value, OutputFormat::Text);
+        assert_eq!(options.format.source, "--format");
+        assert_eq!(options.num_results.value, 3);
+        assert_eq!(options.timeout.value, 5);
+        // Unset flags still come from the config
+        assert_eq!(options.safe_search.value, SafeSearch::Strict);
+
+        let lines = options.describe();
+        assert!(lines.contains(&"num_results: 3  (--num-results)".to_string()));
+        assert!(lines.contains(&"timeout: 5s  (--timeout)".to_string()));
+    }
+
+    #[test]
+    fn test_invalid_config_value() {
+        let cli = Cli::parse_from(["cli-web-search", "rust"]);
+        let err =
+            EffectiveOptions::resolve(&cli, &layered("defaults:\n  format: yaml\n")).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Invalid defaults.format 'yaml' (expected one of: json, markdown, text)"));
+    }
+}
@@ -0,0 +1,454 @@
+//! Generic access to configuration values by dotted key path
+//!
+//! Keys address the serialized `Config` tree (`defaults.num_results`,
+//! `providers.brave.api_key`, `rate_limits.google.per_day`, ...). Values are
+//! written into the tree and the result is deserialized back into `Config`,
+//! so the struct definitions are the schema: type errors come from serde, and
+//! a key that doesn't survive the round trip is unknown.
+
+use super::*;
+use crate::error::{Result, SearchError};
+use serde_yaml::{Mapping, Value};
+
+/// Get a value for display, with secrets masked
+///
+/// Returns `None` for known keys that are not set. Sections are shown as
+/// their `key: value` lines.
+pub fn get_value(config: &Config, key: &str) -> Result<Option<String>> {
+    let flat = config.to_flat_map();
+    if let Some(value) = flat.get(key) {
+        return Ok(Some(value.clone()));
+    }
+
+    let prefix = format!("{}.", key);
+    let mut section: Vec<String> = flat
+        .iter()
+        .filter(|(k, _)| k.starts_with(&prefix))
+        .map(|(k, v)| format!("{}: {}", &k[prefix.len()..], v))
+        .collect();
+    if !section.is_empty() {
+        section.sort();
+        return Ok(Some(section.join("\n")));
+    }
+
+    check_known_key(key)?;
+    let tree = to_tree(config)?;
+    Ok(lookup(&tree, &split_key(key)?).and_then(display_value))
+}
+
+/// Set a value, parsing it to whatever type the key holds
+///
+/// Comma-separated values are accepted for lists
+/// (`fallback_order=brave,tavily`).
+pub fn set_value(config: &mut Config, key: &str, raw: &str) -> Result<()> {
+    let segments = split_key(key)?;
+    let template = to_tree(&template())?;
+    if lookup(&template, &segments).is_some_and(Value::is_mapping) {
+        return Err(SearchError::Config(format!(
+            "{} is a section; set one of its keys instead (see `cli-web-search config get {}`)",
+            key, key
+        )));
+    }
+    let tree = to_tree(config)?;
+
+    let mut first_error = None;
+    for candidate in candidates(raw) {
+        let mut attempt = tree.clone();
+        insert(&mut attempt, &segments, candidate.clone());
+        match from_tree(attempt) {
+            Ok(updated) => {
+                // Unknown keys are dropped by deserialization; known ones may
+                // also vanish when set to a value that isn't serialized
+                if lookup(&to_tree(&updated)?, &segments).is_none() {
+                    check_known_key(key)?;
+                }
+                *config = updated;
+                return Ok(());
+            }
+            Err(e) => {
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+
+    check_known_key(key)?;
+    Err(SearchError::Config(format!(
+        "Invalid value '{}' for {}: {}",
+        raw,
+        key,
+        first_error.map(|e| e.to_string()).unwrap_or_default()
+    )))
+}
+
+/// Remove a value, restoring its default
+///
+/// Unsetting a provider section (`providers.brave`) removes the provider and
+/// drops it from `fallback_order` and `default_provider`.
+pub fn unset_value(config: &mut Config, key: &str) -> Result<()> {
+    let segments = split_key(key)?;
+    check_known_key(key)?;
+
+    let mut tree = to_tree(config)?;
+    if !remove(&mut tree, &segments) {
+        return Err(SearchError::Config(format!("{} is not set", key)));
+    }
+    let mut updated =
+        from_tree(tree).map_err(|e| SearchError::Config(format!("Cannot unset {}: {}", key, e)))?;
+
+    if let ["providers", provider] = segments.as_slice() {
+        updated.fallback_order.retain(|p| p != provider);
+        if updated.default_provider.as_deref() == Some(*provider) {
+            updated.default_provider = None;
+        }
+    }
+
+    *config = updated;
+    Ok(())
+}
+
+fn split_key(key: &str) -> Result<Vec<&str>> {
+    let segments: Vec<&str> = key.split('.').collect();
+    if segments.iter().any(|s| s.is_empty()) {
+        return Err(SearchError::Config(format!(
+            "Invalid configuration key: '{}'",
+            key
+        )));
+    }
+    Ok(segments)
+}
+
+/// Ways to read a raw value, most specific first
+fn candidates(raw: &str) -> Vec<Value> {
+    let scalar = serde_yaml::from_str::<Value>(raw)
+        .ok()
+        .filter(|v| !v.is_mapping() && !v.is_null())
+        .unwrap_or_else(|| Value::String(raw.to_string()));
+
+    let list = Value::Sequence(
+        raw.split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(|item| {
+                serde_yaml::from_str::<Value>(item)
+                    .ok()
+                    .filter(|v| !v.is_mapping() && !v.is_sequence() && !v.is_null())
+                    .unwrap_or_else(|| Value::String(item.to_string()))
+            })
+            .collect(),
+    );
+
+    vec![scalar, list, Value::String(raw.to_string())]
+}
+
+/// Fail unless some value of some type can be stored at `key`
+fn check_known_key(key: &str) -> Result<()> {
+    match is_known_path(&split_key(key)?) {
+        true => Ok(()),
+        false => Err(unknown_key(key)),
+    }
+}
+
+/// Whether some value of some type can be stored at the path
+///
+/// Checked against a config with every provider section present, so keys
+/// inside sections that aren't configured yet are still recognized.
+pub(super) fn is_known_path(segments: &[&str]) -> bool {
+    let tree = to_tree(&template()).expect("template config serializes");
+    if lookup(&tree, segments).is_some() {
+        return true;
+    }
+
+    let probes = [
+        Value::String("x".to_string()),
+        Value::Number(1.into()),
+        Value::Bool(true),
+        Value::Sequence(vec![Value::String("x".to_string())]),
+        Value::Sequence(vec![Value::Number(1.into())]),
+        Value::Mapping(Mapping::new()),
+        // Maps keyed by user-chosen names are left out while empty
+        Value::Mapping(Mapping::from_iter([(
+            Value::String("x".to_string()),
+            Value::Mapping(Mapping::new()),
+        )])),
+        Value::Mapping(Mapping::from_iter([(
+            Value::String("x".to_string()),
+            Value::String("x".to_string()),
+        )])),
+    ];
+    probes.into_iter().any(|probe| {
+        let mut attempt = tree.clone();
+        insert(&mut attempt, segments, probe);
+        from_tree(attempt)
+            .ok()
+            .and_then(|config| to_tree(&config).ok())
+            .is_some_and(|t| lookup(&t, segments).is_some())
+    })
+}
+
+/// Keys of the fixed section at the path (`providers` gives the provider
+/// names); empty for sections keyed by user-chosen names
+pub(super) fn section_keys(segments: &[&str]) -> Vec<String> {
+    let tree = to_tree(&template()).expect("template config serializes");
+    lookup(&tree, segments)
+        .and_then(Value::as_mapping)
+        .map(|map| {
+            map.keys()
+                .filter_map(|k| k.as_str().map(String::from))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn unknown_key(key: &str) -> SearchError {
+    SearchError::Config(format!(
+        "Unknown configuration key: {} (see `cli-web-search config list`)",
+        key
+    ))
+}
+
+/// A config with every provider section filled in
+fn template() -> Config {
+    let mut config = Config::default();
+    let p = &mut config.providers;
+    p.brave = Some(BraveConfig {
+        api_key: "x".to_string(),
+        enabled: true,
+    });
+    p.google = Some(GoogleConfig {
+        api_key: "x".to_string(),
+        cx: "x".to_string(),
+        enabled: true,
+    });
+    p.duckduckgo = Some(DuckDuckGoConfig { enabled: true });
+    p.tavily = Some(TavilyConfig {
+        api_key: "x".to_string(),
+        enabled: true,
+    });
+    p.serper = Some(SerperConfig {
+        api_key: "x".to_string(),
+        enabled: true,
+    });
+    p.firecrawl = Some(FirecrawlConfig {
+        api_key: "x".to_string(),
+        enabled: true,
+    });
+    p.serpapi = Some(SerpApiConfig {
+        api_key: "x".to_string(),
+        enabled: true,
+    });
+    p.bing = Some(BingConfig {
+        api_key: "x".to_string(),
+        enabled: true,
+    });
+    config
+}
+
+fn to_tree(config: &Config) -> Result<Value> {
+    Ok(serde_yaml::to_value(config)?)
+}
+
+fn from_tree(tree: Value) -> std::result::Result<Config, serde_yaml::Error> {
+    serde_yaml::from_value(tree)
+}
+
+fn lookup<'a>(tree: &'a Value, segments: &[&str]) -> Option<&'a Value> {
+    segments
+        .iter()
+        .try_fold(tree, |node, segment| node.get(segment))
+}
+
+/// Store `value` at the path, creating mappings along the way
+fn insert(tree: &mut Value, segments: &[&str], value: Value) {
+    let mut node = tree;
+    for segment in &segments[..segments.len() - 1] {
+        if !node.is_mapping() {
+            *node = Value::Mapping(Mapping::new());
+        }
+        node = node
+            .as_mapping_mut()
+            .expect("node is a mapping")
+            .entry(Value::String(segment.to_string()))
+            .or_insert(Value::Null);
+    }
+    if !node.is_mapping() {
+        *node = Value::Mapping(Mapping::new());
+    }
+    if let Some(map) = node.as_mapping_mut() {
+        map.insert(
+            Value::String(segments[segments.len() - 1].to_string()),
+            value,
+        );
+    }
+}
+
+/// Remove the value at the path; returns whether anything was set there
+fn remove(tree: &mut Value, segments: &[&str]) -> bool {
+    let (last, parents) = segments.split_last().expect("key has segments");
+    let parent = parents
+        .iter()
+        .try_fold(tree, |node, segment| node.get_mut(*segment));
+    match parent.and_then(Value::as_mapping_mut) {
+        Some(map) => map.remove(*last).is_some_and(|v| !v.is_null()),
+        None => false,
+    }
+}
+
+/// Render a scalar or list of scalars; sections are handled by the flat map
+fn display_value(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::Bool(b) => Some(b.to_string()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::String(s) => Some(s.clone()),
+        Value::Sequence(items) if items.is_empty() => None,
+        Value::Sequence(items) => Some(
+            items
+                .iter()
+                .filter_map(display_value)
+                .collect::<Vec<_>>()
+                .join(", "),
+        ),
+        Value::Mapping(map) if map.is_empty() => None,
+        other => serde_yaml::to_string(other)
+            .ok()
+            .map(|s| s.trim_end().to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_typed_values() {
+        let mut config = Config::default();
+        set_value(&mut config, "defaults.num_results", "25").unwrap();
+        set_value(&mut config, "cache.enabled", "false").unwrap();
+        set_value(&mut config, "retry.jitter", "0.2").unwrap();
+        set_value(&mut config, "default_provider", "tavily").unwrap();
+
+        assert_eq!(config.defaults.num_results, 25);
+        assert!(!config.cache.enabled);
+        assert_eq!(config.retry.defaults.jitter, Some(0.2));
+        assert_eq!(config.default_provider.as_deref(), Some("tavily"));
+    }
+
+    #[test]
+    fn test_set_lists() {
+        let mut config = Config::default();
+        set_value(&mut config, "fallback_order", "brave, tavily").unwrap();
+        assert_eq!(config.fallback_order, vec!["brave", "tavily"]);
+
+        set_value(&mut config, "fetch.policy.allowed_ports", "443,8443").unwrap();
+        assert_eq!(config.fetch.policy.allowed_ports, vec![443, 8443]);
+
+        // A single item still becomes a list
+        set_value(&mut config, "retry.retry_on", "timeout").unwrap();
+        assert_eq!(
+            config.retry.defaults.retry_on,
+            Some(vec!["timeout".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_set_string_that_looks_like_another_type() {
+        let mut config = Config::default();
+        set_value(&mut config, "providers.serper.api_key", "12345").unwrap();
+        set_value(&mut config, "providers.tavily.api_key", "true").unwrap();
+        assert_eq!(config.providers.serper.unwrap().api_key, "12345");
+        assert_eq!(config.providers.tavily.unwrap().api_key, "true");
+    }
+
+    #[test]
+    fn test_set_creates_sections() {
+        let mut config = Config::default();
+        set_value(&mut config, "providers.google.api_key", "key").unwrap();
+        set_value(&mut config, "providers.google.cx", "engine").unwrap();
+        set_value(&mut config, "rate_limits.google.per_day", "100").unwrap();
+
+        let google = config.providers.google.clone().unwrap();
+        assert_eq!(google.api_key, "key");
+        assert_eq!(google.cx, "engine");
+        assert_eq!(config.rate_limits["google"].per_day, Some(100));
+
+        // A provider can't be enabled before it has a key
+        let err = set_value(&mut config, "providers.brave.enabled", "false").unwrap_err();
+        assert!(err.to_string().contains("api_key"));
+        assert!(config.providers.brave.is_none());
+    }
+
+    #[test]
+    fn test_set_rejects_bad_values() {
+        let mut config = Config::default();
+        let err = set_value(&mut config, "cache.enabled", "yes").unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Invalid value 'yes' for cache.enabled"));
+
+        assert!(set_value(&mut config, "defaults.num_results", "many").is_err());
+        assert!(set_value(&mut config, "fetch.policy.allowed_ports", "443,https").is_err());
+        assert!(config.cache.enabled);
+    }
+
+    #[test]
+    fn test_unknown_keys() {
+        let mut config = Config::default();
+        for key in ["provders.brave.api_key", "defaults.colour", "cache", "a..b"] {
+            let err = set_value(&mut config, key, "1").unwrap_err();
+            assert!(err.to_string().contains("key"), "{}: {}", key, err);
+        }
+        assert!(get_value(&config, "defaults.nope").is_err());
+
+        let err = set_value(&mut config, "cache", "1").unwrap_err();
+        assert!(err.to_string().contains("cache is a section"));
+    }
+
+    #[test]
+    fn test_get_values() {
+        let mut config = Config::default();
+        set_value(
+            &mut config,
+            "providers.brave.api_key",
+            "BSA-secret-key-1234",
+        )
+        .unwrap();
+        set_value(&mut config, "fallback_order", "brave,duckduckgo").unwrap();
+
+        assert_eq!(
+            get_value(&config, "providers.brave.api_key").unwrap(),
+            Some("BSA-...1234".to_string())
+        );
+        assert_eq!(
+            get_value(&config, "fallback_order").unwrap(),
+            Some("brave, duckduckgo".to_string())
+        );
+        let section = get_value(&config, "providers.brave").unwrap().unwrap();
+        assert!(section.contains("api_key: BSA-...1234"));
+        assert!(section.contains("enabled: true"));
+
+        // Known but unset
+        assert_eq!(get_value(&config, "network.proxy").unwrap(), None);
+        assert_eq!(get_value(&config, "providers.bing.api_key").unwrap(), None);
+    }
+
+    #[test]
+    fn test_unset() {
+        let mut config = Config::default();
+        set_value(&mut config, "providers.brave.api_key", "key").unwrap();
+        set_value(&mut config, "providers.tavily.api_key", "key").unwrap();
+        set_value(&mut config, "fallback_order", "brave,tavily").unwrap();
+        set_value(&mut config, "default_provider", "brave").unwrap();
+        set_value(&mut config, "defaults.num_results", "3").unwrap();
+
+        unset_value(&mut config, "providers.brave").unwrap();
+        assert!(config.providers.brave.is_none());
+        assert_eq!(config.fallback_order, vec!["tavily"]);
+        assert!(config.default_provider.is_none());
+
+        unset_value(&mut config, "defaults.num_results").unwrap();
+        assert_eq!(config.defaults.num_results, 10);
+
+        assert!(unset_value(&mut config, "providers.bing").is_err());
+        assert!(unset_value(&mut config, "providers.nope").is_err());
+    }
+}
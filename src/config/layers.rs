@@ -0,0 +1,189 @@
+//! Layered configuration
+//!
+//! The effective config is built by merging YAML layers over the defaults:
+//! mappings merge key by key, while scalars and lists replace what came
+//! before. Each value remembers which layer last set it, so `config list` can
+//! say where it came from.
+
+use super::*;
+use crate::error::Result;
+use serde_yaml::{Mapping, Value};
+use std::collections::BTreeMap;
+
+/// Source shown for values no layer sets
+pub const DEFAULT_SOURCE: &str = "default";
+
+/// One YAML document in the merge
+#[derive(Debug, Clone)]
+pub struct Layer {
+    /// Where the layer came from (a file path, `profile ci`, ...)
+    pub source: String,
+    pub value: Value,
+}
+
+impl Layer {
+    pub fn new(source: impl Into<String>, value: Value) -> Self {
+        Self {
+            source: source.into(),
+            value,
+        }
+    }
+}
+
+/// The merged config and the source of each value
+#[derive(Debug, Clone, Default)]
+pub struct LayeredConfig {
+    pub config: Config,
+    /// Dotted key paths, each with the source that set it
+    sources: BTreeMap<String, String>,
+}
+
+impl LayeredConfig {
+    /// Merge `layers` in order over the default config
+    pub fn merge(layers: Vec<Layer>) -> Result<Self> {
+        let mut tree = serde_yaml::to_value(Config::default())?;
+        let mut sources = BTreeMap::new();
+        for layer in layers {
+            merge_value(&mut tree, layer.value, "", &layer.source, &mut sources);
+        }
+        Ok(Self {
+            config: serde_yaml::from_value(tree)?,
+            sources,
+        })
+    }
+
+    /// Apply a change made directly to the config, crediting every value it
+    /// changed to `source`
+    pub fn apply(&mut self, source: &str, change: impl FnOnce(&mut Config)) {
+        let before = self.config.to_flat_map();
+        change(&mut self.config);
+        for (key, value) in self.config.to_flat_map() {
+            if before.get(&key) != Some(&value) {
+                self.sources.insert(key, source.to_string());
+            }
+        }
+    }
+
+    /// Whether values came from more than one place (besides the defaults)
+    pub fn is_layered(&self) -> bool {
+        let mut sources = self.sources.values();
+        match sources.next() {
+            Some(first) => sources.any(|source| source != first),
+            None => false,
+        }
+    }
+
+    /// Where the value at `key` came from
+    pub fn source_of(&self, key: &str) -> &str {
+        let mut path = key;
+        loop {
+            if let Some(source) = self.sources.get(path) {
+                return source;
+            }
+            match path.rfind('.') {
+                Some(end) => path = &path[..end],
+                None => return DEFAULT_SOURCE,
+            }
+        }
+    }
+}
+
+/// Merge `overlay` into `base`, recording the source of each value set
+fn merge_value(
+    base: &mut Value,
+    overlay: Value,
+    path: &str,
+    source: &str,
+    sources: &mut BTreeMap<String, String>,
+) {
+    match overlay {
+        Value::Mapping(overlay) => {
+            if !base.is_mapping() {
+                clear_sources(sources, path);
+                *base = Value::Mapping(Mapping::new());
+            }
+            let base = base.as_mapping_mut().expect("base is a mapping");
+            for (key, value) in overlay {
+                let child = match key.as_str() {
+                    Some(name) if path.is_empty() => name.to_string(),
+                    Some(name) => format!("{}.{}", path, name),
+                    None => path.to_string(),
+                };
+                let slot = base.entry(key).or_insert(Value::Null);
+                merge_value(slot, value, &child, source, sources);
+            }
+        }
+        overlay => {
+            clear_sources(sources, path);
+            sources.insert(path.to_string(), source.to_string());
+            *base = overlay;
+        }
+    }
+}
+
+/// Forget sources at or below `path` once it is replaced wholesale
+fn clear_sources(sources: &mut BTreeMap<String, String>, path: &str) {
+    let prefix = format!("{}.", path);
+    sources.retain(|key, _| key != path && !key.starts_with(&prefix));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layer(source: &str, yaml: &str) -> Layer {
+        Layer::new(source, serde_yaml::from_str(yaml).unwrap())
+    }
+
+    #[test]
+    fn test_merge_layers() {
+        let merged = LayeredConfig::merge(vec![
+            layer(
+                "base",
+                "providers:\n  brave:\n    api_key: base-key\nfallback_order: [brave, tavily]\n",
+            ),
+            layer(
+                "ci",
+                "providers:\n  brave:\n    enabled: false\nfallback_order: [duckduckgo]\n",
+            ),
+        ])
+        .unwrap();
+
+        let brave = merged.config.providers.brave.as_ref().unwrap();
+        assert_eq!(brave.api_key, "base-key");
+        assert!(!brave.enabled);
+        assert_eq!(merged.config.fallback_order, vec!["duckduckgo"]);
+
+        assert_eq!(merged.source_of("providers.brave.api_key"), "base");
+        assert_eq!(merged.source_of("providers.brave.enabled"), "ci");
+        assert_eq!(merged.source_of("fallback_order"), "ci");
+        assert_eq!(merged.source_of("defaults.num_results"), DEFAULT_SOURCE);
+        assert!(merged.is_layered());
+    }
+
+    #[test]
+    fn test_replacing_a_section_resets_its_sources() {
+        let merged = LayeredConfig::merge(vec![
+            layer("base", "providers:\n  brave:\n    api_key: base-key\n"),
+            layer("ci", "providers:\n  brave: null\n"),
+        ])
+        .unwrap();
+
+        assert!(merged.config.providers.brave.is_none());
+        assert_eq!(merged.source_of("providers.brave.api_key"), "ci");
+    }
+
+    #[test]
+    fn test_apply_records_changed_values() {
+        let mut merged =
+            LayeredConfig::merge(vec![layer("base", "default_provider: brave\n")]).unwrap();
+        assert!(!merged.is_layered());
+        merged.apply("env", |config| {
+            config.default_provider = Some("tavily".to_string());
+            config.defaults.num_results = 10;
+        });
+
+        assert_eq!(merged.source_of("default_provider"), "env");
+        assert_eq!(merged.source_of("defaults.num_results"), DEFAULT_SOURCE);
+    }
+}
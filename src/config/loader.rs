@@ -1,10 +1,14 @@
-//! Configuration loading and saving
+//! Configuration loading, saving and format migrations
 
+use super::layers::Layer;
+use super::validate::{self, FileKind};
 use super::*;
 use crate::error::{Result, SearchError};
 use directories::ProjectDirs;
+use serde_yaml::{Mapping, Value};
+use similar::{ChangeTag, TextDiff};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 /// Environment variable prefix for configuration
 const ENV_PREFIX: &str = "CLI_WEB_SEARCH";
@@ -22,37 +26,447 @@ pub fn config_path() -> Result<PathBuf> {
 }
 
 /// Get the cache directory path
-#[allow(dead_code)]
 pub fn cache_dir() -> Result<PathBuf> {
     ProjectDirs::from("com", "cli-web-search", "cli-web-search")
         .map(|dirs| dirs.cache_dir().to_path_buf())
         .ok_or_else(|| SearchError::Config("Could not determine cache directory".to_string()))
 }
 
+/// Get the directory for persisted state (usage counters, provider health)
+pub fn data_dir() -> Result<PathBuf> {
+    ProjectDirs::from("com", "cli-web-search", "cli-web-search")
+        .map(|dirs| dirs.data_local_dir().to_path_buf())
+        .ok_or_else(|| SearchError::Config("Could not determine data directory".to_string()))
+}
+
+/// File name of project-local configuration
+pub const PROJECT_CONFIG_FILE: &str = ".cli-web-search.yaml";
+
+/// Which configuration to load
+#[derive(Debug, Clone, Default)]
+pub struct LoadOptions {
+    /// Config file to use instead of the user config file
+    pub config_file: Option<PathBuf>,
+    /// Profile to layer over the config file
+    pub profile: Option<String>,
+}
+
+impl LoadOptions {
+    /// The user-level config file: `--config` if given, else the default
+    /// path. This is the file `config set`, `unset` and `init` write.
+    pub fn user_config_path(&self) -> Result<PathBuf> {
+        match self.config_file {
+            Some(ref path) => Ok(path.clone()),
+            None => config_path(),
+        }
+    }
+}
+
 /// Load configuration from file and environment variables
-pub fn load_config() -> Result<Config> {
-    let mut config = load_config_file().unwrap_or_default();
+///
+/// Secret references (`env:`, `cmd:`, ...) are left in place; they are only
+/// resolved when the provider registry is built.
+pub fn load_config(options: &LoadOptions) -> Result<Config> {
+    Ok(load_layered_config(options)?.config)
+}
 
-    // Override with environment variables
-    apply_env_overrides(&mut config);
+/// Load configuration, keeping track of where each value came from
+///
+/// Layers are applied in order: defaults, the user config file, the selected
+/// profile (and the profiles it inherits from), the project config, then
+/// environment variables. Each file is validated as it is read, and the
+/// result as a whole once merged.
+pub fn load_layered_config(options: &LoadOptions) -> Result<LayeredConfig> {
+    let layered = merge_layers(options)?;
+    validate::ensure_valid("configuration", &validate::check_config(&layered.config))?;
+    Ok(layered)
+}
 
-    Ok(config)
+/// Merge every layer, without checking the merged result
+pub(super) fn merge_layers(options: &LoadOptions) -> Result<LayeredConfig> {
+    let path = options.user_config_path()?;
+    let file = match options.config_file {
+        Some(_) if !path.exists() => {
+            return Err(SearchError::Config(format!(
+                "Config file not found: {}",
+                path.display()
+            )))
+        }
+        _ => read_config_value(&path)?,
+    }
+    .unwrap_or(serde_yaml::Value::Null);
+
+    let mut layers = Vec::new();
+    if !file.is_null() {
+        layers.push(Layer::new(path.display().to_string(), file.clone()));
+    }
+    if let Some(ref profile) = options.profile {
+        layers.extend(profiles::profile_layers(
+            &file,
+            profile,
+            &profile_dir(&path),
+        )?);
+    }
+    if let Some(project) = find_project_config(&std::env::current_dir()?) {
+        if project != path {
+            layers.push(load_project_layer(&project)?);
+        }
+    }
+
+    let mut layered = LayeredConfig::merge(layers)?;
+    layered.apply("env", apply_env_overrides);
+    Ok(layered)
 }
 
-/// Load configuration from file only
-fn load_config_file() -> Result<Config> {
-    let path = config_path()?;
+/// Every place configuration is read from, lowest precedence first, with
+/// whether each file exists
+pub fn config_chain(options: &LoadOptions) -> Result<Vec<(String, Option<PathBuf>)>> {
+    let path = options.user_config_path()?;
+    let mut chain = vec![
+        ("defaults".to_string(), None),
+        ("user".to_string(), Some(path.clone())),
+    ];
+
+    if let Some(ref profile) = options.profile {
+        let file = read_config_value(&path)?.unwrap_or(serde_yaml::Value::Null);
+        for layer in profiles::profile_layers(&file, profile, &profile_dir(&path))? {
+            match layer.source.strip_prefix("profile ") {
+                Some(name) => chain.push((format!("profile {} (in user config)", name), None)),
+                None => chain.push(("profile".to_string(), Some(PathBuf::from(layer.source)))),
+            }
+        }
+    }
+
+    let cwd = std::env::current_dir()?;
+    let project = find_project_config(&cwd).unwrap_or_else(|| cwd.join(PROJECT_CONFIG_FILE));
+    chain.push(("project".to_string(), Some(project)));
+    chain.push((format!("environment ({}_*)", ENV_PREFIX), None));
+    chain.push(("command-line flags".to_string(), None));
+    Ok(chain)
+}
+
+/// The nearest project config in `start` or one of its parents
+pub fn find_project_config(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .map(|dir| dir.join(PROJECT_CONFIG_FILE))
+        .find(|path| path.is_file())
+}
+
+/// Profile files live in `profiles/` next to the user config file
+pub(super) fn profile_dir(config_file: &Path) -> PathBuf {
+    config_file
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_default()
+}
+
+/// Read a project config, refusing anything but harmless settings
+///
+/// Project files come with whatever repository is checked out, so they may
+/// only pick providers and search defaults. Keys, proxies, certificates and
+/// the fetch policy could run commands, read files or redirect requests just
+/// by searching from inside the repository.
+fn load_project_layer(path: &Path) -> Result<Layer> {
+    let value = read_config_value(path)?.unwrap_or(serde_yaml::Value::Null);
+
+    if let Some(key) = find_project_key_denied(&value, &mut Vec::new()) {
+        return Err(SearchError::Config(format!(
+            "{}: {} can't be set in a project config; move it to the user config",
+            path.display(),
+            key
+        )));
+    }
 
+    Ok(Layer::new(path.display().to_string(), value))
+}
+
+/// Whether a project config may set the key at `path`
+fn project_key_allowed(path: &[String]) -> bool {
+    let path: Vec<&str> = path.iter().map(String::as_str).collect();
+    matches!(
+        path.as_slice(),
+        ["version" | "default_provider" | "fallback_order"]
+            | ["defaults", ..]
+            | ["providers", _, "enabled"]
+    )
+}
+
+/// The first key below `value` a project config may not set
+fn find_project_key_denied(value: &serde_yaml::Value, path: &mut Vec<String>) -> Option<String> {
+    if !path.is_empty() && project_key_allowed(path) {
+        return None;
+    }
+    let Some(mapping) = value.as_mapping() else {
+        return (!path.is_empty()).then(|| path.join("."));
+    };
+    for (key, child) in mapping {
+        path.push(key.as_str().unwrap_or_default().to_string());
+        let denied = find_project_key_denied(child, path);
+        path.pop();
+        if denied.is_some() {
+            return denied;
+        }
+    }
+    None
+}
+
+/// A config file as YAML (`None` if it doesn't exist), refused if it has
+/// errors
+///
+/// Older files are migrated to the current format first.
+fn read_config_value(path: &Path) -> Result<Option<serde_yaml::Value>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = read_migrated(path)?;
+    validate::ensure_valid(
+        &path.display().to_string(),
+        &validate::check_file(&content, FileKind::Config),
+    )?;
+    Ok(Some(serde_yaml::from_str(&content)?))
+}
+
+/// Load configuration from a single file, without environment overrides
+pub(crate) fn load_config_file(path: &Path) -> Result<Config> {
     if !path.exists() {
         return Ok(Config::default());
     }
 
-    let content = fs::read_to_string(&path)?;
+    let content = read_migrated(path)?;
     let config: Config = serde_yaml::from_str(&content)?;
 
     Ok(config)
 }
 
+/// A change to the config file format
+struct Migration {
+    /// Version the migration upgrades from (to `from + 1`)
+    from: u32,
+    /// What changes, shown by `config migrate`
+    description: &'static str,
+    /// Rewrite one config mapping: the file itself, or an inline profile
+    apply: fn(&mut Mapping),
+}
+
+/// Every format change, oldest first; `CONFIG_VERSION` is the version after
+/// the last one
+const MIGRATIONS: &[Migration] = &[Migration {
+    from: 0,
+    description: "record the format version (files without `version` are version 0)",
+    apply: |_| {},
+}];
+
+/// A config file brought up to the current format
+#[derive(Debug, Clone)]
+pub struct MigratedFile {
+    /// Version the file was at
+    pub from: u32,
+    /// Descriptions of the migrations applied, in order
+    pub steps: Vec<&'static str>,
+    /// Whether only the `version` stamp changed, so the file reads the same
+    /// either way
+    pub version_only: bool,
+    /// The file's new contents
+    pub content: String,
+}
+
+/// A file `config migrate` found out of date
+#[derive(Debug, Clone)]
+pub struct FileMigration {
+    pub original: String,
+    pub migrated: MigratedFile,
+    /// Copy of the original file (`None` for a dry run)
+    pub backup: Option<PathBuf>,
+}
+
+impl FileMigration {
+    /// Line diff from the original contents to the migrated ones
+    pub fn diff(&self) -> String {
+        let diff = TextDiff::from_lines(&self.original, &self.migrated.content);
+        let mut output = String::new();
+        for change in diff.iter_all_changes() {
+            let sign = match change.tag() {
+                ChangeTag::Delete => "-",
+                ChangeTag::Insert => "+",
+                ChangeTag::Equal => continue,
+            };
+            output.push_str(&format!("{} {}", sign, change));
+        }
+        output
+    }
+}
+
+/// Upgrade a config file's contents to `CONFIG_VERSION`
+///
+/// Returns `None` when the file is current, or isn't YAML at all (which
+/// validation reports). Files written by a newer version are refused rather
+/// than guessed at.
+pub fn migrate_content(content: &str, source: &str) -> Result<Option<MigratedFile>> {
+    migrate_with(content, source, MIGRATIONS, CONFIG_VERSION)
+}
+
+fn migrate_with(
+    content: &str,
+    source: &str,
+    migrations: &[Migration],
+    current: u32,
+) -> Result<Option<MigratedFile>> {
+    let Ok(original) = serde_yaml::from_str::<Value>(content) else {
+        return Ok(None);
+    };
+    if !original.is_mapping() {
+        return Ok(None);
+    }
+    let from = file_version(&original, source)?;
+    if from > current {
+        return Err(SearchError::Config(format!(
+            "{} is config version {}, but this cli-web-search only understands versions up \
+             to {}; upgrade cli-web-search to use it",
+            source, from, current
+        )));
+    }
+    if from == current {
+        return Ok(None);
+    }
+
+    let mut value = original.clone();
+    let map = value.as_mapping_mut().expect("config is a mapping");
+    let mut steps = Vec::new();
+    for migration in migrations.iter().filter(|m| m.from >= from) {
+        (migration.apply)(map);
+        let profiles = map.get_mut("profiles").and_then(Value::as_mapping_mut);
+        for profile in profiles.into_iter().flat_map(|p| p.values_mut()) {
+            if let Some(profile) = profile.as_mapping_mut() {
+                (migration.apply)(profile);
+            }
+        }
+        steps.push(migration.description);
+    }
+    map.insert("version".into(), current.into());
+
+    // Only the version changed: edit the text to keep comments and layout
+    let version_only = without_version(&original) == without_version(&value);
+    let stamped = match version_only {
+        true => stamp_version(content, current, &value),
+        false => None,
+    };
+    let content = match stamped {
+        Some(stamped) => stamped,
+        None => serde_yaml::to_string(&value)?,
+    };
+    Ok(Some(MigratedFile {
+        from,
+        steps,
+        version_only,
+        content,
+    }))
+}
+
+/// The `version` a file declares; files from before versioning are 0
+fn file_version(value: &Value, source: &str) -> Result<u32> {
+    match value.get("version") {
+        None | Some(Value::Null) => Ok(0),
+        Some(version) => version
+            .as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .ok_or_else(|| {
+                SearchError::Config(format!(
+                    "{}: version must be a whole number, not {}",
+                    source,
+                    serde_yaml::to_string(version).unwrap_or_default().trim()
+                ))
+            }),
+    }
+}
+
+fn without_version(value: &Value) -> Value {
+    let mut value = value.clone();
+    if let Some(map) = value.as_mapping_mut() {
+        map.remove("version");
+    }
+    value
+}
+
+/// Set the top-level `version:` line
+///
+/// A missing one is added at the end, so line numbers in diagnostics for a
+/// file migrated only in memory still match the file. Returns `None` when
+/// the edited text doesn't read back as `expected`, as for a flow-style
+/// mapping or a file ending in a document marker.
+fn stamp_version(content: &str, version: u32, expected: &Value) -> Option<String> {
+    let line = format!("version: {}", version);
+    let mut lines: Vec<&str> = content.lines().collect();
+    match lines.iter().position(|l| l.starts_with("version:")) {
+        Some(i) => lines[i] = &line,
+        None => lines.push(&line),
+    }
+    let stamped = lines.join("\n") + "\n";
+    let reread: Value = serde_yaml::from_str(&stamped).ok()?;
+    (reread == *expected).then_some(stamped)
+}
+
+/// Read a config file, migrated to the current format in memory
+///
+/// The file itself is left alone; `config migrate` rewrites it.
+pub(super) fn read_migrated(path: &Path) -> Result<String> {
+    let content = fs::read_to_string(path)?;
+    match migrate_content(&content, &path.display().to_string())? {
+        Some(migrated) => Ok(migrated.content),
+        None => Ok(content),
+    }
+}
+
+/// Replace a file with its migrated contents, keeping a copy of the original
+fn write_migrated(path: &Path, migrated: &MigratedFile) -> Result<PathBuf> {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".v{}.bak", migrated.from));
+    let backup = path.with_file_name(name);
+
+    // `copy` keeps the permissions, which may be protecting API keys
+    fs::copy(path, &backup)?;
+    fs::write(path, &migrated.content)?;
+    Ok(backup)
+}
+
+/// Migrate the user config, profile files and project config
+///
+/// Returns every file found, with its migration if it was out of date. With
+/// `dry_run`, nothing is written.
+pub fn migrate_config_files(
+    options: &LoadOptions,
+    dry_run: bool,
+) -> Result<Vec<(PathBuf, Option<FileMigration>)>> {
+    let user = options.user_config_path()?;
+    let mut paths = vec![user.clone()];
+    paths.extend(profiles::profile_files(&profile_dir(&user)));
+    if let Some(project) = find_project_config(&std::env::current_dir()?) {
+        paths.push(project);
+    }
+    paths.dedup();
+
+    let mut files = Vec::new();
+    for path in paths.into_iter().filter(|p| p.exists()) {
+        let original = fs::read_to_string(&path)?;
+        let migration = match migrate_content(&original, &path.display().to_string())? {
+            Some(migrated) => {
+                let backup = match dry_run {
+                    true => None,
+                    false => Some(write_migrated(&path, &migrated)?),
+                };
+                Some(FileMigration {
+                    original,
+                    migrated,
+                    backup,
+                })
+            }
+            None => None,
+        };
+        files.push((path, migration));
+    }
+    Ok(files)
+}
+
 /// Apply environment variable overrides to config
 fn apply_env_overrides(config: &mut Config) {
     // Brave API key
@@ -167,213 +581,66 @@ fn apply_env_overrides(config: &mut Config) {
     }
 }
 
-/// Save configuration to file
-pub fn save_config(config: &Config) -> Result<()> {
-    let path = config_path()?;
-
+/// Save configuration to a file
+pub fn save_config(config: &Config, path: &Path) -> Result<()> {
     // Ensure directory exists
     if let Some(parent) = path.parent() {
         fs::create_dir_all(parent)?;
     }
 
     let content = serde_yaml::to_string(config)?;
-    fs::write(&path, content)?;
+    fs::write(path, content)?;
 
     // Set restrictive permissions on Unix
     #[cfg(unix)]
     {
         use std::os::unix::fs::PermissionsExt;
-        let mut perms = fs::metadata(&path)?.permissions();
+        let mut perms = fs::metadata(path)?.permissions();
         perms.set_mode(0o600);
-        fs::set_permissions(&path, perms)?;
+        fs::set_permissions(path, perms)?;
     }
 
     Ok(())
 }
 
-/// Initialize configuration with interactive wizard
-pub fn init_config_interactive() -> Result<Config> {
-    // For now, create a default config
-    // TODO: Implement interactive wizard with prompts
-    let config = Config::default();
-    save_config(&config)?;
-    Ok(config)
-}
-
-/// Set a specific configuration value by key path
-pub fn set_config_value(key: &str, value: &str) -> Result<()> {
-    let mut config = load_config()?;
-
-    let parts: Vec<&str> = key.split('.').collect();
-
-    match parts.as_slice() {
-        ["default_provider"] => {
-            config.default_provider = Some(value.to_string());
-        }
-        ["providers", "brave", "api_key"] => {
-            if config.providers.brave.is_none() {
-                config.providers.brave = Some(BraveConfig {
-                    api_key: value.to_string(),
-                    enabled: true,
-                });
-            } else if let Some(ref mut brave) = config.providers.brave {
-                brave.api_key = value.to_string();
-            }
-        }
-        ["providers", "brave", "enabled"] => {
-            if let Some(ref mut brave) = config.providers.brave {
-                brave.enabled = value.parse().unwrap_or(true);
-            }
-        }
-        ["providers", "google", "api_key"] => {
-            if config.providers.google.is_none() {
-                config.providers.google = Some(GoogleConfig {
-                    api_key: value.to_string(),
-                    cx: String::new(),
-                    enabled: true,
-                });
-            } else if let Some(ref mut google) = config.providers.google {
-                google.api_key = value.to_string();
-            }
-        }
-        ["providers", "google", "cx"] => {
-            if let Some(ref mut google) = config.providers.google {
-                google.cx = value.to_string();
-            } else {
-                config.providers.google = Some(GoogleConfig {
-                    api_key: String::new(),
-                    cx: value.to_string(),
-                    enabled: true,
-                });
-            }
-        }
-        ["providers", "google", "enabled"] => {
-            if let Some(ref mut google) = config.providers.google {
-                google.enabled = value.parse().unwrap_or(true);
-            }
-        }
-        ["providers", "tavily", "api_key"] => {
-            if config.providers.tavily.is_none() {
-                config.providers.tavily = Some(TavilyConfig {
-                    api_key: value.to_string(),
-                    enabled: true,
-                });
-            } else if let Some(ref mut tavily) = config.providers.tavily {
-                tavily.api_key = value.to_string();
-            }
-        }
-        ["providers", "tavily", "enabled"] => {
-            if let Some(ref mut tavily) = config.providers.tavily {
-                tavily.enabled = value.parse().unwrap_or(true);
-            }
-        }
-        ["providers", "duckduckgo", "enabled"] => {
-            if config.providers.duckduckgo.is_none() {
-                config.providers.duckduckgo = Some(DuckDuckGoConfig {
-                    enabled: value.parse().unwrap_or(true),
-                });
-            } else if let Some(ref mut ddg) = config.providers.duckduckgo {
-                ddg.enabled = value.parse().unwrap_or(true);
-            }
-        }
-        ["providers", "serper", "api_key"] => {
-            if config.providers.serper.is_none() {
-                config.providers.serper = Some(SerperConfig {
-                    api_key: value.to_string(),
-                    enabled: true,
-                });
-            } else if let Some(ref mut serper) = config.providers.serper {
-                serper.api_key = value.to_string();
-            }
-        }
-        ["providers", "serper", "enabled"] => {
-            if let Some(ref mut serper) = config.providers.serper {
-                serper.enabled = value.parse().unwrap_or(true);
-            }
-        }
-        ["providers", "firecrawl", "api_key"] => {
-            if config.providers.firecrawl.is_none() {
-                config.providers.firecrawl = Some(FirecrawlConfig {
-                    api_key: value.to_string(),
-                    enabled: true,
-                });
-            } else if let Some(ref mut firecrawl) = config.providers.firecrawl {
-                firecrawl.api_key = value.to_string();
-            }
-        }
-        ["providers", "firecrawl", "enabled"] => {
-            if let Some(ref mut firecrawl) = config.providers.firecrawl {
-                firecrawl.enabled = value.parse().unwrap_or(true);
-            }
-        }
-        ["providers", "serpapi", "api_key"] => {
-            if config.providers.serpapi.is_none() {
-                config.providers.serpapi = Some(SerpApiConfig {
-                    api_key: value.to_string(),
-                    enabled: true,
-                });
-            } else if let Some(ref mut serpapi) = config.providers.serpapi {
-                serpapi.api_key = value.to_string();
-            }
-        }
-        ["providers", "serpapi", "enabled"] => {
-            if let Some(ref mut serpapi) = config.providers.serpapi {
-                serpapi.enabled = value.parse().unwrap_or(true);
-            }
-        }
-        ["providers", "bing", "api_key"] => {
-            if config.providers.bing.is_none() {
-                config.providers.bing = Some(BingConfig {
-                    api_key: value.to_string(),
-                    enabled: true,
-                });
-            } else if let Some(ref mut bing) = config.providers.bing {
-                bing.api_key = value.to_string();
-            }
-        }
-        ["providers", "bing", "enabled"] => {
-            if let Some(ref mut bing) = config.providers.bing {
-                bing.enabled = value.parse().unwrap_or(true);
-            }
-        }
-        ["defaults", "num_results"] => {
-            config.defaults.num_results = value.parse().unwrap_or(10);
-        }
-        ["defaults", "safe_search"] => {
-            config.defaults.safe_search = value.to_string();
-        }
-        ["defaults", "timeout"] => {
-            config.defaults.timeout = value.parse().unwrap_or(30);
-        }
-        ["defaults", "format"] => {
-            config.defaults.format = value.to_string();
-        }
-        ["cache", "enabled"] => {
-            config.cache.enabled = value.parse().unwrap_or(true);
-        }
-        ["cache", "ttl_seconds"] => {
-            config.cache.ttl_seconds = value.parse().unwrap_or(3600);
-        }
-        ["cache", "max_entries"] => {
-            config.cache.max_entries = value.parse().unwrap_or(1000);
-        }
-        _ => {
-            return Err(SearchError::Config(format!(
-                "Unknown configuration key: {}",
-                key
-            )));
-        }
+/// Set a specific configuration value by key path in the user config file
+///
+/// Environment overrides are not applied, so they never end up written to
+/// the file. Values that fail validation (an unknown provider in
+/// `fallback_order`, say) are refused. Returns the value as it will be
+/// displayed (secrets masked).
+pub fn set_config_value(key: &str, value: &str, options: &LoadOptions) -> Result<String> {
+    let path = options.user_config_path()?;
+    let mut config = load_config_file(&path)?;
+    keys::set_value(&mut config, key, value)?;
+
+    let section = format!("{}.", key);
+    let problem = validate::check_config(&config)
+        .into_iter()
+        .find(|d| d.is_error() && (d.key() == key || d.key().starts_with(&section)));
+    if let Some(problem) = problem {
+        return Err(SearchError::Config(format!(
+            "Invalid value '{}' for {}: {}",
+            value, key, problem.message
+        )));
     }
 
-    save_config(&config)?;
-    Ok(())
+    save_config(&config, &path)?;
+    Ok(keys::get_value(&config, key)?.unwrap_or_default())
+}
+
+/// Remove a configuration value by key path, restoring its default
+pub fn unset_config_value(key: &str, options: &LoadOptions) -> Result<()> {
+    let path = options.user_config_path()?;
+    let mut config = load_config_file(&path)?;
+    keys::unset_value(&mut config, key)?;
+    save_config(&config, &path)
 }
 
 /// Get a specific configuration value by key path
-pub fn get_config_value(key: &str) -> Result<Option<String>> {
-    let config = load_config()?;
-    let map = config.to_flat_map();
-    Ok(map.get(key).cloned())
+pub fn get_config_value(key: &str, options: &LoadOptions) -> Result<Option<String>> {
+    let config = load_config(options)?;
+    keys::get_value(&config, key)
 }
 
 #[cfg(test)]
@@ -410,10 +677,236 @@ mod tests {
     #[test]
     fn test_load_config_returns_default_when_no_file() {
         // This should return a default config without failing
-        let config = load_config();
+        let config = load_config(&LoadOptions::default());
         assert!(config.is_ok());
     }
 
+    #[test]
+    fn test_find_project_config_walks_up() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let nested = dir.path().join("a/b");
+        fs::create_dir_all(&nested).unwrap();
+        assert_eq!(find_project_config(&nested), None);
+
+        let project = dir.path().join("a").join(PROJECT_CONFIG_FILE);
+        fs::write(&project, "defaults:\n  num_results: 3\n").unwrap();
+        assert_eq!(find_project_config(&nested), Some(project));
+    }
+
+    #[test]
+    fn test_project_config_allows_search_settings() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join(PROJECT_CONFIG_FILE);
+
+        fs::write(
+            &path,
+            "version: 1\ndefault_provider: brave\nfallback_order: [brave, duckduckgo]\n\
+             defaults:\n  num_results: 3\n  safe_search: strict\n\
+             providers:\n  duckduckgo:\n    enabled: false\n",
+        )
+        .unwrap();
+        load_project_layer(&path).unwrap();
+    }
+
+    #[test]
+    fn test_project_config_refuses_other_settings() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join(PROJECT_CONFIG_FILE);
+
+        for (content, key) in [
+            (
+                "providers:\n  brave:\n    api_key: 'cmd:curl evil'\n",
+                "providers.brave.api_key",
+            ),
+            (
+                "providers:\n  brave:\n    api_key: 'file:~/.ssh/id_ed25519'\n",
+                "providers.brave.api_key",
+            ),
+            (
+                "network:\n  proxy: http://attacker.example:8080\n",
+                "network.proxy",
+            ),
+            (
+                "network:\n  root_certs: [./evil-ca.pem]\n",
+                "network.root_certs",
+            ),
+            (
+                "fetch:\n  policy:\n    block_private_networks: false\n",
+                "fetch.policy.block_private_networks",
+            ),
+            ("cache:\n  enabled: false\n", "cache.enabled"),
+        ] {
+            fs::write(&path, content).unwrap();
+            let err = load_project_layer(&path).unwrap_err().to_string();
+            assert!(
+                err.contains(&format!("{} can't be set in a project config", key)),
+                "{}",
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn test_explicit_config_file() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let options = LoadOptions {
+            config_file: Some(dir.path().join("custom.yaml")),
+            profile: None,
+        };
+        assert!(load_config(&options).is_err());
+
+        set_config_value("defaults.num_results", "7", &options).unwrap();
+        let config = load_config(&options).unwrap();
+        assert_eq!(config.defaults.num_results, 7);
+    }
+
+    #[test]
+    fn test_secrets_resolved_when_registry_is_built() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("config.yaml");
+        fs::write(
+            &path,
+            "providers:\n  brave:\n    api_key: 'env:CLI_WEB_SEARCH_TEST_LAZY_UNSET'\n",
+        )
+        .unwrap();
+        let options = LoadOptions {
+            config_file: Some(path),
+            profile: None,
+        };
+
+        let config = load_config(&options).unwrap();
+        assert_eq!(
+            get_config_value("providers.brave.api_key", &options).unwrap(),
+            Some("env:CLI_WEB_SEARCH_TEST_LAZY_UNSET".to_string())
+        );
+        let err = crate::providers::build_registry(&config)
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(err.contains("CLI_WEB_SEARCH_TEST_LAZY_UNSET"), "{}", err);
+    }
+
+    #[test]
+    fn test_migrations_are_contiguous() {
+        for (i, migration) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(migration.from, i as u32);
+        }
+        assert_eq!(MIGRATIONS.len() as u32, CONFIG_VERSION);
+    }
+
+    #[test]
+    fn test_unversioned_file_is_stamped() {
+        let content = "# my settings\nproviders:\n  brave: {api_key: x}  # personal\n";
+        let migrated = migrate_content(content, "config.yaml").unwrap().unwrap();
+        assert_eq!(migrated.from, 0);
+        assert!(migrated.version_only);
+        assert_eq!(
+            migrated.content,
+            format!(
+                "# my settings\nproviders:\n  brave: {{api_key: x}}  # personal\nversion: {}\n",
+                CONFIG_VERSION
+            )
+        );
+
+        assert!(migrate_content(&migrated.content, "config.yaml")
+            .unwrap()
+            .is_none());
+        assert!(migrate_content("", "config.yaml").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_stamping_falls_back_to_rewriting() {
+        // Neither can take a `version:` line at the end
+        for content in [
+            "{providers: {brave: {api_key: x}}}\n",
+            "providers:\n  brave: {api_key: x}\n...\n",
+        ] {
+            let migrated = migrate_content(content, "config.yaml").unwrap().unwrap();
+            assert!(migrated.version_only);
+
+            let value: Value = serde_yaml::from_str(&migrated.content).unwrap();
+            assert_eq!(value["version"], Value::from(CONFIG_VERSION));
+            assert_eq!(value["providers"]["brave"]["api_key"], Value::from("x"));
+            assert!(migrate_content(&migrated.content, "config.yaml")
+                .unwrap()
+                .is_none());
+        }
+    }
+
+    #[test]
+    fn test_migrations_rewrite_files_and_profiles() {
+        let migrations = [
+            Migration {
+                from: 0,
+                description: "nothing",
+                apply: |_| {},
+            },
+            Migration {
+                from: 1,
+                description: "rename cache.ttl",
+                apply: |map| {
+                    let cache = map.get_mut("cache").and_then(Value::as_mapping_mut);
+                    if let Some(cache) = cache {
+                        if let Some(ttl) = cache.remove("ttl") {
+                            cache.insert("ttl_seconds".into(), ttl);
+                        }
+                    }
+                },
+            },
+        ];
+        let content = "version: 1\ncache: {ttl: 60}\nprofiles:\n  ci:\n    cache: {ttl: 5}\n";
+        let migrated = migrate_with(content, "config.yaml", &migrations, 2)
+            .unwrap()
+            .unwrap();
+        assert_eq!(migrated.steps, vec!["rename cache.ttl"]);
+        assert!(!migrated.version_only);
+
+        let value: Value = serde_yaml::from_str(&migrated.content).unwrap();
+        assert_eq!(value["version"], Value::from(2));
+        assert_eq!(value["cache"]["ttl_seconds"], Value::from(60));
+        assert_eq!(
+            value["profiles"]["ci"]["cache"]["ttl_seconds"],
+            Value::from(5)
+        );
+    }
+
+    #[test]
+    fn test_newer_version_is_refused() {
+        let err = migrate_content("version: 99\n", "config.yaml").unwrap_err();
+        assert!(err.to_string().contains("config.yaml is config version 99"));
+
+        let err = migrate_content("version: two\n", "config.yaml").unwrap_err();
+        assert!(err.to_string().contains("version must be a whole number"));
+    }
+
+    #[test]
+    fn test_migrate_config_files() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("config.yaml");
+        fs::write(&path, "defaults:\n  num_results: 3\n").unwrap();
+        let options = LoadOptions {
+            config_file: Some(path.clone()),
+            profile: None,
+        };
+
+        let files = migrate_config_files(&options, true).unwrap();
+        let migration = files[0].1.as_ref().unwrap();
+        assert!(migration.backup.is_none());
+        assert_eq!(migration.diff(), format!("+ version: {}\n", CONFIG_VERSION));
+        assert!(!fs::read_to_string(&path).unwrap().contains("version"));
+
+        let files = migrate_config_files(&options, false).unwrap();
+        let backup = files[0].1.as_ref().unwrap().backup.clone().unwrap();
+        assert!(backup.ends_with("config.yaml.v0.bak"));
+        assert_eq!(
+            fs::read_to_string(&backup).unwrap(),
+            "defaults:\n  num_results: 3\n"
+        );
+        assert!(migrate_config_files(&options, false).unwrap()[0]
+            .1
+            .is_none());
+    }
+
     #[test]
     fn test_env_prefix_constant() {
         assert_eq!(ENV_PREFIX, "CLI_WEB_SEARCH");
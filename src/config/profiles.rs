@@ -0,0 +1,210 @@
+//! Named configuration profiles
+//!
+//! A profile is a partial config layered over the main file. It is defined
+//! either inline under `profiles.<name>` or in `profiles/<name>.yaml` next to
+//! the config file, and may name another profile to start from with
+//! `inherits: <name>`.
+
+use super::layers::Layer;
+use super::validate::{self, FileKind};
+use crate::error::{Result, SearchError};
+use serde_yaml::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Key naming the profile a profile builds on
+const INHERITS_KEY: &str = "inherits";
+
+/// Layers for `name`, starting with its furthest ancestor
+///
+/// `base` is the main config file, which may define profiles inline; profile
+/// files are looked up in `dir/profiles`.
+pub fn profile_layers(base: &Value, name: &str, dir: &Path) -> Result<Vec<Layer>> {
+    let mut layers = Vec::new();
+    let mut chain: Vec<String> = Vec::new();
+    let mut next = Some(name.to_string());
+
+    while let Some(name) = next.take() {
+        if chain.contains(&name) {
+            chain.push(name);
+            return Err(SearchError::Config(format!(
+                "Profile inheritance cycle: {}",
+                chain.join(" -> ")
+            )));
+        }
+
+        let mut layer = find_profile(base, &name, dir)?;
+        if let Some(map) = layer.value.as_mapping_mut() {
+            next = match map.remove(INHERITS_KEY) {
+                Some(Value::String(parent)) => Some(parent),
+                Some(Value::Null) | None => None,
+                Some(_) => {
+                    return Err(SearchError::Config(format!(
+                        "Profile {}: `{}` must be a profile name",
+                        name, INHERITS_KEY
+                    )))
+                }
+            };
+            // Profiles can't define further profiles
+            map.remove("profiles");
+        }
+
+        chain.push(name);
+        layers.push(layer);
+    }
+
+    layers.reverse();
+    Ok(layers)
+}
+
+/// Names of all defined profiles, sorted
+pub fn profile_names(base: &Value, dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = base
+        .get("profiles")
+        .and_then(Value::as_mapping)
+        .map(|map| {
+            map.keys()
+                .filter_map(|k| k.as_str().map(String::from))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    names.extend(
+        profile_files(dir)
+            .iter()
+            .filter_map(|path| Some(path.file_stem()?.to_str()?.to_string())),
+    );
+
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Profile files in `dir/profiles`, sorted
+pub fn profile_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir.join("profiles"))
+        .map(|entries| {
+            entries
+                .filter_map(|entry| Some(entry.ok()?.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "yaml"))
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort();
+    files
+}
+
+fn find_profile(base: &Value, name: &str, dir: &Path) -> Result<Layer> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(SearchError::Config(format!(
+            "Invalid profile name '{}' (use letters, digits, '-' and '_')",
+            name
+        )));
+    }
+
+    let inline = base.get("profiles").and_then(|p| p.get(name)).cloned();
+    let path = dir.join("profiles").join(format!("{}.yaml", name));
+
+    match (inline, path.exists()) {
+        (Some(_), true) => Err(SearchError::Config(format!(
+            "Profile {} is defined both inline and in {}",
+            name,
+            path.display()
+        ))),
+        (Some(value), false) => Ok(Layer::new(format!("profile {}", name), value)),
+        (None, true) => {
+            let content = super::loader::read_migrated(&path)?;
+            let source = path.display().to_string();
+            validate::ensure_valid(&source, &validate::check_file(&content, FileKind::Profile))?;
+            Ok(Layer::new(source, serde_yaml::from_str(&content)?))
+        }
+        (None, false) => {
+            let available = profile_names(base, dir);
+            Err(SearchError::Config(format!(
+                "Unknown profile: {} ({})",
+                name,
+                match available.is_empty() {
+                    true => "no profiles are defined".to_string(),
+                    false => format!("available: {}", available.join(", ")),
+                }
+            )))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn base() -> Value {
+        serde_yaml::from_str(
+            r#"
+providers:
+  brave:
+    api_key: personal
+profiles:
+  team:
+    providers:
+      brave:
+        api_key: team
+  ci:
+    inherits: team
+    fallback_order: [brave]
+  loop-a:
+    inherits: loop-b
+  loop-b:
+    inherits: loop-a
+"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_inherited_layers_in_order() {
+        let dir = TempDir::new().unwrap();
+        let layers = profile_layers(&base(), "ci", dir.path()).unwrap();
+
+        let sources: Vec<_> = layers.iter().map(|l| l.source.as_str()).collect();
+        assert_eq!(sources, vec!["profile team", "profile ci"]);
+        assert!(layers[1].value.get(INHERITS_KEY).is_none());
+    }
+
+    #[test]
+    fn test_profile_files() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir(dir.path().join("profiles")).unwrap();
+        fs::write(
+            dir.path().join("profiles/locked.yaml"),
+            "inherits: ci\ncache:\n  enabled: false\n",
+        )
+        .unwrap();
+
+        let layers = profile_layers(&base(), "locked", dir.path()).unwrap();
+        assert_eq!(layers.len(), 3);
+        assert!(layers[2].source.ends_with("locked.yaml"));
+
+        assert_eq!(
+            profile_names(&base(), dir.path()),
+            vec!["ci", "locked", "loop-a", "loop-b", "team"]
+        );
+    }
+
+    #[test]
+    fn test_profile_errors() {
+        let dir = TempDir::new().unwrap();
+        let err = profile_layers(&base(), "loop-a", dir.path()).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("cycle: loop-a -> loop-b -> loop-a"));
+
+        let err = profile_layers(&base(), "nope", dir.path()).unwrap_err();
+        assert!(err.to_string().contains("available: ci, loop-a"));
+
+        assert!(profile_layers(&base(), "../etc/passwd", dir.path()).is_err());
+    }
+}
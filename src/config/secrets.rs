@@ -0,0 +1,342 @@
+//! Secret references for provider API keys
+//!
+//! Instead of a literal key, `api_key` may name where the key lives:
+//!
+//! - `env:VAR` - an environment variable
+//! - `file:PATH` - the contents of a file (e.g. `/run/secrets/brave`)
+//! - `cmd:COMMAND` - the output of a shell command (e.g. `pass show brave`)
+//! - `age:PATH` - a passphrase-encrypted [age](https://age-encryption.org)
+//!   file, as written by `age -p`
+//!
+//! References are resolved when the provider registry is built, so commands
+//! that don't search never run a command or ask for a passphrase. The file
+//! and `config list` only ever contain the reference itself.
+//!
+//! Any value with one of these prefixes is a reference; a literal key that
+//! starts with one has to be stored elsewhere and referenced.
+
+use super::*;
+use crate::error::{Result, SearchError};
+use age::secrecy::SecretString;
+use std::io::{IsTerminal, Read};
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Environment variable holding the passphrase for `age:` files
+const PASSPHRASE_ENV: &str = "CLI_WEB_SEARCH_SECRETS_PASSPHRASE";
+
+/// Where a secret is stored
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecretRef<'a> {
+    Env(&'a str),
+    File(&'a str),
+    Command(&'a str),
+    Age(&'a str),
+}
+
+impl<'a> SecretRef<'a> {
+    /// Parse a reference; literal secrets return `None`
+    ///
+    /// There is no escape: `env:X` is always a reference, never a key.
+    pub fn parse(value: &'a str) -> Option<Self> {
+        let (scheme, target) = value.split_once(':')?;
+        let target = target.trim();
+        if target.is_empty() {
+            return None;
+        }
+        match scheme {
+            "env" => Some(Self::Env(target)),
+            "file" => Some(Self::File(target)),
+            "cmd" => Some(Self::Command(target)),
+            "age" => Some(Self::Age(target)),
+            _ => None,
+        }
+    }
+}
+
+/// Show a secret for display: references as written, literals masked
+pub fn display_secret(value: &str) -> String {
+    match SecretRef::parse(value) {
+        Some(_) => value.trim().to_string(),
+        None => mask_api_key(value),
+    }
+}
+
+/// Replace secret references in enabled providers with the secrets
+pub fn resolve_secrets(config: &mut Config) -> Result<()> {
+    let mut resolver = Resolver::default();
+    for (name, api_key) in api_keys_mut(&mut config.providers) {
+        if SecretRef::parse(api_key).is_none() {
+            continue;
+        }
+        *api_key = resolver.resolve(api_key).map_err(|e| {
+            SearchError::Config(format!(
+                "Could not resolve providers.{}.api_key: {}",
+                name, e
+            ))
+        })?;
+    }
+    Ok(())
+}
+
+/// API keys of enabled providers, by provider name
+pub(super) fn api_keys_mut(providers: &mut ProvidersConfig) -> Vec<(&'static str, &mut String)> {
+    let p = providers;
+    [
+        (
+            "brave",
+            p.brave.as_mut().map(|c| (c.enabled, &mut c.api_key)),
+        ),
+        (
+            "google",
+            p.google.as_mut().map(|c| (c.enabled, &mut c.api_key)),
+        ),
+        (
+            "tavily",
+            p.tavily.as_mut().map(|c| (c.enabled, &mut c.api_key)),
+        ),
+        (
+            "serper",
+            p.serper.as_mut().map(|c| (c.enabled, &mut c.api_key)),
+        ),
+        (
+            "firecrawl",
+            p.firecrawl.as_mut().map(|c| (c.enabled, &mut c.api_key)),
+        ),
+        (
+            "serpapi",
+            p.serpapi.as_mut().map(|c| (c.enabled, &mut c.api_key)),
+        ),
+        ("bing", p.bing.as_mut().map(|c| (c.enabled, &mut c.api_key))),
+    ]
+    .into_iter()
+    .filter_map(|(name, entry)| match entry {
+        Some((true, api_key)) => Some((name, api_key)),
+        _ => None,
+    })
+    .collect()
+}
+
+/// Resolves references, asking for the `age` passphrase at most once
+struct Resolver {
+    passphrase: Option<SecretString>,
+    /// Environment lookup, replaceable in tests
+    env: fn(&str) -> Option<String>,
+}
+
+impl Default for Resolver {
+    fn default() -> Self {
+        Self {
+            passphrase: None,
+            env: |var| std::env::var(var).ok(),
+        }
+    }
+}
+
+impl Resolver {
+    fn resolve(&mut self, value: &str) -> std::result::Result<String, String> {
+        let secret = match SecretRef::parse(value) {
+            Some(SecretRef::Env(var)) => {
+                (self.env)(var).ok_or_else(|| format!("environment variable {} is not set", var))?
+            }
+            Some(SecretRef::File(path)) => std::fs::read_to_string(expand_home(path))
+                .map_err(|e| format!("cannot read {}: {}", path, e))?,
+            Some(SecretRef::Command(command)) => run_command(command)?,
+            Some(SecretRef::Age(path)) => self.decrypt(path)?,
+            None => return Ok(value.to_string()),
+        };
+
+        let secret = secret.trim().to_string();
+        if secret.is_empty() {
+            return Err(format!("{} is empty", value.trim()));
+        }
+        Ok(secret)
+    }
+
+    fn decrypt(&mut self, path: &str) -> std::result::Result<String, String> {
+        let data =
+            std::fs::read(expand_home(path)).map_err(|e| format!("cannot read {}: {}", path, e))?;
+        let passphrase = self.passphrase(path)?;
+
+        let decryptor = age::Decryptor::new_buffered(&data[..])
+            .map_err(|e| format!("cannot decrypt {}: {}", path, e))?;
+        let identity = age::scrypt::Identity::new(passphrase);
+        let mut reader = decryptor
+            .decrypt(std::iter::once(&identity as &dyn age::Identity))
+            .map_err(|e| format!("cannot decrypt {}: {}", path, e))?;
+
+        let mut secret = String::new();
+        reader
+            .read_to_string(&mut secret)
+            .map_err(|e| format!("cannot decrypt {}: {}", path, e))?;
+        Ok(secret)
+    }
+
+    /// The passphrase from the environment, or asked for on a terminal
+    fn passphrase(&mut self, path: &str) -> std::result::Result<SecretString, String> {
+        if let Some(ref passphrase) = self.passphrase {
+            return Ok(passphrase.clone());
+        }
+
+        let passphrase = match (self.env)(PASSPHRASE_ENV) {
+            Some(passphrase) => passphrase,
+            None if std::io::stdin().is_terminal() && std::io::stderr().is_terminal() => {
+                dialoguer::Password::new()
+                    .with_prompt(format!("Passphrase for {}", path))
+                    .interact()
+                    .map_err(|e| e.to_string())?
+            }
+            None => {
+                return Err(format!(
+                    "{} needs a passphrase; set {}",
+                    path, PASSPHRASE_ENV
+                ))
+            }
+        };
+
+        let passphrase = SecretString::from(passphrase);
+        self.passphrase = Some(passphrase.clone());
+        Ok(passphrase)
+    }
+}
+
+/// Run a command through the shell and return its standard output
+fn run_command(command: &str) -> std::result::Result<String, String> {
+    #[cfg(windows)]
+    let output = Command::new("cmd").args(["/C", command]).output();
+    #[cfg(not(windows))]
+    let output = Command::new("sh").args(["-c", command]).output();
+
+    let output = output.map_err(|e| format!("cannot run `{}`: {}", command, e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!(
+            "`{}` failed ({}): {}",
+            command,
+            output.status,
+            stderr.trim()
+        ));
+    }
+    String::from_utf8(output.stdout).map_err(|_| format!("`{}` printed invalid UTF-8", command))
+}
+
+/// Expand a leading `~/` to the home directory
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), directories::BaseDirs::new()) {
+        (Some(rest), Some(dirs)) => dirs.home_dir().join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn brave(api_key: &str, enabled: bool) -> Config {
+        let mut config = Config::default();
+        config.providers.brave = Some(BraveConfig {
+            api_key: api_key.to_string(),
+            enabled,
+        });
+        config
+    }
+
+    fn brave_key(config: &Config) -> &str {
+        &config.providers.brave.as_ref().unwrap().api_key
+    }
+
+    #[test]
+    fn test_parse_references() {
+        assert_eq!(SecretRef::parse("env:BRAVE"), Some(SecretRef::Env("BRAVE")));
+        assert_eq!(
+            SecretRef::parse("cmd:pass show brave"),
+            Some(SecretRef::Command("pass show brave"))
+        );
+        assert_eq!(SecretRef::parse("file:"), None);
+        assert_eq!(SecretRef::parse("BSA-plain-key"), None);
+        assert_eq!(SecretRef::parse("https://example.com"), None);
+        // Prefixes always make a reference
+        assert_eq!(
+            SecretRef::parse("env:abc123"),
+            Some(SecretRef::Env("abc123"))
+        );
+
+        assert_eq!(
+            display_secret("file:/run/secrets/brave"),
+            "file:/run/secrets/brave"
+        );
+        assert_eq!(display_secret("abcd1234efgh5678"), "abcd...5678");
+    }
+
+    #[test]
+    fn test_resolve_env_and_file() {
+        let mut resolver = Resolver {
+            env: |var| (var == "BRAVE_KEY").then(|| "from-env\n".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(resolver.resolve("env:BRAVE_KEY").unwrap(), "from-env");
+        assert!(resolver.resolve("env:OTHER_KEY").is_err());
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(file, "from-file").unwrap();
+        let mut config = brave(&format!("file:{}", file.path().display()), true);
+        resolve_secrets(&mut config).unwrap();
+        assert_eq!(brave_key(&config), "from-file");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_resolve_command() {
+        let mut config = brave("cmd:printf from-cmd", true);
+        resolve_secrets(&mut config).unwrap();
+        assert_eq!(brave_key(&config), "from-cmd");
+
+        let mut config = brave("cmd:echo oops >&2; exit 3", true);
+        let err = resolve_secrets(&mut config).unwrap_err().to_string();
+        assert!(err.contains("providers.brave.api_key"));
+        assert!(err.contains("oops"));
+    }
+
+    #[test]
+    fn test_resolve_errors() {
+        let mut config = brave("env:CLI_WEB_SEARCH_TEST_UNSET", true);
+        let err = resolve_secrets(&mut config).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("environment variable CLI_WEB_SEARCH_TEST_UNSET is not set"));
+
+        // Disabled providers are left alone
+        let mut config = brave("env:CLI_WEB_SEARCH_TEST_UNSET", false);
+        resolve_secrets(&mut config).unwrap();
+        assert_eq!(brave_key(&config), "env:CLI_WEB_SEARCH_TEST_UNSET");
+    }
+
+    #[test]
+    fn test_resolve_age_file() {
+        let passphrase = SecretString::from("correct horse".to_string());
+        let mut recipient = age::scrypt::Recipient::new(passphrase.clone());
+        recipient.set_work_factor(2);
+        let encryptor =
+            age::Encryptor::with_recipients(std::iter::once(&recipient as &dyn age::Recipient))
+                .unwrap();
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        let mut writer = encryptor.wrap_output(file.as_file_mut()).unwrap();
+        writer.write_all(b"from-age\n").unwrap();
+        writer.finish().unwrap();
+
+        let reference = format!("age:{}", file.path().display());
+        let mut resolver = Resolver {
+            passphrase: Some(passphrase),
+            ..Default::default()
+        };
+        assert_eq!(resolver.resolve(&reference).unwrap(), "from-age");
+
+        let mut resolver = Resolver {
+            passphrase: Some(SecretString::from("wrong".to_string())),
+            ..Default::default()
+        };
+        assert!(resolver.resolve(&reference).is_err());
+    }
+}
@@ -0,0 +1,593 @@
+//! Strict configuration validation
+//!
+//! Serde skips keys it doesn't know, and most options are plain strings, so a
+//! misspelled key or provider name would otherwise be dropped without a word.
+//! The checks here find those mistakes, with the line they are on when the
+//! YAML source is at hand. Loading refuses configs with errors; `config
+//! doctor` reports warnings too.
+
+use super::effective::expected_values;
+use super::*;
+use crate::cli::{OutputFormat, SafeSearch};
+use crate::error::{Result, SearchError};
+use clap::ValueEnum;
+use serde_yaml::Value;
+use std::fmt;
+
+/// How serious a problem is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The config is refused
+    Error,
+    /// Accepted, but probably not what was meant
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => f.write_str("error"),
+            Severity::Warning => f.write_str("warning"),
+        }
+    }
+}
+
+/// A problem found in a config
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Key path the problem is about (empty for the file as a whole)
+    pub path: Vec<String>,
+    pub message: String,
+    /// Line in the file, when known
+    pub line: Option<usize>,
+}
+
+impl Diagnostic {
+    pub(super) fn error(path: &[&str], message: impl Into<String>) -> Self {
+        Self::new(Severity::Error, path, message)
+    }
+
+    pub(super) fn warning(path: &[&str], message: impl Into<String>) -> Self {
+        Self::new(Severity::Warning, path, message)
+    }
+
+    fn new(severity: Severity, path: &[&str], message: impl Into<String>) -> Self {
+        Self {
+            severity,
+            path: path.iter().map(|s| s.to_string()).collect(),
+            message: message.into(),
+            line: None,
+        }
+    }
+
+    /// The dotted key the problem is about
+    pub fn key(&self) -> String {
+        self.path.join(".")
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(line) = self.line {
+            write!(f, "line {}: ", line)?;
+        }
+        if !self.path.is_empty() {
+            write!(f, "{}: ", self.key())?;
+        }
+        f.write_str(&self.message)
+    }
+}
+
+/// What a config file holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileKind {
+    /// A full config (user or project file), which may define profiles
+    Config,
+    /// A profile file, which may name the profile it `inherits`
+    Profile,
+}
+
+/// Check a config file's contents
+///
+/// Covers YAML and type errors, unknown keys (in inline profiles too),
+/// unknown provider names and invalid option values.
+pub fn check_file(content: &str, kind: FileKind) -> Vec<Diagnostic> {
+    let value: Value = match serde_yaml::from_str(content) {
+        Ok(value) => value,
+        Err(e) => return vec![Diagnostic::error(&[], e.to_string())],
+    };
+    if value.is_null() {
+        return Vec::new();
+    }
+    // Unknown keys are ignored here, so `inherits` doesn't get in the way
+    let config: Config = match serde_yaml::from_str(content) {
+        Ok(config) => config,
+        Err(e) => return vec![Diagnostic::error(&[], e.to_string())],
+    };
+
+    let mut checker = Checker {
+        content,
+        diagnostics: Vec::new(),
+    };
+    match kind {
+        FileKind::Config => {
+            checker.check_keys(&value, &[], 0, &["profiles"]);
+            checker.report_all(&[], check_values(&config));
+            let profiles = value.get("profiles").and_then(Value::as_mapping);
+            for (name, profile) in profiles.into_iter().flatten() {
+                let name = name.as_str().unwrap_or_default().to_string();
+                checker.check_profile(profile, &["profiles".to_string(), name]);
+            }
+        }
+        FileKind::Profile => checker.check_profile(&value, &[]),
+    }
+    checker.diagnostics
+}
+
+/// Check a loaded config as a whole, after all layers are merged
+///
+/// On top of the per-file value checks, this finds providers that are named
+/// but not enabled, Google without a search engine ID, and enabled providers
+/// with an empty API key.
+pub fn check_config(config: &Config) -> Vec<Diagnostic> {
+    let mut diagnostics = check_values(config);
+    let known = provider_names();
+    let enabled = config.enabled_providers();
+
+    let named = config
+        .fallback_order
+        .iter()
+        .map(|name| ("fallback_order", name))
+        .chain(
+            config
+                .default_provider
+                .iter()
+                .map(|name| ("default_provider", name)),
+        );
+    for (key, name) in named {
+        if known.contains(name) && !enabled.contains(name) {
+            diagnostics.push(Diagnostic::warning(
+                &[key],
+                format!("{} is not configured or is disabled", name),
+            ));
+        }
+    }
+
+    if let Some(ref google) = config.providers.google {
+        if google.enabled && google.cx.trim().is_empty() {
+            diagnostics.push(Diagnostic::error(
+                &["providers", "google", "cx"],
+                "Google is enabled but has no search engine ID (cx)",
+            ));
+        }
+    }
+
+    let mut providers = config.providers.clone();
+    for (name, api_key) in secrets::api_keys_mut(&mut providers) {
+        if api_key.trim().is_empty() {
+            diagnostics.push(Diagnostic::error(
+                &["providers", name, "api_key"],
+                "API key is empty",
+            ));
+        }
+    }
+
+    diagnostics
+}
+
+/// Fail with every error in `diagnostics`, each prefixed with `source`
+pub fn ensure_valid(source: &str, diagnostics: &[Diagnostic]) -> Result<()> {
+    let errors: Vec<String> = diagnostics
+        .iter()
+        .filter(|d| d.is_error())
+        .map(|d| format!("{}: {}", source, d))
+        .collect();
+    match errors.is_empty() {
+        true => Ok(()),
+        false => Err(SearchError::Config(format!(
+            "{} (run `cli-web-search config doctor` for details)",
+            errors.join("; ")
+        ))),
+    }
+}
+
+/// Problems with values that don't depend on other layers
+fn check_values(config: &Config) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let known = provider_names();
+
+    if config.version > CONFIG_VERSION {
+        diagnostics.push(Diagnostic::error(
+            &["version"],
+            format!(
+                "version {} is newer than this cli-web-search supports ({})",
+                config.version, CONFIG_VERSION
+            ),
+        ));
+    }
+
+    for name in &config.fallback_order {
+        if !known.contains(name) {
+            diagnostics.push(Diagnostic::error(
+                &["fallback_order"],
+                unknown_provider(name, &known),
+            ));
+        }
+    }
+    if let Some(ref name) = config.default_provider {
+        if !known.contains(name) {
+            diagnostics.push(Diagnostic::error(
+                &["default_provider"],
+                unknown_provider(name, &known),
+            ));
+        }
+    }
+
+    let defaults = &config.defaults;
+    diagnostics.extend(check_enum::<SafeSearch>(
+        &["defaults", "safe_search"],
+        &defaults.safe_search,
+    ));
+    diagnostics.extend(check_enum::<OutputFormat>(
+        &["defaults", "format"],
+        &defaults.format,
+    ));
+    diagnostics
+}
+
+fn check_enum<T: ValueEnum>(path: &[&str], value: &str) -> Option<Diagnostic> {
+    match T::from_str(value, true) {
+        Ok(_) => None,
+        Err(_) => Some(Diagnostic::error(
+            path,
+            format!(
+                "invalid value '{}' (expected one of: {})",
+                value,
+                expected_values::<T>().join(", ")
+            ),
+        )),
+    }
+}
+
+fn provider_names() -> Vec<String> {
+    keys::section_keys(&["providers"])
+}
+
+fn unknown_provider(name: &str, known: &[String]) -> String {
+    match suggest(name, known) {
+        Some(similar) => format!("unknown provider '{}' (did you mean {}?)", name, similar),
+        None => format!(
+            "unknown provider '{}' (known providers: {})",
+            name,
+            known.join(", ")
+        ),
+    }
+}
+
+/// Collects diagnostics for one file, locating each in the source
+struct Checker<'a> {
+    content: &'a str,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Checker<'_> {
+    /// Add diagnostics found under `prefix` in the file
+    fn report_all(&mut self, prefix: &[String], diagnostics: Vec<Diagnostic>) {
+        for mut diagnostic in diagnostics {
+            let mut path = prefix.to_vec();
+            path.append(&mut diagnostic.path);
+            diagnostic.line = line_of(self.content, &path);
+            diagnostic.path = path;
+            self.diagnostics.push(diagnostic);
+        }
+    }
+
+    fn report(&mut self, path: &[String], diagnostic: Diagnostic) {
+        self.report_all(path, vec![diagnostic]);
+    }
+
+    /// Check a profile (a partial config) found at `prefix`
+    fn check_profile(&mut self, value: &Value, prefix: &[String]) {
+        if value.is_null() {
+            return;
+        }
+        let Some(map) = value.as_mapping() else {
+            self.report(
+                prefix,
+                Diagnostic::error(&[], "a profile must be a mapping of config keys"),
+            );
+            return;
+        };
+
+        let mut stripped = map.clone();
+        match stripped.remove("inherits") {
+            Some(Value::String(_)) | Some(Value::Null) | None => {}
+            Some(_) => self.report(
+                prefix,
+                Diagnostic::error(&["inherits"], "must be a profile name"),
+            ),
+        }
+        if stripped.remove("profiles").is_some() {
+            self.report(
+                prefix,
+                Diagnostic::warning(
+                    &["profiles"],
+                    "profiles can't be defined inside a profile; ignored",
+                ),
+            );
+        }
+
+        match serde_yaml::from_value::<Config>(Value::Mapping(stripped)) {
+            Ok(config) => {
+                self.check_keys(value, prefix, prefix.len(), &["inherits", "profiles"]);
+                self.report_all(prefix, check_values(&config));
+            }
+            Err(e) => self.report(prefix, Diagnostic::error(&[], e.to_string())),
+        }
+    }
+
+    /// Report keys under `path` that aren't part of the config
+    ///
+    /// `base` is where the config root is in `path` (inline profiles sit
+    /// below `profiles.<name>`); `skip` lists top-level keys checked
+    /// elsewhere.
+    fn check_keys(&mut self, value: &Value, path: &[String], base: usize, skip: &[&str]) {
+        let Some(map) = value.as_mapping() else {
+            return;
+        };
+        for (key, child) in map {
+            let Some(name) = key.as_str() else {
+                self.report(path, Diagnostic::error(&[], "keys must be strings"));
+                continue;
+            };
+            if skip.contains(&name) {
+                continue;
+            }
+
+            let mut child_path = path.to_vec();
+            child_path.push(name.to_string());
+            let schema: Vec<&str> = child_path[base..].iter().map(String::as_str).collect();
+            if !keys::is_known_path(&schema) {
+                let siblings = keys::section_keys(&schema[..schema.len() - 1]);
+                let message = match suggest(name, &siblings) {
+                    Some(similar) => format!("unknown key (did you mean {}?)", similar),
+                    None => "unknown key".to_string(),
+                };
+                self.report(&child_path, Diagnostic::error(&[], message));
+                continue;
+            }
+            if child.is_mapping() {
+                self.check_keys(child, &child_path, base, &[]);
+            }
+        }
+    }
+}
+
+/// The line a key path is defined on
+///
+/// Follows block-style indentation; for keys inside flow mappings
+/// (`brave: {api_key: x}`) the line of the closest enclosing key is given.
+fn line_of(content: &str, path: &[String]) -> Option<usize> {
+    let mut stack: Vec<(usize, String)> = Vec::new();
+    let mut found = None;
+    let mut found_depth = 0;
+
+    for (number, line) in content.lines().enumerate() {
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with('-') {
+            continue;
+        }
+        let Some(key) = line_key(trimmed) else {
+            continue;
+        };
+        let indent = line.len() - trimmed.len();
+        while stack.last().is_some_and(|(i, _)| *i >= indent) {
+            stack.pop();
+        }
+        stack.push((indent, key));
+
+        let matches = stack.len() <= path.len()
+            && stack
+                .iter()
+                .zip(path)
+                .all(|((_, key), segment)| key == segment);
+        if matches && stack.len() > found_depth {
+            found = Some(number + 1);
+            found_depth = stack.len();
+            if found_depth == path.len() {
+                break;
+            }
+        }
+    }
+    found
+}
+
+/// The key a `key: value` line defines
+fn line_key(line: &str) -> Option<String> {
+    for quote in ['"', '\''] {
+        if let Some(rest) = line.strip_prefix(quote) {
+            let end = rest.find(quote)?;
+            return rest[end + 1..]
+                .trim_start()
+                .starts_with(':')
+                .then(|| rest[..end].to_string());
+        }
+    }
+    let end = line
+        .find(": ")
+        .or_else(|| line.strip_suffix(':').map(str::len))?;
+    Some(line[..end].trim_end().to_string())
+}
+
+/// The candidate closest to `name`, if it looks like a typo of it
+fn suggest<'a>(name: &str, candidates: &'a [String]) -> Option<&'a str> {
+    candidates
+        .iter()
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= 2 && *distance < name.len())
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate.as_str())
+}
+
+/// Levenshtein distance, counting a swap of neighbours as one edit
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut rows = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in rows[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut best = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = best;
+        }
+    }
+    rows[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn described(diagnostics: &[Diagnostic]) -> Vec<String> {
+        diagnostics.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_unknown_keys_with_lines() {
+        let content = "\
+provders:
+  brave:
+    api_key: x
+defaults:
+  num_results: 5
+  safe_serch: strict
+fetch:
+  credentials:
+    docs.example.com:
+      bearer_token: t
+      header: {}
+";
+        let diagnostics = check_file(content, FileKind::Config);
+        assert_eq!(
+            described(&diagnostics),
+            vec![
+                "line 1: provders: unknown key (did you mean providers?)",
+                "line 6: defaults.safe_serch: unknown key (did you mean safe_search?)",
+                "line 11: fetch.credentials.docs.example.com.header: unknown key",
+            ]
+        );
+        assert!(diagnostics.iter().all(Diagnostic::is_error));
+    }
+
+    #[test]
+    fn test_values() {
+        let content = "\
+fallback_order: [brave, brvae]
+default_provider: nope
+defaults:
+  safe_search: maybe
+";
+        let diagnostics = check_file(content, FileKind::Config);
+        assert_eq!(
+            described(&diagnostics),
+            vec![
+                "line 1: fallback_order: unknown provider 'brvae' (did you mean brave?)",
+                "line 2: default_provider: unknown provider 'nope' (known providers: brave, \
+                 google, duckduckgo, tavily, serper, firecrawl, serpapi, bing)",
+                "line 4: defaults.safe_search: invalid value 'maybe' (expected one of: off, \
+                 moderate, strict)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_syntax_and_type_errors() {
+        let diagnostics = check_file("providers: [brave\n", FileKind::Config);
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].is_error());
+
+        let diagnostics = check_file("defaults:\n  num_results: lots\n", FileKind::Config);
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].message.contains("num_results"));
+
+        assert!(check_file("", FileKind::Config).is_empty());
+    }
+
+    #[test]
+    fn test_profiles() {
+        let content = "\
+profiles:
+  ci:
+    inherits: team
+    fallback_order: [duckduckgo]
+    cache:
+      enabeld: false
+";
+        assert_eq!(
+            described(&check_file(content, FileKind::Config)),
+            vec!["line 6: profiles.ci.cache.enabeld: unknown key (did you mean enabled?)"]
+        );
+
+        let profile = "inherits: ci\ndefault_provider: bravee\n";
+        assert_eq!(
+            described(&check_file(profile, FileKind::Profile)),
+            vec!["line 2: default_provider: unknown provider 'bravee' (did you mean brave?)"]
+        );
+        // `inherits` only means something in a profile
+        assert_eq!(check_file(profile, FileKind::Config)[0].key(), "inherits");
+    }
+
+    #[test]
+    fn test_check_config() {
+        let config: Config = serde_yaml::from_str(
+            "\
+fallback_order: [brave, tavily]
+providers:
+  brave: {api_key: ''}
+  google: {api_key: g}
+  tavily: {api_key: t, enabled: false}
+",
+        )
+        .unwrap();
+        let diagnostics = check_config(&config);
+        let keys: Vec<(Severity, String)> =
+            diagnostics.iter().map(|d| (d.severity, d.key())).collect();
+        assert_eq!(
+            keys,
+            vec![
+                (Severity::Warning, "fallback_order".to_string()),
+                (Severity::Error, "providers.google.cx".to_string()),
+                (Severity::Error, "providers.brave.api_key".to_string()),
+            ]
+        );
+
+        let err = ensure_valid("configuration", &diagnostics).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("configuration: providers.google.cx: Google is enabled"));
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("brvae", "brave"), 1);
+        assert_eq!(edit_distance("brvea", "brave"), 2);
+        assert_eq!(edit_distance("provders", "providers"), 1);
+        assert_eq!(suggest("ab", &["xy".to_string()]), None);
+    }
+}
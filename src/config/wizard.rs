@@ -0,0 +1,573 @@
+//! `config init` setup wizard
+//!
+//! On a terminal the wizard walks through each provider, checks new API keys
+//! against the provider's API, and asks for the fallback order and search
+//! defaults. Flags pre-answer prompts; without a terminal (or with
+//! `--non-interactive`) only the flags are applied. Either way, the changes
+//! are shown as a diff before the file is written.
+
+use super::*;
+use crate::cli::InitArgs;
+use crate::error::{Result, SearchError};
+use clap::ValueEnum;
+use dialoguer::{Confirm, Input, Password, Select};
+use similar::{ChangeTag, TextDiff};
+use std::io::{IsTerminal, Write};
+
+/// A provider the wizard knows how to set up
+struct ProviderInfo {
+    name: &'static str,
+    label: &'static str,
+    /// Where to get an API key (`None` for providers without one)
+    signup_url: Option<&'static str>,
+}
+
+/// Supported providers, in the order they are offered
+const PROVIDERS: &[ProviderInfo] = &[
+    ProviderInfo {
+        name: "brave",
+        label: "Brave Search",
+        signup_url: Some("https://brave.com/search/api/"),
+    },
+    ProviderInfo {
+        name: "google",
+        label: "Google Custom Search",
+        signup_url: Some("https://developers.google.com/custom-search/v1/overview"),
+    },
+    ProviderInfo {
+        name: "duckduckgo",
+        label: "DuckDuckGo",
+        signup_url: None,
+    },
+    ProviderInfo {
+        name: "tavily",
+        label: "Tavily",
+        signup_url: Some("https://tavily.com/"),
+    },
+    ProviderInfo {
+        name: "serper",
+        label: "Serper",
+        signup_url: Some("https://serper.dev/"),
+    },
+    ProviderInfo {
+        name: "firecrawl",
+        label: "Firecrawl",
+        signup_url: Some("https://firecrawl.dev/"),
+    },
+    ProviderInfo {
+        name: "serpapi",
+        label: "SerpAPI",
+        signup_url: Some("https://serpapi.com/"),
+    },
+    ProviderInfo {
+        name: "bing",
+        label: "Bing Web Search",
+        signup_url: Some("https://azure.microsoft.com/en-us/products/ai-services/ai-search"),
+    },
+];
+
+const SAFE_SEARCH_LEVELS: &[&str] = &["off", "moderate", "strict"];
+
+/// Run `config init`; returns whether the configuration file was written
+pub async fn init_config(args: &InitArgs, options: &LoadOptions) -> Result<bool> {
+    let path = options.user_config_path()?;
+    let current = load_config_file(&path)?;
+    let interactive =
+        !args.non_interactive && std::io::stdin().is_terminal() && std::io::stdout().is_terminal();
+
+    let mut config = current.clone();
+    apply_init_args(&mut config, args)?;
+
+    if interactive {
+        prompt_providers(&mut config, args).await?;
+        prompt_search_settings(&mut config, args)?;
+    } else if !args.skip_validation {
+        let mut names: Vec<&str> = args.api_keys.iter().map(|(p, _)| p.as_str()).collect();
+        if args.google_cx.is_some() && !names.contains(&"google") {
+            names.push("google");
+        }
+        for name in names {
+            validate_provider(&config, name).await?;
+        }
+    }
+
+    let diff = render_diff(&current, &config);
+    if diff.is_empty() && path.exists() {
+        println!("No changes to {}", path.display());
+        return Ok(false);
+    }
+    if !diff.is_empty() {
+        println!("\nChanges to {}:\n{}", path.display(), diff);
+    }
+
+    if interactive && !args.yes {
+        let write = Confirm::new()
+            .with_prompt("Write this configuration?")
+            .default(true)
+            .interact()
+            .map_err(prompt_error)?;
+        if !write {
+            println!("Configuration not written.");
+            return Ok(false);
+        }
+    }
+
+    save_config(&config, &path)?;
+    Ok(true)
+}
+
+/// Apply the values given as flags
+fn apply_init_args(config: &mut Config, args: &InitArgs) -> Result<()> {
+    for (provider, api_key) in &args.api_keys {
+        set_api_key(&mut config.providers, provider, api_key.clone())?;
+    }
+
+    if let Some(ref cx) = args.google_cx {
+        let google = config.providers.google.get_or_insert_with(|| GoogleConfig {
+            api_key: String::new(),
+            cx: String::new(),
+            enabled: true,
+        });
+        google.cx = cx.clone();
+    }
+
+    if args.duckduckgo {
+        config.providers.duckduckgo = Some(DuckDuckGoConfig { enabled: true });
+    }
+
+    if let Some(ref order) = args.fallback_order {
+        for name in order {
+            check_provider_name(name)?;
+        }
+        config.fallback_order = order.clone();
+    }
+
+    if let Some(ref provider) = args.default_provider {
+        check_provider_name(provider)?;
+        config.default_provider = Some(provider.clone());
+    }
+
+    if let Some(num_results) = args.num_results {
+        config.defaults.num_results = num_results;
+    }
+    if let Some(ref safe_search) = args.safe_search {
+        if let Some(value) = safe_search.to_possible_value() {
+            config.defaults.safe_search = value.get_name().to_string();
+        }
+    }
+    if let Some(timeout) = args.timeout {
+        config.defaults.timeout = timeout;
+    }
+
+    Ok(())
+}
+
+/// Offer each provider in turn, asking for (and checking) its API key
+async fn prompt_providers(config: &mut Config, args: &InitArgs) -> Result<()> {
+    println!("Supported providers:\n");
+    for info in PROVIDERS {
+        let status = if is_configured(config, info.name) {
+            "configured"
+        } else {
+            "not configured"
+        };
+        println!("  {:<11} {:<22} [{}]", info.name, info.label, status);
+    }
+    println!();
+
+    for info in PROVIDERS {
+        // Keys passed as flags are not asked for again
+        if args.api_keys.iter().any(|(p, _)| p == info.name) {
+            continue;
+        }
+
+        let configured = is_configured(config, info.name);
+        let setup = Confirm::new()
+            .with_prompt(format!("Configure {}?", info.label))
+            .default(configured)
+            .interact()
+            .map_err(prompt_error)?;
+        if !setup {
+            continue;
+        }
+
+        let signup_url = match info.signup_url {
+            Some(url) => url,
+            None => {
+                config.providers.duckduckgo = Some(DuckDuckGoConfig { enabled: true });
+                continue;
+            }
+        };
+
+        loop {
+            let mut prompt = format!("{} API key (from {})", info.label, signup_url);
+            if configured {
+                prompt.push_str(" [empty keeps the current key]");
+            }
+            let api_key = Password::new()
+                .with_prompt(prompt)
+                .allow_empty_password(configured)
+                .interact()
+                .map_err(prompt_error)?;
+            let kept_current = api_key.is_empty();
+            if !kept_current {
+                set_api_key(&mut config.providers, info.name, api_key)?;
+            }
+
+            if info.name == "google" {
+                let current = config
+                    .providers
+                    .google
+                    .as_ref()
+                    .map(|g| g.cx.clone())
+                    .unwrap_or_default();
+                let cx: String = Input::new()
+                    .with_prompt("Google Custom Search Engine ID (cx)")
+                    .with_initial_text(current)
+                    .interact_text()
+                    .map_err(prompt_error)?;
+                if let Some(ref mut google) = config.providers.google {
+                    google.cx = cx;
+                }
+            }
+
+            if args.skip_validation || (kept_current && info.name != "google") {
+                break;
+            }
+
+            print!("  Checking {} API key... ", info.name);
+            // Show the line before the request, not once it finishes
+            std::io::stdout().flush()?;
+            match validate_provider(config, info.name).await {
+                Ok(()) => {
+                    println!("valid");
+                    break;
+                }
+                Err(e) => {
+                    println!("failed: {}", e);
+                    let retry = Confirm::new()
+                        .with_prompt("Enter the key again?")
+                        .default(true)
+                        .interact()
+                        .map_err(prompt_error)?;
+                    if !retry {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Ask for fallback order, default provider and search defaults
+fn prompt_search_settings(config: &mut Config, args: &InitArgs) -> Result<()> {
+    let configured: Vec<&str> = PROVIDERS
+        .iter()
+        .map(|p| p.name)
+        .filter(|name| is_configured(config, name))
+        .collect();
+
+    if configured.is_empty() {
+        println!("No providers configured; searches will fail until one is set up.");
+    }
+
+    if args.fallback_order.is_none() && configured.len() > 1 {
+        let current: Vec<&str> = config
+            .fallback_order
+            .iter()
+            .map(String::as_str)
+            .filter(|name| configured.contains(name))
+            .collect();
+        let initial = if current.is_empty() {
+            configured.join(",")
+        } else {
+            current.join(",")
+        };
+        let order: String = Input::new()
+            .with_prompt("Fallback order (comma-separated)")
+            .with_initial_text(initial)
+            .validate_with(|input: &String| -> std::result::Result<(), String> {
+                parse_provider_list(input).map(|_| ())
+            })
+            .interact_text()
+            .map_err(prompt_error)?;
+        config.fallback_order = parse_provider_list(&order).map_err(SearchError::Config)?;
+    }
+
+    if args.default_provider.is_none() && !configured.is_empty() {
+        let mut items = vec!["(first in fallback order)"];
+        items.extend(configured.iter().copied());
+        let current = config
+            .default_provider
+            .as_deref()
+            .and_then(|p| items.iter().position(|item| *item == p))
+            .unwrap_or(0);
+        let choice = Select::new()
+            .with_prompt("Default provider")
+            .items(&items)
+            .default(current)
+            .interact()
+            .map_err(prompt_error)?;
+        config.default_provider = (choice > 0).then(|| items[choice].to_string());
+    }
+
+    if args.num_results.is_none() {
+        config.defaults.num_results = Input::new()
+            .with_prompt("Default number of results")
+            .default(config.defaults.num_results)
+            .interact_text()
+            .map_err(prompt_error)?;
+    }
+
+    if args.safe_search.is_none() {
+        let current = SAFE_SEARCH_LEVELS
+            .iter()
+            .position(|level| *level == config.defaults.safe_search)
+            .unwrap_or(1);
+        let choice = Select::new()
+            .with_prompt("Default safe search level")
+            .items(SAFE_SEARCH_LEVELS)
+            .default(current)
+            .interact()
+            .map_err(prompt_error)?;
+        config.defaults.safe_search = SAFE_SEARCH_LEVELS[choice].to_string();
+    }
+
+    if args.timeout.is_none() {
+        config.defaults.timeout = Input::new()
+            .with_prompt("Default timeout (seconds)")
+            .default(config.defaults.timeout)
+            .interact_text()
+            .map_err(prompt_error)?;
+    }
+
+    Ok(())
+}
+
+/// Check a provider's credentials against its API
+async fn validate_provider(config: &Config, name: &str) -> Result<()> {
+    let registry = crate::providers::build_registry(config)?;
+    let provider = registry
+        .get(name)
+        .ok_or_else(|| SearchError::Config(format!("Provider {} is not configured", name)))?;
+
+    match provider.validate_api_key().await? {
+        true => Ok(()),
+        false => Err(SearchError::Config(format!(
+            "Provider {} is missing required settings",
+            name
+        ))),
+    }
+}
+
+/// Set a provider's API key, creating and enabling its section
+fn set_api_key(providers: &mut ProvidersConfig, name: &str, api_key: String) -> Result<()> {
+    match name {
+        "brave" => {
+            providers.brave = Some(BraveConfig {
+                api_key,
+                enabled: true,
+            })
+        }
+        "google" => {
+            let cx = providers.google.take().map(|g| g.cx).unwrap_or_default();
+            providers.google = Some(GoogleConfig {
+                api_key,
+                cx,
+                enabled: true,
+            });
+        }
+        "tavily" => {
+            providers.tavily = Some(TavilyConfig {
+                api_key,
+                enabled: true,
+            })
+        }
+        "serper" => {
+            providers.serper = Some(SerperConfig {
+                api_key,
+                enabled: true,
+            })
+        }
+        "firecrawl" => {
+            providers.firecrawl = Some(FirecrawlConfig {
+                api_key,
+                enabled: true,
+            })
+        }
+        "serpapi" => {
+            providers.serpapi = Some(SerpApiConfig {
+                api_key,
+                enabled: true,
+            })
+        }
+        "bing" => {
+            providers.bing = Some(BingConfig {
+                api_key,
+                enabled: true,
+            })
+        }
+        "duckduckgo" => {
+            return Err(SearchError::Config(
+                "DuckDuckGo does not use an API key; pass --duckduckgo instead".to_string(),
+            ))
+        }
+        _ => return Err(unknown_provider(name)),
+    }
+    Ok(())
+}
+
+/// Whether a provider has an enabled section
+fn is_configured(config: &Config, name: &str) -> bool {
+    config.enabled_providers().iter().any(|p| p == name)
+}
+
+fn check_provider_name(name: &str) -> Result<()> {
+    if PROVIDERS.iter().any(|p| p.name == name) {
+        Ok(())
+    } else {
+        Err(unknown_provider(name))
+    }
+}
+
+fn unknown_provider(name: &str) -> SearchError {
+    let names: Vec<&str> = PROVIDERS.iter().map(|p| p.name).collect();
+    SearchError::Config(format!(
+        "Unknown provider '{}'. Supported providers: {}",
+        name,
+        names.join(", ")
+    ))
+}
+
+fn parse_provider_list(input: &str) -> std::result::Result<Vec<String>, String> {
+    input
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| {
+            check_provider_name(name)
+                .map(|_| name.to_string())
+                .map_err(|e| e.to_string())
+        })
+        .collect()
+}
+
+/// Line diff of the flattened settings, with secrets masked
+fn render_diff(old: &Config, new: &Config) -> String {
+    fn lines(config: &Config) -> String {
+        let mut entries: Vec<String> = config
+            .to_flat_map()
+            .into_iter()
+            .map(|(key, value)| format!("{}: {}\n", key, value))
+            .collect();
+        entries.sort();
+        entries.concat()
+    }
+
+    let (old, new) = (lines(old), lines(new));
+    let diff = TextDiff::from_lines(&old, &new);
+    let mut output = String::new();
+    for change in diff.iter_all_changes() {
+        let sign = match change.tag() {
+            ChangeTag::Delete => "-",
+            ChangeTag::Insert => "+",
+            ChangeTag::Equal => continue,
+        };
+        output.push_str(&format!("{} {}", sign, change));
+    }
+    output
+}
+
+fn prompt_error(e: dialoguer::Error) -> SearchError {
+    match e {
+        dialoguer::Error::IO(e) => SearchError::Io(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::SafeSearch;
+
+    #[test]
+    fn test_apply_init_args() {
+        let args = InitArgs {
+            api_keys: vec![
+                ("brave".to_string(), "BSA-key-1234".to_string()),
+                ("google".to_string(), "AIza-key".to_string()),
+            ],
+            google_cx: Some("engine-id".to_string()),
+            duckduckgo: true,
+            fallback_order: Some(vec!["brave".to_string(), "duckduckgo".to_string()]),
+            default_provider: Some("brave".to_string()),
+            num_results: Some(5),
+            safe_search: Some(SafeSearch::Strict),
+            timeout: Some(10),
+            ..Default::default()
+        };
+
+        let mut config = Config::default();
+        apply_init_args(&mut config, &args).unwrap();
+
+        assert_eq!(config.providers.brave.unwrap().api_key, "BSA-key-1234");
+        let google = config.providers.google.unwrap();
+        assert_eq!(google.api_key, "AIza-key");
+        assert_eq!(google.cx, "engine-id");
+        assert!(config.providers.duckduckgo.unwrap().enabled);
+        assert_eq!(config.fallback_order, vec!["brave", "duckduckgo"]);
+        assert_eq!(config.default_provider.as_deref(), Some("brave"));
+        assert_eq!(config.defaults.num_results, 5);
+        assert_eq!(config.defaults.safe_search, "strict");
+        assert_eq!(config.defaults.timeout, 10);
+    }
+
+    #[test]
+    fn test_apply_init_args_rejects_unknown_provider() {
+        let mut config = Config::default();
+        let args = InitArgs {
+            api_keys: vec![("altavista".to_string(), "key".to_string())],
+            ..Default::default()
+        };
+        assert!(matches!(
+            apply_init_args(&mut config, &args),
+            Err(SearchError::Config(msg)) if msg.contains("altavista")
+        ));
+
+        let args = InitArgs {
+            fallback_order: Some(vec!["brave".to_string(), "yahoo".to_string()]),
+            ..Default::default()
+        };
+        assert!(apply_init_args(&mut config, &args).is_err());
+    }
+
+    #[test]
+    fn test_render_diff_masks_keys() {
+        let old = Config::default();
+        let mut new = old.clone();
+        set_api_key(
+            &mut new.providers,
+            "tavily",
+            "tvly-secret-key-5678".to_string(),
+        )
+        .unwrap();
+        new.defaults.num_results = 20;
+
+        let diff = render_diff(&old, &new);
+        assert!(diff.contains("+ providers.tavily.api_key: tvly...5678"));
+        assert!(diff.contains("- defaults.num_results: 10"));
+        assert!(diff.contains("+ defaults.num_results: 20"));
+        assert!(!diff.contains("tvly-secret-key-5678"));
+        assert!(render_diff(&old, &old).is_empty());
+    }
+
+    #[test]
+    fn test_parse_provider_list() {
+        assert_eq!(
+            parse_provider_list("brave, tavily,").unwrap(),
+            vec!["brave", "tavily"]
+        );
+        assert!(parse_provider_list("brave,nope").is_err());
+    }
+}
@@ -1,7 +1,11 @@
 //! Error types for cli-web-search
 
+use crate::fetch::PolicyViolation;
 use thiserror::Error;
 
+/// Provider error codes for a query that failed validation (Brave, Bing)
+const QUERY_VALIDATION_CODES: &[&str] = &["VALIDATION", "InvalidRequest"];
+
 /// Main error type for the application
 #[derive(Error, Debug)]
 pub enum SearchError {
@@ -10,8 +14,15 @@ pub enum SearchError {
     Network(#[from] reqwest::Error),
 
     /// API errors from search providers
-    #[error("API error from {provider}: {message}")]
-    Api { provider: String, message: String },
+    #[error("API error from {provider}: {}{message}", .status.map(|s| format!("HTTP {}: ", s)).unwrap_or_default())]
+    Api {
+        provider: String,
+        message: String,
+        /// HTTP status code, when the error came from a response
+        status: Option<u16>,
+        /// Provider-specific error code from the response body
+        code: Option<String>,
+    },
 
     /// Rate limit exceeded
     #[error("Rate limited by {provider}{}", .retry_after.map(|s| format!(", retry after {} seconds", s)).unwrap_or_default())]
@@ -20,6 +31,13 @@ pub enum SearchError {
         retry_after: Option<u64>,
     },
 
+    /// Request quota exhausted until the next period
+    #[error("Quota exceeded for {provider}{}", .reset_after.map(|s| format!(", resets in {} seconds", s)).unwrap_or_default())]
+    QuotaExceeded {
+        provider: String,
+        reset_after: Option<u64>,
+    },
+
     /// Invalid or missing API key
     #[error("Invalid API key for {provider}")]
     InvalidApiKey { provider: String },
@@ -57,21 +75,274 @@ pub enum SearchError {
     #[error("No search providers configured. Run `cli-web-search config init` to set up.")]
     NoProvidersConfigured,
 
-    /// All providers failed
-    #[error("All providers failed. Last error: {0}")]
-    AllProvidersFailed(String),
+    /// All providers failed, with the final error from each
+    #[error("All providers failed: {}", join_errors(.0))]
+    AllProvidersFailed(Vec<SearchError>),
+
+    /// Provider skipped because its circuit breaker is open
+    #[error("Circuit open for {provider} after repeated failures{}", .retry_after.map(|s| format!(", retry after {} seconds", s)).unwrap_or_default())]
+    CircuitOpen {
+        provider: String,
+        retry_after: Option<u64>,
+    },
 
     /// Timeout
     #[error("Request timed out after {0} seconds")]
     Timeout(u64),
+
+    /// URL rejected by the fetch network policy
+    #[error("Blocked by network policy: {0}")]
+    PolicyViolation(#[from] PolicyViolation),
+
+    /// Missing or invalid command-line input
+    #[error("{0}")]
+    InvalidInput(String),
+}
+
+/// Error category, stable across releases
+///
+/// Each kind has a snake_case name used in JSON output and `retry_on`, and
+/// an exit code; see the README for the full table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    Network,
+    InvalidResponse,
+    Timeout,
+    ServerError,
+    ClientError,
+    Api,
+    RateLimited,
+    QuotaExceeded,
+    InvalidApiKey,
+    MissingApiKey,
+    Config,
+    Io,
+    Yaml,
+    Json,
+    Url,
+    UnknownProvider,
+    NoProvidersConfigured,
+    AllProvidersFailed,
+    CircuitOpen,
+    PolicyViolation,
+    InvalidInput,
+}
+
+impl ErrorKind {
+    /// Short, stable name of the category
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Network => "network",
+            Self::InvalidResponse => "invalid_response",
+            Self::Timeout => "timeout",
+            Self::ServerError => "server_error",
+            Self::ClientError => "client_error",
+            Self::Api => "api",
+            Self::RateLimited => "rate_limited",
+            Self::QuotaExceeded => "quota_exceeded",
+            Self::InvalidApiKey => "invalid_api_key",
+            Self::MissingApiKey => "missing_api_key",
+            Self::Config => "config",
+            Self::Io => "io",
+            Self::Yaml => "yaml",
+            Self::Json => "json",
+            Self::Url => "url",
+            Self::UnknownProvider => "unknown_provider",
+            Self::NoProvidersConfigured => "no_providers_configured",
+            Self::AllProvidersFailed => "all_providers_failed",
+            Self::CircuitOpen => "circuit_open",
+            Self::PolicyViolation => "policy_violation",
+            Self::InvalidInput => "invalid_input",
+        }
+    }
+
+    /// Stable process exit code for the category
+    pub fn exit_code(self) -> i32 {
+        match self {
+            Self::Config | Self::Yaml | Self::UnknownProvider => 3,
+            Self::NoProvidersConfigured => 4,
+            Self::InvalidApiKey | Self::MissingApiKey => 5,
+            Self::RateLimited => 6,
+            Self::QuotaExceeded => 7,
+            Self::Network | Self::InvalidResponse => 8,
+            Self::Timeout => 9,
+            Self::ServerError | Self::Api => 10,
+            Self::ClientError => 11,
+            Self::AllProvidersFailed | Self::CircuitOpen => 12,
+            Self::PolicyViolation => 13,
+            Self::Io | Self::Json | Self::Url => 1,
+            Self::InvalidInput => 2,
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
 }
 
 impl SearchError {
+    /// Category of the error
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Network(e) if e.is_timeout() => ErrorKind::Timeout,
+            Self::Network(e) if e.is_decode() => ErrorKind::InvalidResponse,
+            Self::Network(_) => ErrorKind::Network,
+            Self::Api {
+                status: Some(408), ..
+            } => ErrorKind::Timeout,
+            Self::Api {
+                status: Some(500..=599),
+                ..
+            } => ErrorKind::ServerError,
+            Self::Api {
+                status: Some(400..=499),
+                ..
+            } => ErrorKind::ClientError,
+            Self::Api { .. } => ErrorKind::Api,
+            Self::RateLimited { .. } => ErrorKind::RateLimited,
+            Self::QuotaExceeded { .. } => ErrorKind::QuotaExceeded,
+            Self::InvalidApiKey { .. } => ErrorKind::InvalidApiKey,
+            Self::MissingApiKey { .. } => ErrorKind::MissingApiKey,
+            Self::Config(_) => ErrorKind::Config,
+            Self::Io(_) => ErrorKind::Io,
+            Self::Yaml(_) => ErrorKind::Yaml,
+            Self::Json(_) => ErrorKind::Json,
+            Self::Url(_) => ErrorKind::Url,
+            Self::UnknownProvider(_) => ErrorKind::UnknownProvider,
+            Self::NoProvidersConfigured => ErrorKind::NoProvidersConfigured,
+            Self::AllProvidersFailed(_) => ErrorKind::AllProvidersFailed,
+            Self::CircuitOpen { .. } => ErrorKind::CircuitOpen,
+            Self::Timeout(_) => ErrorKind::Timeout,
+            Self::PolicyViolation(_) => ErrorKind::PolicyViolation,
+            Self::InvalidInput(_) => ErrorKind::InvalidInput,
+        }
+    }
+
+    /// Stable process exit code for the error category
+    ///
+    /// Scripts can rely on these values; see the README for the full table.
+    pub fn exit_code(&self) -> i32 {
+        self.kind().exit_code()
+    }
+
+    /// Provider the error came from, if it is tied to one
+    pub fn provider(&self) -> Option<&str> {
+        match self {
+            Self::Api { provider, .. }
+            | Self::RateLimited { provider, .. }
+            | Self::QuotaExceeded { provider, .. }
+            | Self::CircuitOpen { provider, .. }
+            | Self::InvalidApiKey { provider }
+            | Self::MissingApiKey { provider, .. } => Some(provider),
+            Self::UnknownProvider(provider) => Some(provider),
+            _ => None,
+        }
+    }
+
+    /// Seconds to wait before the request can succeed, if known
+    pub fn retry_after(&self) -> Option<u64> {
+        match self {
+            Self::RateLimited { retry_after, .. } => *retry_after,
+            Self::QuotaExceeded { reset_after, .. } => *reset_after,
+            Self::CircuitOpen { retry_after, .. } => *retry_after,
+            // The soonest any provider becomes usable again
+            Self::AllProvidersFailed(errors) => errors.iter().filter_map(|e| e.retry_after()).min(),
+            _ => None,
+        }
+    }
+
+    /// HTTP status code of a failed provider response
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            Self::Api { status, .. } => *status,
+            Self::RateLimited { .. } => Some(429),
+            Self::Network(e) => e.status().map(|s| s.as_u16()),
+            _ => None,
+        }
+    }
+
+    /// Provider-specific error code, if the response carried one
+    pub fn code(&self) -> Option<&str> {
+        match self {
+            Self::Api { code, .. } => code.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Whether the same request may succeed if repeated
+    ///
+    /// Connection failures, timeouts, rate limits and 5xx responses are
+    /// transient; client errors, bad credentials and exhausted quotas are not.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self.kind(),
+            ErrorKind::Network
+                | ErrorKind::Timeout
+                | ErrorKind::RateLimited
+                | ErrorKind::ServerError
+        )
+    }
+
+    /// Whether another provider may succeed where this one failed
+    ///
+    /// A query the provider rejected as invalid would most likely fail the
+    /// same way everywhere. Other 4xx responses, such as a 404 or a bad
+    /// Google `cx`, are specific to the provider.
+    pub fn should_fall_back(&self) -> bool {
+        match self.kind() {
+            ErrorKind::ClientError => !self.is_invalid_query(),
+            kind => matches!(
+                kind,
+                ErrorKind::Network
+                    | ErrorKind::InvalidResponse
+                    | ErrorKind::Timeout
+                    | ErrorKind::RateLimited
+                    | ErrorKind::QuotaExceeded
+                    | ErrorKind::InvalidApiKey
+                    | ErrorKind::ServerError
+                    | ErrorKind::Api
+            ),
+        }
+    }
+
+    /// A 400 or 422 whose error code says the query failed validation
+    fn is_invalid_query(&self) -> bool {
+        match self {
+            Self::Api {
+                status: Some(400 | 422),
+                code: Some(code),
+                ..
+            } => QUERY_VALIDATION_CODES
+                .iter()
+                .any(|c| c.eq_ignore_ascii_case(code)),
+            _ => false,
+        }
+    }
+
     /// Create an API error for a specific provider
     pub fn api(provider: impl Into<String>, message: impl Into<String>) -> Self {
         Self::Api {
             provider: provider.into(),
             message: message.into(),
+            status: None,
+            code: None,
+        }
+    }
+
+    /// Create an API error from an HTTP response status
+    pub fn http(
+        provider: impl Into<String>,
+        status: u16,
+        code: Option<String>,
+        message: impl Into<String>,
+    ) -> Self {
+        Self::Api {
+            provider: provider.into(),
+            message: message.into(),
+            status: Some(status),
+            code,
         }
     }
 
@@ -83,6 +354,14 @@ impl SearchError {
         }
     }
 
+    /// Create a quota exceeded error
+    pub fn quota_exceeded(provider: impl Into<String>, reset_after: Option<u64>) -> Self {
+        Self::QuotaExceeded {
+            provider: provider.into(),
+            reset_after,
+        }
+    }
+
     /// Create an invalid API key error
     pub fn invalid_api_key(provider: impl Into<String>) -> Self {
         Self::InvalidApiKey {
@@ -99,6 +378,17 @@ impl SearchError {
     }
 }
 
+fn join_errors(errors: &[SearchError]) -> String {
+    if errors.is_empty() {
+        return "no providers were available".to_string();
+    }
+    errors
+        .iter()
+        .map(|e| e.to_string())
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
 /// Result type alias for SearchError
 pub type Result<T> = std::result::Result<T, SearchError>;
 
@@ -110,9 +400,16 @@ mod tests {
     fn test_api_error_constructor() {
         let err = SearchError::api("brave", "Connection failed");
         match err {
-            SearchError::Api { provider, message } => {
+            SearchError::Api {
+                provider,
+                message,
+                status,
+                code,
+            } => {
                 assert_eq!(provider, "brave");
                 assert_eq!(message, "Connection failed");
+                assert!(status.is_none());
+                assert!(code.is_none());
             }
             _ => panic!("Expected Api error"),
         }
@@ -195,6 +492,14 @@ mod tests {
         assert!(!msg.contains("seconds"));
     }
 
+    #[test]
+    fn test_quota_exceeded_display() {
+        let err = SearchError::quota_exceeded("google", Some(3600));
+        let msg = format!("{}", err);
+        assert!(msg.contains("Quota exceeded for google"));
+        assert!(msg.contains("3600 seconds"));
+    }
+
     #[test]
     fn test_invalid_api_key_display() {
         let err = SearchError::invalid_api_key("bing");
@@ -228,10 +533,16 @@ mod tests {
 
     #[test]
     fn test_all_providers_failed_display() {
-        let err = SearchError::AllProvidersFailed("Network timeout".to_string());
+        let err = SearchError::AllProvidersFailed(vec![
+            SearchError::Timeout(30),
+            SearchError::rate_limited("tavily", Some(60)),
+            SearchError::quota_exceeded("google", Some(3600)),
+        ]);
         let msg = format!("{}", err);
         assert!(msg.contains("All providers failed"));
-        assert!(msg.contains("Network timeout"));
+        assert!(msg.contains("timed out after 30 seconds"));
+        assert!(msg.contains("Rate limited by tavily"));
+        assert_eq!(err.retry_after(), Some(60));
     }
 
     #[test]
@@ -249,6 +560,110 @@ mod tests {
         assert!(msg.contains("Unknown provider"));
     }
 
+    #[test]
+    fn test_error_kind() {
+        assert_eq!(SearchError::api("brave", "oops").kind(), ErrorKind::Api);
+        assert_eq!(
+            SearchError::rate_limited("brave", None).kind().as_str(),
+            "rate_limited"
+        );
+        assert_eq!(SearchError::Timeout(5).kind(), ErrorKind::Timeout);
+        assert_eq!(
+            SearchError::NoProvidersConfigured.kind().to_string(),
+            "no_providers_configured"
+        );
+        let missing = SearchError::InvalidInput("No search query provided".to_string());
+        assert_eq!(missing.kind().as_str(), "invalid_input");
+        assert_eq!(missing.exit_code(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_network_timeout_kind() {
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(ResponseTemplate::new(200).set_delay(std::time::Duration::from_secs(5)))
+            .mount(&server)
+            .await;
+
+        let error = reqwest::Client::new()
+            .get(server.uri())
+            .timeout(std::time::Duration::from_millis(50))
+            .send()
+            .await
+            .unwrap_err();
+        let error = SearchError::from(error);
+        assert_eq!(error.kind(), ErrorKind::Timeout);
+        assert_eq!(error.exit_code(), 9);
+        assert!(error.is_retryable());
+    }
+
+    #[test]
+    fn test_http_error_classification() {
+        let server = SearchError::http("brave", 503, None, "Service Unavailable");
+        assert_eq!(server.kind(), ErrorKind::ServerError);
+        assert!(server.is_retryable());
+        assert!(server.should_fall_back());
+        assert_eq!(
+            server.to_string(),
+            "API error from brave: HTTP 503: Service Unavailable"
+        );
+
+        let bad_query = SearchError::http("bing", 400, Some("InvalidRequest".into()), "bad");
+        assert_eq!(bad_query.kind(), ErrorKind::ClientError);
+        assert!(!bad_query.is_retryable());
+        assert!(!bad_query.should_fall_back());
+        let bad_query = SearchError::http("brave", 422, Some("VALIDATION".into()), "bad");
+        assert!(!bad_query.should_fall_back());
+
+        // Other client errors are the provider's own problem
+        let bad_cx = SearchError::http("google", 400, Some("invalid".into()), "Invalid Value");
+        assert_eq!(bad_cx.kind(), ErrorKind::ClientError);
+        assert!(bad_cx.should_fall_back());
+        assert!(SearchError::http("serper", 404, None, "Not Found").should_fall_back());
+        assert!(SearchError::http("bing", 400, None, "Bad Request").should_fall_back());
+
+        let timeout = SearchError::http("firecrawl", 408, None, "Request Timeout");
+        assert_eq!(timeout.kind(), ErrorKind::Timeout);
+        assert!(timeout.is_retryable());
+
+        let quota = SearchError::quota_exceeded("google", None);
+        assert!(!quota.is_retryable());
+        assert!(quota.should_fall_back());
+        assert!(!SearchError::invalid_api_key("brave").is_retryable());
+        assert!(SearchError::invalid_api_key("brave").should_fall_back());
+    }
+
+    #[test]
+    fn test_exit_codes_are_distinct() {
+        assert_eq!(SearchError::NoProvidersConfigured.exit_code(), 4);
+        assert_eq!(SearchError::rate_limited("brave", None).exit_code(), 6);
+        assert_eq!(SearchError::Timeout(5).exit_code(), 9);
+        assert_eq!(SearchError::Config("bad".into()).exit_code(), 3);
+        assert_eq!(
+            SearchError::http("bing", 400, None, "Bad Request").exit_code(),
+            11
+        );
+        assert_eq!(
+            SearchError::Io(std::io::Error::other("disk")).exit_code(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_error_accessors() {
+        let err = SearchError::rate_limited("brave", Some(30));
+        assert_eq!(err.provider(), Some("brave"));
+        assert_eq!(err.retry_after(), Some(30));
+        assert_eq!(err.status(), Some(429));
+
+        let err = SearchError::http("google", 400, Some("invalid".into()), "Invalid Value");
+        assert_eq!(err.status(), Some(400));
+        assert_eq!(err.code(), Some("invalid"));
+        assert!(SearchError::Timeout(5).provider().is_none());
+    }
+
     #[test]
     fn test_error_debug_impl() {
         let err = SearchError::api("test", "error");
@@ -0,0 +1,190 @@
+//! Netscape-format cookie files
+//!
+//! The format written by curl, wget and most browser export extensions: one
+//! cookie per line with seven tab-separated fields (domain, include
+//! subdomains, path, secure, expiry, name, value). Lines starting with `#`
+//! are comments, except for the `#HttpOnly_` domain prefix.
+
+use crate::error::{Result, SearchError};
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use url::Url;
+
+/// Prefix curl uses to mark HttpOnly cookies
+const HTTP_ONLY_PREFIX: &str = "#HttpOnly_";
+
+/// A single cookie from a cookie file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cookie {
+    /// Domain the cookie belongs to (without leading dot)
+    pub domain: String,
+    /// Whether the cookie is also sent to subdomains
+    pub include_subdomains: bool,
+    /// Path prefix the cookie applies to
+    pub path: String,
+    /// Only send over HTTPS
+    pub secure: bool,
+    /// Expiry as a Unix timestamp (0 = session cookie)
+    pub expires: u64,
+    /// Cookie name
+    pub name: String,
+    /// Cookie value
+    pub value: String,
+}
+
+impl Cookie {
+    /// Check whether the cookie should be sent with a request to the URL
+    fn matches(&self, url: &Url, now: u64) -> bool {
+        if self.expires != 0 && self.expires <= now {
+            return false;
+        }
+        if self.secure && url.scheme() != "https" {
+            return false;
+        }
+
+        let host = match url.host_str() {
+            Some(h) => h.to_lowercase(),
+            None => return false,
+        };
+        let domain_matches = host == self.domain
+            || (self.include_subdomains && host.ends_with(&format!(".{}", self.domain)));
+
+        domain_matches && url.path().starts_with(&self.path)
+    }
+}
+
+/// A set of cookies loaded from a cookie file
+#[derive(Debug, Clone, Default)]
+pub struct CookieJar {
+    cookies: Vec<Cookie>,
+}
+
+impl CookieJar {
+    /// Load cookies from a Netscape-format cookie file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path).map_err(|e| {
+            SearchError::Config(format!(
+                "Failed to read cookie file {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        Self::parse(&content)
+    }
+
+    /// Parse the contents of a Netscape-format cookie file
+    pub fn parse(content: &str) -> Result<Self> {
+        let mut cookies = Vec::new();
+
+        for (i, line) in content.lines().enumerate() {
+            let line = line.trim_end_matches('\r');
+            let line = line.strip_prefix(HTTP_ONLY_PREFIX).unwrap_or(line);
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let fields: Vec<&str> = line.split('\t').collect();
+            if fields.len() != 7 {
+                return Err(SearchError::Config(format!(
+                    "Invalid cookie file line {}: expected 7 tab-separated fields, found {}",
+                    i + 1,
+                    fields.len()
+                )));
+            }
+
+            cookies.push(Cookie {
+                domain: fields[0].trim_start_matches('.').to_lowercase(),
+                include_subdomains: fields[1].eq_ignore_ascii_case("TRUE"),
+                path: fields[2].to_string(),
+                secure: fields[3].eq_ignore_ascii_case("TRUE"),
+                expires: fields[4].parse().unwrap_or(0),
+                name: fields[5].to_string(),
+                value: fields[6].to_string(),
+            });
+        }
+
+        Ok(Self { cookies })
+    }
+
+    /// Add the cookies from another jar
+    pub fn extend(&mut self, other: CookieJar) {
+        self.cookies.extend(other.cookies);
+    }
+
+    /// Build a `Cookie` header value for a request to the URL
+    pub fn header_for(&self, url: &Url) -> Option<String> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+
+        let pairs: Vec<String> = self
+            .cookies
+            .iter()
+            .filter(|c| c.matches(url, now))
+            .map(|c| format!("{}={}", c.name, c.value))
+            .collect();
+
+        if pairs.is_empty() {
+            None
+        } else {
+            Some(pairs.join("; "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COOKIE_FILE: &str = "# Netscape HTTP Cookie File\n\
+        \n\
+        .example.com\tTRUE\t/\tFALSE\t0\tsession\tabc123\n\
+        #HttpOnly_wiki.example.com\tFALSE\t/docs\tTRUE\t0\ttoken\tsecret\n\
+        expired.example.com\tFALSE\t/\tFALSE\t1\told\tgone\n";
+
+    #[test]
+    fn test_parse_cookie_file() {
+        let jar = CookieJar::parse(COOKIE_FILE).unwrap();
+        assert_eq!(jar.cookies.len(), 3);
+        assert_eq!(jar.cookies[0].domain, "example.com");
+        assert!(jar.cookies[0].include_subdomains);
+        assert_eq!(jar.cookies[1].domain, "wiki.example.com");
+        assert!(jar.cookies[1].secure);
+    }
+
+    #[test]
+    fn test_parse_invalid_line() {
+        let result = CookieJar::parse("example.com\tTRUE\t/\n");
+        assert!(matches!(result, Err(SearchError::Config(_))));
+    }
+
+    #[test]
+    fn test_header_for_matching_cookies() {
+        let jar = CookieJar::parse(COOKIE_FILE).unwrap();
+
+        let url = Url::parse("https://wiki.example.com/docs/page").unwrap();
+        assert_eq!(
+            jar.header_for(&url),
+            Some("session=abc123; token=secret".to_string())
+        );
+
+        // Secure cookie is not sent over plain HTTP, path must match
+        let url = Url::parse("http://wiki.example.com/other").unwrap();
+        assert_eq!(jar.header_for(&url), Some("session=abc123".to_string()));
+
+        // Expired cookies and unrelated domains are skipped
+        let url = Url::parse("https://expired.example.com/").unwrap();
+        assert_eq!(jar.header_for(&url), Some("session=abc123".to_string()));
+        let url = Url::parse("https://other.org/").unwrap();
+        assert!(jar.header_for(&url).is_none());
+    }
+
+    #[test]
+    fn test_load_missing_file() {
+        let result = CookieJar::load("/nonexistent/cookies.txt");
+        assert!(matches!(result, Err(SearchError::Config(_))));
+    }
+}
@@ -0,0 +1,1547 @@
+//! URL fetching module for retrieving web page content
+
+mod cookies;
+mod policy;
+
+pub use cookies::CookieJar;
+pub use policy::{NetworkPolicy, PolicyViolation};
+
+use crate::cache::{CachedPage, FetchCache};
+use crate::config::{mask_api_key, FetchConfig, NetworkConfig};
+use crate::error::{Result, SearchError};
+use crate::output::OUTPUT_FORMAT_VERSION;
+use reqwest::header::{
+    HeaderMap, HeaderName, HeaderValue, AUTHORIZATION, COOKIE, IF_MODIFIED_SINCE, IF_NONE_MATCH,
+    LOCATION,
+};
+use reqwest::{Client, RequestBuilder, StatusCode};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use url::Url;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum ContentFormat {
+    /// Raw HTML content
+    Html,
+    /// Plain text (HTML tags stripped)
+    #[default]
+    Text,
+    /// Markdown format
+    Markdown,
+}
+
+impl ContentFormat {
+    /// Get the format name
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ContentFormat::Html => "html",
+            ContentFormat::Text => "text",
+            ContentFormat::Markdown => "markdown",
+        }
+    }
+}
+
+/// Options for fetching URLs
+#[derive(Clone, Debug)]
+pub struct FetchOptions {
+    /// Request timeout
+    pub timeout: Duration,
+    /// Output format
+    pub format: ContentFormat,
+    /// Follow redirects
+    pub follow_redirects: bool,
+    /// Maximum content length in bytes (0 = no limit)
+    pub max_length: usize,
+    /// User agent string
+    pub user_agent: String,
+    /// Bypass cached pages (responses are still stored)
+    pub refresh: bool,
+    /// Extra request headers (take precedence over credential profiles)
+    pub headers: Vec<(String, String)>,
+    /// Cookies to send with matching requests
+    pub cookies: CookieJar,
+    /// Per-domain credential profiles
+    pub fetch_config: FetchConfig,
+    /// Restrictions on which hosts may be fetched
+    pub policy: NetworkPolicy,
+}
+
+impl Default for FetchOptions {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(30),
+            format: ContentFormat::Text,
+            follow_redirects: true,
+            max_length: 0,
+            user_agent: format!(
+                "cli-web-search/{} (https://github.com/scottgl9/cli-web-search)",
+                env!("CARGO_PKG_VERSION")
+            ),
+            refresh: false,
+            headers: Vec::new(),
+            cookies: CookieJar::default(),
+            fetch_config: FetchConfig::default(),
+            policy: NetworkPolicy::default(),
+        }
+    }
+}
+
+impl FetchOptions {
+    /// Create new fetch options
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set timeout
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set content format
+    pub fn with_format(mut self, format: ContentFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Set max content length
+    pub fn with_max_length(mut self, max_length: usize) -> Self {
+        self.max_length = max_length;
+        self
+    }
+
+    /// Set whether to bypass the fetch cache
+    pub fn with_refresh(mut self, refresh: bool) -> Self {
+        self.refresh = refresh;
+        self
+    }
+
+    /// Set user agent
+    pub fn with_user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = user_agent.into();
+        self
+    }
+
+    /// Set whether to follow redirects
+    pub fn with_follow_redirects(mut self, follow_redirects: bool) -> Self {
+        self.follow_redirects = follow_redirects;
+        self
+    }
+
+    /// Add extra request headers
+    pub fn with_headers(mut self, headers: Vec<(String, String)>) -> Self {
+        self.headers.extend(headers);
+        self
+    }
+
+    /// Set cookies to send with matching requests
+    pub fn with_cookies(mut self, cookies: CookieJar) -> Self {
+        self.cookies = cookies;
+        self
+    }
+
+    /// Set per-domain credential profiles
+    pub fn with_fetch_config(mut self, fetch_config: FetchConfig) -> Self {
+        self.fetch_config = fetch_config;
+        self
+    }
+
+    /// Set the network policy
+    pub fn with_policy(mut self, policy: NetworkPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+}
+
+/// Response from fetching a URL
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct FetchResponse {
+    /// JSON output format version; cached responses always get the current one
+    #[serde(skip_deserializing, default = "output_format_version")]
+    pub format_version: u32,
+    /// The URL that was fetched
+    pub url: String,
+    /// Final URL after redirects
+    pub final_url: String,
+    /// HTTP status code
+    pub status: u16,
+    /// Content type header
+    pub content_type: Option<String>,
+    /// The page content
+    pub content: String,
+    /// Content length in bytes
+    pub content_length: usize,
+    /// Page title (if available)
+    pub title: Option<String>,
+}
+
+fn output_format_version() -> u32 {
+    OUTPUT_FORMAT_VERSION
+}
+
+/// URL fetcher
+pub struct Fetcher {
+    client: Client,
+    options: FetchOptions,
+    cache: Option<FetchCache>,
+    /// Requests go through a configured proxy
+    proxied: bool,
+}
+
+impl Fetcher {
+    /// Create a new fetcher with default options
+    pub fn new() -> Self {
+        Self::with_options(FetchOptions::default())
+    }
+
+    /// Create a new fetcher with custom options
+    pub fn with_options(options: FetchOptions) -> Self {
+        Self::with_network(options, &NetworkConfig::default()).expect("Failed to build HTTP client")
+    }
+
+    /// Create a new fetcher with custom options and network settings
+    pub fn with_network(options: FetchOptions, network: &NetworkConfig) -> Result<Self> {
+        let policy = Arc::new(options.policy.clone());
+        // Redirects are followed in `send`, which checks each hop
+        let mut builder = crate::http::client_builder(network)?
+            .timeout(options.timeout)
+            .redirect(reqwest::redirect::Policy::none())
+            .user_agent(&options.user_agent);
+        if policy.block_private_networks {
+            let mut resolver = policy::PolicyResolver::new(policy);
+            if let Some(host) = network.proxy.as_deref().and_then(proxy_host) {
+                resolver = resolver.with_exempt_host(host);
+            }
+            builder = builder.dns_resolver(Arc::new(resolver));
+        }
+        let client = builder
+            .build()
+            .map_err(|e| SearchError::Config(format!("Failed to build HTTP client: {}", e)))?;
+
+        Ok(Self {
+            client,
+            options,
+            cache: None,
+            proxied: network.proxy.is_some(),
+        })
+    }
+
+    /// Use an on-disk cache for fetched pages
+    pub fn with_cache(mut self, cache: FetchCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// A fetcher sharing this one's HTTP client and cache that converts
+    /// pages to `format` and truncates them at `max_length`
+    #[cfg(feature = "mcp")]
+    pub fn with_output(&self, format: ContentFormat, max_length: usize) -> Self {
+        Self {
+            client: self.client.clone(),
+            options: self
+                .options
+                .clone()
+                .with_format(format)
+                .with_max_length(max_length),
+            cache: self.cache.clone(),
+            proxied: self.proxied,
+        }
+    }
+
+    /// Fetch a URL and return the content
+    pub async fn fetch(&self, url: &str) -> Result<FetchResponse> {
+        // Validate URL
+        let parsed_url = url::Url::parse(url)
+            .map_err(|e| SearchError::api("fetch", format!("Invalid URL: {}", e)))?;
+        self.check_target(&parsed_url).await?;
+
+        // Serve fresh cached pages directly, otherwise revalidate them. Pages
+        // fetched with credentials are never cached, so they are not looked
+        // up either; without redirects, only a page for this exact URL fits.
+        let cached = match self.cache {
+            Some(ref cache)
+                if !self.options.refresh
+                    && !self.is_personalized(&parsed_url, HopCredentials::All) =>
+            {
+                cache
+                    .get(url, &self.options.format, self.options.max_length)
+                    .filter(|page| {
+                        self.options.follow_redirects
+                            || page.response.final_url == parsed_url.as_str()
+                    })
+            }
+            _ => None,
+        };
+
+        if let Some(ref page) = cached {
+            if page.is_fresh() {
+                // The policy may have changed since the page was stored
+                let final_url = url::Url::parse(&page.response.final_url)
+                    .map_err(|e| SearchError::api("fetch", format!("Invalid URL: {}", e)))?;
+                self.check_target(&final_url).await?;
+                tracing::debug!("Serving {} from fetch cache", url);
+                return Ok(FetchResponse {
+                    url: url.to_string(),
+                    ..page.response.clone()
+                });
+            }
+        }
+
+        // Make request
+        let (response, personalized) = self.send(&parsed_url, cached.as_ref()).await?;
+
+        let status = response.status().as_u16();
+        let final_url = response.url().to_string();
+        let content_type = response
+            .headers()
+            .get("content-type")
+            .and_then(|v| v.to_str().ok())
+            .map(|s| s.to_string());
+
+        if response.status() == StatusCode::NOT_MODIFIED {
+            if let Some(mut page) = cached {
+                tracing::debug!("Revalidated {} from fetch cache", url);
+                page.update_headers(response.headers());
+                if !personalized {
+                    self.store(url, &page);
+                }
+                return Ok(FetchResponse {
+                    url: url.to_string(),
+                    ..page.response
+                });
+            }
+        }
+
+        let headers = response.headers().clone();
+
+        // Check for error status
+        if !response.status().is_success() {
+            let location = headers
+                .get(LOCATION)
+                .and_then(|v| v.to_str().ok())
+                .map(|l| format!(" (redirects to {})", l))
+                .unwrap_or_default();
+            return Err(SearchError::http(
+                "fetch",
+                status,
+                None,
+                format!(
+                    "{}{}",
+                    response.status().canonical_reason().unwrap_or("Unknown"),
+                    location
+                ),
+            ));
+        }
+
+        // Get content
+        let html = response
+            .text()
+            .await
+            .map_err(|e| SearchError::api("fetch", format!("Failed to read response: {}", e)))?;
+
+        // Apply max length if set
+        let html = if self.options.max_length > 0 && html.len() > self.options.max_length {
+            html[..self.options.max_length].to_string()
+        } else {
+            html
+        };
+
+        // Extract title
+        let title = extract_title(&html);
+
+        // Convert content based on format
+        let content = match self.options.format {
+            ContentFormat::Html => html.clone(),
+            ContentFormat::Text => html_to_text(&html),
+            ContentFormat::Markdown => html_to_markdown(&html),
+        };
+
+        let content_length = content.len();
+
+        let response = FetchResponse {
+            format_version: OUTPUT_FORMAT_VERSION,
+            url: url.to_string(),
+            final_url,
+            status,
+            content_type,
+            content,
+            content_length,
+            title,
+        };
+
+        let page = CachedPage::new(response, &headers);
+        if page.is_storable() && !personalized {
+            self.store(url, &page);
+        }
+
+        Ok(page.response)
+    }
+
+    /// Check a URL (the requested one or a redirect target) may be fetched
+    async fn check_target(&self, url: &Url) -> Result<()> {
+        // Only allow http and https
+        if url.scheme() != "http" && url.scheme() != "https" {
+            return Err(SearchError::api(
+                "fetch",
+                format!("Unsupported URL scheme: {}", url.scheme()),
+            ));
+        }
+
+        self.options.policy.check_url(url)?;
+        if self.proxied {
+            // The proxy resolves the target, so check its addresses up front
+            if let Some(url::Host::Domain(host)) = url.host() {
+                self.options.policy.check_host(host).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Send a GET for `url`, following redirects one hop at a time
+    ///
+    /// Every hop is checked against the network policy and gets the
+    /// credentials of its own host, so nothing meant for the requested host
+    /// reaches another one. Also returns whether any hop sent credentials,
+    /// cookies or custom headers.
+    async fn send(
+        &self,
+        url: &Url,
+        cached: Option<&CachedPage>,
+    ) -> Result<(reqwest::Response, bool)> {
+        let mut hop = url.clone();
+        let mut personalized = false;
+        for _ in 0..=policy::MAX_REDIRECTS {
+            let credentials = HopCredentials::for_hop(url, &hop);
+            personalized |= self.is_personalized(&hop, credentials);
+            let mut request =
+                self.apply_credentials(self.client.get(hop.clone()), &hop, credentials)?;
+            if let Some(page) = cached {
+                if let Some(ref etag) = page.etag {
+                    request = request.header(IF_NONE_MATCH, etag);
+                }
+                if let Some(ref last_modified) = page.last_modified {
+                    request = request.header(IF_MODIFIED_SINCE, last_modified);
+                }
+            }
+
+            let response = request.send().await.map_err(|e| {
+                if let Some(violation) = policy::find_violation(&e) {
+                    SearchError::from(violation)
+                } else if e.is_timeout() {
+                    SearchError::Timeout(self.options.timeout.as_secs())
+                } else {
+                    SearchError::api("fetch", format!("Network error: {}", e))
+                }
+            })?;
+
+            let redirect = matches!(
+                response.status(),
+                StatusCode::MOVED_PERMANENTLY
+                    | StatusCode::FOUND
+                    | StatusCode::SEE_OTHER
+                    | StatusCode::TEMPORARY_REDIRECT
+                    | StatusCode::PERMANENT_REDIRECT
+            );
+            let location = response
+                .headers()
+                .get(LOCATION)
+                .and_then(|l| l.to_str().ok())
+                .map(str::to_string);
+            let (true, true, Some(location)) = (self.options.follow_redirects, redirect, location)
+            else {
+                return Ok((response, personalized));
+            };
+
+            let next = hop.join(&location).map_err(|e| {
+                SearchError::api("fetch", format!("Invalid redirect to {}: {}", location, e))
+            })?;
+            self.check_target(&next).await?;
+            tracing::debug!("Following redirect to {}", next);
+            hop = next;
+        }
+
+        Err(SearchError::api(
+            "fetch",
+            format!("Too many redirects (more than {})", policy::MAX_REDIRECTS),
+        ))
+    }
+
+    /// Whether a request to `url` would carry credentials, cookies or custom
+    /// headers, making its response unfit for the shared cache
+    fn is_personalized(&self, url: &Url, credentials: HopCredentials) -> bool {
+        match credentials {
+            HopCredentials::None => false,
+            HopCredentials::All if !self.options.headers.is_empty() => true,
+            _ => {
+                url.host_str()
+                    .and_then(|host| self.options.fetch_config.credentials_for(host))
+                    .is_some()
+                    || self.options.cookies.header_for(url).is_some()
+            }
+        }
+    }
+
+    /// Add credential profile, cookie and custom headers to a request, as far
+    /// as `credentials` allows
+    fn apply_credentials(
+        &self,
+        mut request: RequestBuilder,
+        url: &Url,
+        credentials: HopCredentials,
+    ) -> Result<RequestBuilder> {
+        if credentials == HopCredentials::None {
+            return Ok(request);
+        }
+
+        let mut headers = HeaderMap::new();
+        let mut cookies = self.options.cookies.clone();
+
+        let profile = url
+            .host_str()
+            .and_then(|host| self.options.fetch_config.credentials_for(host));
+        if let Some(profile) = profile {
+            if let Some(ref token) = profile.bearer_token {
+                insert_header(&mut headers, "Authorization", &format!("Bearer {}", token))?;
+            }
+            if let Some(ref username) = profile.username {
+                request = request.basic_auth(username, profile.password.as_ref());
+            }
+            for (name, value) in &profile.headers {
+                insert_header(&mut headers, name, value)?;
+            }
+            if let Some(ref cookie_file) = profile.cookie_file {
+                cookies.extend(CookieJar::load(cookie_file)?);
+            }
+        }
+
+        if let Some(cookie) = cookies.header_for(url) {
+            insert_header(&mut headers, COOKIE.as_str(), &cookie)?;
+        }
+
+        if credentials == HopCredentials::All {
+            for (name, value) in &self.options.headers {
+                insert_header(&mut headers, name, value)?;
+            }
+        }
+
+        for (name, value) in &headers {
+            tracing::debug!(
+                "Request header {}: {}",
+                name,
+                mask_api_key(value.to_str().unwrap_or_default())
+            );
+        }
+
+        Ok(request.headers(headers))
+    }
+
+    /// Store a page in the fetch cache, if one is configured
+    fn store(&self, url: &str, page: &CachedPage) {
+        if let Some(ref cache) = self.cache {
+            if let Err(e) = cache.put(url, &self.options.format, self.options.max_length, page) {
+                tracing::warn!("Failed to update fetch cache: {}", e);
+            }
+        }
+    }
+}
+
+impl Default for Fetcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Which credentials a request may carry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum HopCredentials {
+    /// Everything: the profile, cookies and `--header` values
+    All,
+    /// Only the profile and cookies matching the hop's own host
+    HostOnly,
+    /// Nothing, because it would travel in the clear
+    None,
+}
+
+impl HopCredentials {
+    /// Credentials for a hop while fetching `requested`
+    ///
+    /// `--header` values are meant for the requested origin (scheme, host
+    /// and port), and a redirect from https to http gets no credentials at
+    /// all.
+    fn for_hop(requested: &Url, hop: &Url) -> Self {
+        if requested.scheme() == "https" && hop.scheme() != "https" {
+            Self::None
+        } else if hop.origin() == requested.origin() {
+            Self::All
+        } else {
+            Self::HostOnly
+        }
+    }
+}
+
+/// Host name of a proxy URL
+fn proxy_host(proxy: &str) -> Option<String> {
+    Url::parse(proxy)
+        .ok()
+        .and_then(|u| u.host_str().map(|h| h.to_string()))
+}
+
+/// Insert a header, replacing any earlier value and hiding secrets from debug output
+fn insert_header(headers: &mut HeaderMap, name: &str, value: &str) -> Result<()> {
+    let header_name = HeaderName::from_bytes(name.as_bytes())
+        .map_err(|_| SearchError::Config(format!("Invalid header name: {}", name)))?;
+    let mut header_value = HeaderValue::from_str(value)
+        .map_err(|_| SearchError::Config(format!("Invalid value for header {}", name)))?;
+
+    if header_name == AUTHORIZATION || header_name == COOKIE {
+        header_value.set_sensitive(true);
+    }
+
+    headers.insert(header_name, header_value);
+    Ok(())
+}
+
+/// Extract the title from HTML content
+fn extract_title(html: &str) -> Option<String> {
+    // Simple regex-free title extraction
+    let lower = html.to_lowercase();
+    let start = lower.find("<title")?;
+    let after_tag = html[start..].find('>')?;
+    let title_start = start + after_tag + 1;
+    let title_end = lower[title_start..].find("</title")?;
+
+    let title = html[title_start..title_start + title_end].trim();
+    if title.is_empty() {
+        None
+    } else {
+        Some(decode_html_entities(title))
+    }
+}
+
+/// Convert HTML to plain text by stripping tags
+fn html_to_text(html: &str) -> String {
+    let mut result = String::with_capacity(html.len());
+    let mut in_tag = false;
+    let mut in_script = false;
+    let mut in_style = false;
+    let mut last_was_space = true;
+
+    let lower = html.to_lowercase();
+    let chars: Vec<char> = html.chars().collect();
+    let lower_chars: Vec<char> = lower.chars().collect();
+
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+
+        // Check for script/style start
+        if i + 7 < chars.len() {
+            let slice: String = lower_chars[i..i + 7].iter().collect();
+            if slice == "<script" {
+                in_script = true;
+            } else if slice == "<style "
+                || (i + 6 < chars.len()
+                    && lower_chars[i..i + 6].iter().collect::<String>() == "<style")
+            {
+                in_style = true;
+            }
+        }
+
+        // Check for script/style end
+        if i + 9 <= chars.len() {
+            let slice: String = lower_chars[i..i + 9].iter().collect();
+            if slice == "</script>" {
+                in_script = false;
+                i += 9;
+                continue;
+            }
+        }
+        if i + 8 <= chars.len() {
+            let slice: String = lower_chars[i..i + 8].iter().collect();
+            if slice == "</style>" {
+                in_style = false;
+                i += 8;
+                continue;
+            }
+        }
+
+        if in_script || in_style {
+            i += 1;
+            continue;
+        }
+
+        if c == '<' {
+            in_tag = true;
+            // Add space for block elements
+            if i + 2 < chars.len() {
+                let next_two: String = lower_chars[i + 1..i + 3].iter().collect();
+                if matches!(
+                    next_two.as_str(),
+                    "p>" | "br"
+                        | "di"
+                        | "li"
+                        | "h1"
+                        | "h2"
+                        | "h3"
+                        | "h4"
+                        | "h5"
+                        | "h6"
+                        | "tr"
+                        | "td"
+                        | "th"
+                        | "/p"
+                        | "/d"
+                        | "/l"
+                        | "/h"
+                        | "/t"
+                ) && !last_was_space
+                {
+                    result.push('\n');
+                    last_was_space = true;
+                }
+            }
+        } else if c == '>' {
+            in_tag = false;
+        } else if !in_tag {
+            if c.is_whitespace() {
+                if !last_was_space {
+                    result.push(' ');
+                    last_was_space = true;
+                }
+            } else {
+                result.push(c);
+                last_was_space = false;
+            }
+        }
+        i += 1;
+    }
+
+    // Decode HTML entities
+    let decoded = decode_html_entities(&result);
+
+    // Clean up extra whitespace
+    let lines: Vec<&str> = decoded
+        .lines()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty())
+        .collect();
+
+    lines.join("\n")
+}
+
+/// Convert HTML to a simple Markdown format
+#[allow(clippy::if_same_then_else)]
+fn html_to_markdown(html: &str) -> String {
+    let mut result = String::with_capacity(html.len());
+    let mut in_tag = false;
+    let mut current_tag = String::new();
+    let mut in_script = false;
+    let mut in_style = false;
+    let mut list_depth: usize = 0;
+
+    let lower = html.to_lowercase();
+    let chars: Vec<char> = html.chars().collect();
+    let lower_chars: Vec<char> = lower.chars().collect();
+
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+
+        // Check for script/style
+        if i + 7 < chars.len() {
+            let slice: String = lower_chars[i..i + 7].iter().collect();
+            if slice == "<script" {
+                in_script = true;
+            }
+        }
+        if i + 6 < chars.len() {
+            let slice: String = lower_chars[i..i + 6].iter().collect();
+            if slice == "<style" {
+                in_style = true;
+            }
+        }
+        if i + 9 <= chars.len() && lower_chars[i..i + 9].iter().collect::<String>() == "</script>" {
+            in_script = false;
+            i += 9;
+            continue;
+        }
+        if i + 8 <= chars.len() && lower_chars[i..i + 8].iter().collect::<String>() == "</style>" {
+            in_style = false;
+            i += 8;
+            continue;
+        }
+
+        if in_script || in_style {
+            i += 1;
+            continue;
+        }
+
+        if c == '<' {
+            in_tag = true;
+            current_tag.clear();
+        } else if c == '>' {
+            in_tag = false;
+            let tag = current_tag.to_lowercase();
+
+            // Handle tags
+            if tag.starts_with("h1") {
+                result.push_str("\n# ");
+            } else if tag.starts_with("h2") {
+                result.push_str("\n## ");
+            } else if tag.starts_with("h3") {
+                result.push_str("\n### ");
+            } else if tag.starts_with("h4") {
+                result.push_str("\n#### ");
+            } else if tag.starts_with("h5") {
+                result.push_str("\n##### ");
+            } else if tag.starts_with("h6") {
+                result.push_str("\n###### ");
+            } else if tag == "p" || tag.starts_with("p ") {
+                result.push_str("\n\n");
+            } else if tag == "/p" {
+                result.push('\n');
+            } else if tag == "br" || tag == "br/" || tag == "br /" {
+                result.push_str("  \n");
+            } else if tag == "ul" || tag.starts_with("ul ") {
+                list_depth += 1;
+                result.push('\n');
+            } else if tag == "/ul" {
+                list_depth = list_depth.saturating_sub(1);
+                result.push('\n');
+            } else if tag == "ol" || tag.starts_with("ol ") {
+                list_depth += 1;
+                result.push('\n');
+            } else if tag == "/ol" {
+                list_depth = list_depth.saturating_sub(1);
+                result.push('\n');
+            } else if tag == "li" || tag.starts_with("li ") {
+                result.push_str(&"  ".repeat(list_depth.saturating_sub(1)));
+                result.push_str("- ");
+            } else if tag == "/li" {
+                result.push('\n');
+            } else if tag == "strong"
+                || tag == "b"
+                || tag.starts_with("strong ")
+                || tag.starts_with("b ")
+            {
+                result.push_str("**");
+            } else if tag == "/strong" || tag == "/b" {
+                result.push_str("**");
+            } else if tag == "em" || tag == "i" || tag.starts_with("em ") || tag.starts_with("i ") {
+                result.push('*');
+            } else if tag == "/em" || tag == "/i" {
+                result.push('*');
+            } else if tag == "code" || tag.starts_with("code ") {
+                result.push('`');
+            } else if tag == "/code" {
+                result.push('`');
+            } else if tag == "pre" || tag.starts_with("pre ") {
+                result.push_str("\n```\n");
+            } else if tag == "/pre" {
+                result.push_str("\n```\n");
+            } else if tag == "blockquote" || tag.starts_with("blockquote ") {
+                result.push_str("\n> ");
+            } else if tag == "hr" || tag == "hr/" || tag == "hr /" {
+                result.push_str("\n---\n");
+            } else if tag.starts_with("/h") {
+                result.push('\n');
+            }
+            current_tag.clear();
+        } else if in_tag {
+            current_tag.push(c);
+        } else {
+            result.push(c);
+        }
+        i += 1;
+    }
+
+    // Decode HTML entities
+    let decoded = decode_html_entities(&result);
+
+    // Clean up
+    let lines: Vec<&str> = decoded.lines().collect();
+    let mut cleaned = Vec::new();
+    let mut prev_empty = false;
+
+    for line in lines {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            if !prev_empty {
+                cleaned.push("");
+                prev_empty = true;
+            }
+        } else {
+            cleaned.push(trimmed);
+            prev_empty = false;
+        }
+    }
+
+    cleaned.join("\n").trim().to_string()
+}
+
+/// Decode common HTML entities
+fn decode_html_entities(s: &str) -> String {
+    s.replace("&amp;", "&")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&apos;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&#x27;", "'")
+        .replace("&#x2F;", "/")
+        .replace("&mdash;", "—")
+        .replace("&ndash;", "–")
+        .replace("&hellip;", "...")
+        .replace("&copy;", "©")
+        .replace("&reg;", "®")
+        .replace("&trade;", "™")
+        .replace("&ldquo;", "\u{201C}")
+        .replace("&rdquo;", "\u{201D}")
+        .replace("&lsquo;", "\u{2018}")
+        .replace("&rsquo;", "\u{2019}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fetch_options_default() {
+        let options = FetchOptions::default();
+        assert_eq!(options.timeout, Duration::from_secs(30));
+        assert_eq!(options.format, ContentFormat::Text);
+        assert!(options.follow_redirects);
+    }
+
+    #[test]
+    fn test_fetch_options_builder() {
+        let options = FetchOptions::new()
+            .with_timeout(Duration::from_secs(60))
+            .with_format(ContentFormat::Markdown)
+            .with_max_length(1000);
+
+        assert_eq!(options.timeout, Duration::from_secs(60));
+        assert_eq!(options.format, ContentFormat::Markdown);
+        assert_eq!(options.max_length, 1000);
+    }
+
+    #[test]
+    fn test_extract_title() {
+        assert_eq!(
+            extract_title("<html><head><title>Test Page</title></head></html>"),
+            Some("Test Page".to_string())
+        );
+        assert_eq!(
+            extract_title("<html><head><title>  Spaced Title  </title></head></html>"),
+            Some("Spaced Title".to_string())
+        );
+        assert_eq!(extract_title("<html><head></head></html>"), None);
+        assert_eq!(
+            extract_title("<html><head><title></title></head></html>"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_extract_title_with_entities() {
+        assert_eq!(
+            extract_title("<title>Test &amp; Page</title>"),
+            Some("Test & Page".to_string())
+        );
+    }
+
+    #[test]
+    fn test_html_to_text() {
+        let html = "<html><body><p>Hello <b>World</b>!</p></body></html>";
+        let text = html_to_text(html);
+        assert!(text.contains("Hello"));
+        assert!(text.contains("World"));
+        assert!(!text.contains("<p>"));
+        assert!(!text.contains("<b>"));
+    }
+
+    #[test]
+    fn test_html_to_text_strips_scripts() {
+        let html = "<html><body><script>alert('hi');</script><p>Content</p></body></html>";
+        let text = html_to_text(html);
+        assert!(!text.contains("alert"));
+        assert!(text.contains("Content"));
+    }
+
+    #[test]
+    fn test_html_to_text_strips_styles() {
+        let html =
+            "<html><head><style>body { color: red; }</style></head><body>Content</body></html>";
+        let text = html_to_text(html);
+        assert!(!text.contains("color"));
+        assert!(text.contains("Content"));
+    }
+
+    #[test]
+    fn test_html_to_markdown_headings() {
+        let html = "<h1>Title</h1><h2>Subtitle</h2><p>Content</p>";
+        let md = html_to_markdown(html);
+        assert!(md.contains("# Title"));
+        assert!(md.contains("## Subtitle"));
+    }
+
+    #[test]
+    fn test_html_to_markdown_formatting() {
+        let html = "<p><strong>Bold</strong> and <em>italic</em></p>";
+        let md = html_to_markdown(html);
+        assert!(md.contains("**Bold**"));
+        assert!(md.contains("*italic*"));
+    }
+
+    #[test]
+    fn test_html_to_markdown_lists() {
+        let html = "<ul><li>Item 1</li><li>Item 2</li></ul>";
+        let md = html_to_markdown(html);
+        assert!(md.contains("- Item 1"));
+        assert!(md.contains("- Item 2"));
+    }
+
+    #[test]
+    fn test_decode_html_entities() {
+        assert_eq!(decode_html_entities("&amp;"), "&");
+        assert_eq!(decode_html_entities("&lt;"), "<");
+        assert_eq!(decode_html_entities("&gt;"), ">");
+        assert_eq!(decode_html_entities("&quot;"), "\"");
+        assert_eq!(decode_html_entities("Test &amp; Test"), "Test & Test");
+    }
+
+    #[test]
+    fn test_content_format_default() {
+        assert_eq!(ContentFormat::default(), ContentFormat::Text);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_invalid_url() {
+        let fetcher = Fetcher::new();
+        let result = fetcher.fetch("not-a-valid-url").await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_fetch_unsupported_scheme() {
+        let fetcher = Fetcher::new();
+        let result = fetcher.fetch("ftp://example.com/file.txt").await;
+        assert!(result.is_err());
+        if let Err(SearchError::Api { message, .. }) = result {
+            assert!(message.contains("Unsupported URL scheme"));
+        }
+    }
+
+    #[test]
+    fn test_fetch_options_request_settings() {
+        let options = FetchOptions::new()
+            .with_user_agent("custom-agent/1.0")
+            .with_follow_redirects(false)
+            .with_headers(vec![("X-Test".to_string(), "1".to_string())]);
+
+        assert_eq!(options.user_agent, "custom-agent/1.0");
+        assert!(!options.follow_redirects);
+        assert_eq!(options.headers.len(), 1);
+    }
+
+    #[test]
+    fn test_insert_header_invalid_name() {
+        let mut headers = HeaderMap::new();
+        let result = insert_header(&mut headers, "Bad Header", "value");
+        assert!(matches!(result, Err(SearchError::Config(_))));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_sends_credentials_and_headers() {
+        use crate::config::CredentialProfile;
+        use wiremock::matchers::{header, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/wiki"))
+            .and(header("Authorization", "Bearer wiki-token"))
+            .and(header("X-Team", "cli"))
+            .and(header("Cookie", "session=abc"))
+            .and(header("User-Agent", "test-agent"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("<p>Secret wiki</p>"))
+            .mount(&mock_server)
+            .await;
+
+        let mut fetch_config = FetchConfig::default();
+        fetch_config.credentials.insert(
+            "127.0.0.1".to_string(),
+            CredentialProfile {
+                bearer_token: Some("wiki-token".to_string()),
+                headers: [("X-Team".to_string(), "profile".to_string())].into(),
+                ..Default::default()
+            },
+        );
+
+        let options = FetchOptions::new()
+            .with_user_agent("test-agent")
+            .with_fetch_config(fetch_config)
+            .with_headers(vec![("X-Team".to_string(), "cli".to_string())])
+            .with_cookies(
+                CookieJar::parse("127.0.0.1\tFALSE\t/\tFALSE\t0\tsession\tabc\n").unwrap(),
+            );
+
+        let fetcher = Fetcher::with_options(options);
+        let response = fetcher
+            .fetch(&format!("{}/wiki", mock_server.uri()))
+            .await
+            .unwrap();
+        assert_eq!(response.content, "Secret wiki");
+    }
+
+    #[tokio::test]
+    async fn test_fetch_redirect_uses_target_host_credentials() {
+        use crate::config::CredentialProfile;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let origin = MockServer::start().await;
+        let target = MockServer::start().await;
+        // Same address, different host, so a different credential profile
+        let target_url = target.uri().replace("127.0.0.1", "localhost");
+
+        Mock::given(method("GET"))
+            .and(path("/start"))
+            .respond_with(
+                ResponseTemplate::new(302)
+                    .insert_header("Location", format!("{}/landing", target_url).as_str()),
+            )
+            .mount(&origin)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/landing"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("<p>Landed</p>"))
+            .mount(&target)
+            .await;
+
+        let mut fetch_config = FetchConfig::default();
+        fetch_config.credentials.insert(
+            "127.0.0.1".to_string(),
+            CredentialProfile {
+                bearer_token: Some("origin-token".to_string()),
+                ..Default::default()
+            },
+        );
+        fetch_config.credentials.insert(
+            "localhost".to_string(),
+            CredentialProfile {
+                headers: [("X-Target".to_string(), "yes".to_string())].into(),
+                ..Default::default()
+            },
+        );
+        let options = FetchOptions::new()
+            .with_fetch_config(fetch_config)
+            .with_headers(vec![("X-Api-Key".to_string(), "secret".to_string())]);
+
+        let response = Fetcher::with_options(options)
+            .fetch(&format!("{}/start", origin.uri()))
+            .await
+            .unwrap();
+        assert_eq!(response.content, "Landed");
+        assert_eq!(response.final_url, format!("{}/landing", target_url));
+
+        let header = |request: &wiremock::Request, name: &str| {
+            request
+                .headers
+                .iter()
+                .find(|(key, _)| key.as_str().eq_ignore_ascii_case(name))
+                .map(|(_, values)| values.as_str().to_string())
+        };
+        let sent = origin.received_requests().await.unwrap();
+        assert_eq!(
+            header(&sent[0], "authorization").as_deref(),
+            Some("Bearer origin-token")
+        );
+        assert_eq!(header(&sent[0], "x-api-key").as_deref(), Some("secret"));
+
+        let sent = target.received_requests().await.unwrap();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(header(&sent[0], "authorization"), None);
+        assert_eq!(header(&sent[0], "x-api-key"), None);
+        assert_eq!(header(&sent[0], "x-target").as_deref(), Some("yes"));
+    }
+
+    #[test]
+    fn test_hop_credentials() {
+        let url = |s: &str| Url::parse(s).unwrap();
+        let requested = url("https://example.com/a");
+        for (hop, expected) in [
+            ("https://example.com/b", HopCredentials::All),
+            ("https://example.com:8443/b", HopCredentials::HostOnly),
+            ("https://other.example/b", HopCredentials::HostOnly),
+            ("http://example.com/b", HopCredentials::None),
+            ("http://other.example/b", HopCredentials::None),
+        ] {
+            assert_eq!(
+                HopCredentials::for_hop(&requested, &url(hop)),
+                expected,
+                "{}",
+                hop
+            );
+        }
+        assert_eq!(
+            HopCredentials::for_hop(&url("http://example.com/"), &url("https://example.com/")),
+            HopCredentials::HostOnly
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fetch_redirect_to_other_port_drops_headers() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let origin = MockServer::start().await;
+        let target = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/start"))
+            .respond_with(
+                ResponseTemplate::new(302)
+                    .insert_header("Location", format!("{}/landing", target.uri()).as_str()),
+            )
+            .mount(&origin)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/landing"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("<p>Landed</p>"))
+            .mount(&target)
+            .await;
+
+        let options =
+            FetchOptions::new().with_headers(vec![("X-Api-Key".to_string(), "secret".to_string())]);
+        let response = Fetcher::with_options(options)
+            .fetch(&format!("{}/start", origin.uri()))
+            .await
+            .unwrap();
+        assert_eq!(response.content, "Landed");
+
+        let sent = target.received_requests().await.unwrap();
+        assert!(!sent[0]
+            .headers
+            .iter()
+            .any(|(name, _)| name.as_str().eq_ignore_ascii_case("x-api-key")));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_no_redirects() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/old"))
+            .respond_with(ResponseTemplate::new(301).insert_header("Location", "/new"))
+            .mount(&mock_server)
+            .await;
+
+        let fetcher = Fetcher::with_options(FetchOptions::new().with_follow_redirects(false));
+        let result = fetcher.fetch(&format!("{}/old", mock_server.uri())).await;
+        match result {
+            Err(SearchError::Api {
+                message, status, ..
+            }) => {
+                assert_eq!(status, Some(301));
+                assert!(message.contains("redirects to /new"));
+            }
+            _ => panic!("Expected Api error"),
+        }
+    }
+
+    fn private_network_policy() -> NetworkPolicy {
+        NetworkPolicy {
+            block_private_networks: true,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_blocks_private_address() {
+        let fetcher =
+            Fetcher::with_options(FetchOptions::new().with_policy(private_network_policy()));
+        let result = fetcher
+            .fetch("http://169.254.169.254/latest/meta-data")
+            .await;
+        assert!(matches!(
+            result,
+            Err(SearchError::PolicyViolation(
+                PolicyViolation::AddressBlocked { .. }
+            ))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_blocks_host_resolving_to_private_address() {
+        let fetcher =
+            Fetcher::with_options(FetchOptions::new().with_policy(private_network_policy()));
+        let result = fetcher.fetch("http://localhost:9/").await;
+        match result {
+            Err(SearchError::PolicyViolation(PolicyViolation::AddressBlocked { host, addr })) => {
+                assert_eq!(host, "localhost");
+                assert!(addr.is_loopback());
+            }
+            other => panic!("Expected AddressBlocked, got {:?}", other.map(|r| r.url)),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_checks_redirect_hops() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/old"))
+            .respond_with(
+                ResponseTemplate::new(302).insert_header("Location", "http://internal.example/"),
+            )
+            .mount(&mock_server)
+            .await;
+
+        let policy = NetworkPolicy {
+            denied_domains: vec!["internal.example".to_string()],
+            ..Default::default()
+        };
+        let fetcher = Fetcher::with_options(FetchOptions::new().with_policy(policy));
+        let result = fetcher.fetch(&format!("{}/old", mock_server.uri())).await;
+        assert!(matches!(
+            result,
+            Err(SearchError::PolicyViolation(PolicyViolation::DomainDenied(ref host)))
+                if host == "internal.example"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_through_proxy_checks_target_host() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let proxy = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/page"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("<p>Via proxy</p>"))
+            .mount(&proxy)
+            .await;
+
+        let network = NetworkConfig {
+            proxy: Some(proxy.uri()),
+            ..Default::default()
+        };
+        let options = FetchOptions::new().with_policy(private_network_policy());
+        let fetcher = Fetcher::with_network(options, &network).unwrap();
+
+        let response = fetcher.fetch("http://93.184.216.34/page").await.unwrap();
+        assert_eq!(response.content, "Via proxy");
+
+        let result = fetcher.fetch("http://localhost/page").await;
+        assert!(matches!(
+            result,
+            Err(SearchError::PolicyViolation(
+                PolicyViolation::AddressBlocked { .. }
+            ))
+        ));
+
+        // Only the proxy could tell where this name leads
+        let result = fetcher.fetch("http://intranet.invalid/page").await;
+        assert!(matches!(
+            result,
+            Err(SearchError::PolicyViolation(PolicyViolation::Unresolved(ref host)))
+                if host == "intranet.invalid"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_revalidates_cached_page() {
+        use wiremock::matchers::{header, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mock_server = MockServer::start().await;
+        let dir = tempfile::tempdir().unwrap();
+
+        // Conditional request is answered with 304
+        Mock::given(method("GET"))
+            .and(path("/page"))
+            .and(header("If-None-Match", "\"v1\""))
+            .respond_with(ResponseTemplate::new(304).insert_header("ETag", "\"v1\""))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path("/page"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("ETag", "\"v1\"")
+                    .set_body_string("<html><title>Cached</title><p>Body</p></html>"),
+            )
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+
+        let url = format!("{}/page", mock_server.uri());
+
+        let fetcher = Fetcher::new().with_cache(FetchCache::new(dir.path()));
+        let first = fetcher.fetch(&url).await.unwrap();
+        let second = fetcher.fetch(&url).await.unwrap();
+        assert_eq!(first.content, second.content);
+        assert_eq!(second.title, Some("Cached".to_string()));
+
+        // --refresh bypasses the cache and downloads the page again
+        let fetcher = Fetcher::with_options(FetchOptions::new().with_refresh(true))
+            .with_cache(FetchCache::new(dir.path()));
+        let refreshed = fetcher.fetch(&url).await.unwrap();
+        assert_eq!(refreshed.content, first.content);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_does_not_cache_authenticated_pages() {
+        use crate::config::CredentialProfile;
+        use wiremock::matchers::{header, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mock_server = MockServer::start().await;
+        let dir = tempfile::tempdir().unwrap();
+
+        Mock::given(method("GET"))
+            .and(path("/account"))
+            .and(header("Authorization", "Bearer secret-token"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("Cache-Control", "max-age=3600")
+                    .set_body_string("<p>Private account</p>"),
+            )
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/account"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("Cache-Control", "max-age=3600")
+                    .set_body_string("<p>Please log in</p>"),
+            )
+            .mount(&mock_server)
+            .await;
+
+        let url = format!("{}/account", mock_server.uri());
+        let mut fetch_config = FetchConfig::default();
+        fetch_config.credentials.insert(
+            "127.0.0.1".to_string(),
+            CredentialProfile {
+                bearer_token: Some("secret-token".to_string()),
+                ..Default::default()
+            },
+        );
+        let fetcher = Fetcher::with_options(FetchOptions::new().with_fetch_config(fetch_config))
+            .with_cache(FetchCache::new(dir.path()));
+        let private = fetcher.fetch(&url).await.unwrap();
+        assert_eq!(private.content, "Private account");
+
+        let fetcher = Fetcher::new().with_cache(FetchCache::new(dir.path()));
+        let public = fetcher.fetch(&url).await.unwrap();
+        assert_eq!(public.content, "Please log in");
+
+        // A request with custom headers doesn't read the anonymous entry
+        let fetcher = Fetcher::with_options(
+            FetchOptions::new().with_headers(vec![("X-Api-Key".to_string(), "k".to_string())]),
+        )
+        .with_cache(FetchCache::new(dir.path()));
+        fetcher.fetch(&url).await.unwrap();
+        assert_eq!(mock_server.received_requests().await.unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_cache_respects_no_redirects() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mock_server = MockServer::start().await;
+        let dir = tempfile::tempdir().unwrap();
+
+        Mock::given(method("GET"))
+            .and(path("/old"))
+            .respond_with(ResponseTemplate::new(301).insert_header("Location", "/new"))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/new"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("Cache-Control", "max-age=3600")
+                    .set_body_string("<p>New</p>"),
+            )
+            .mount(&mock_server)
+            .await;
+
+        let url = format!("{}/old", mock_server.uri());
+        let fetcher = Fetcher::new().with_cache(FetchCache::new(dir.path()));
+        assert_eq!(fetcher.fetch(&url).await.unwrap().content, "New");
+
+        let fetcher = Fetcher::with_options(FetchOptions::new().with_follow_redirects(false))
+            .with_cache(FetchCache::new(dir.path()));
+        assert!(matches!(
+            fetcher.fetch(&url).await,
+            Err(SearchError::Api {
+                status: Some(301),
+                ..
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_checks_policy_for_cached_page() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mock_server = MockServer::start().await;
+        let dir = tempfile::tempdir().unwrap();
+        let target = mock_server.uri().replace("127.0.0.1", "localhost");
+
+        Mock::given(method("GET"))
+            .and(path("/page"))
+            .respond_with(
+                ResponseTemplate::new(302)
+                    .insert_header("Location", format!("{}/final", target).as_str()),
+            )
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/final"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("Cache-Control", "max-age=3600")
+                    .set_body_string("<p>Final</p>"),
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let url = format!("{}/page", mock_server.uri());
+        let fetcher = Fetcher::new().with_cache(FetchCache::new(dir.path()));
+        assert_eq!(fetcher.fetch(&url).await.unwrap().content, "Final");
+
+        // The fresh entry ends on a host the new policy denies
+        let policy = NetworkPolicy {
+            denied_domains: vec!["localhost".to_string()],
+            ..Default::default()
+        };
+        let fetcher = Fetcher::with_options(FetchOptions::new().with_policy(policy))
+            .with_cache(FetchCache::new(dir.path()));
+        let result = fetcher.fetch(&url).await;
+        assert!(matches!(
+            result,
+            Err(SearchError::PolicyViolation(PolicyViolation::DomainDenied(ref host)))
+                if host == "localhost"
+        ));
+    }
+}
@@ -0,0 +1,458 @@
+//! Network policy for fetched URLs
+//!
+//! Guards against server-side request forgery when URLs come from an
+//! untrusted caller such as an MCP client. The policy is checked against the
+//! requested URL, every redirect hop, and the addresses a host resolves to,
+//! so a public hostname pointing at an internal address is rejected too.
+
+use crate::config::NetworkPolicyConfig;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use std::error::Error as StdError;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
+use thiserror::Error;
+use url::{Host, Url};
+
+/// Maximum number of redirects followed
+pub(crate) const MAX_REDIRECTS: usize = 10;
+
+/// Reason a URL was rejected by the network policy
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum PolicyViolation {
+    /// Host is on the deny list
+    #[error("domain {0} is denied")]
+    DomainDenied(String),
+
+    /// Host is not on the allow list
+    #[error("domain {0} is not in the allowed domains")]
+    DomainNotAllowed(String),
+
+    /// Port is not in the allowed ports
+    #[error("port {port} on {host} is not allowed")]
+    PortNotAllowed { host: String, port: u16 },
+
+    /// Host is, or resolves to, a private, loopback or link-local address
+    #[error("{host} resolves to non-public address {addr}")]
+    AddressBlocked { host: String, addr: IpAddr },
+
+    /// Host could not be resolved locally, so its addresses are unknown
+    #[error("{0} could not be resolved to check its addresses")]
+    Unresolved(String),
+}
+
+/// Restrictions on which hosts may be fetched
+#[derive(Debug, Clone, Default)]
+pub struct NetworkPolicy {
+    /// Block private, loopback and link-local addresses
+    pub block_private_networks: bool,
+    /// Only allow these domains and their subdomains (empty = allow all)
+    pub allowed_domains: Vec<String>,
+    /// Never fetch from these domains or their subdomains
+    pub denied_domains: Vec<String>,
+    /// Only allow these ports (empty = allow all)
+    pub allowed_ports: Vec<u16>,
+}
+
+impl NetworkPolicy {
+    /// Build a policy from config, using `block_private_default` when the
+    /// config does not say whether to block private networks
+    pub fn from_config(config: &NetworkPolicyConfig, block_private_default: bool) -> Self {
+        Self {
+            block_private_networks: config
+                .block_private_networks
+                .unwrap_or(block_private_default),
+            allowed_domains: normalize_domains(&config.allowed_domains),
+            denied_domains: normalize_domains(&config.denied_domains),
+            allowed_ports: config.allowed_ports.clone(),
+        }
+    }
+
+    /// Check a URL's host and port against the policy
+    pub fn check_url(&self, url: &Url) -> Result<(), PolicyViolation> {
+        let host = match url.host() {
+            Some(h) => h,
+            None => return Ok(()),
+        };
+        let host_str = match host {
+            // `example.com.` is the same host as `example.com`
+            Host::Domain(d) => d.strip_suffix('.').unwrap_or(d).to_lowercase(),
+            Host::Ipv4(ip) => ip.to_string(),
+            Host::Ipv6(ip) => ip.to_string(),
+        };
+
+        if self
+            .denied_domains
+            .iter()
+            .any(|d| domain_matches(&host_str, d))
+        {
+            return Err(PolicyViolation::DomainDenied(host_str));
+        }
+        if !self.allowed_domains.is_empty()
+            && !self
+                .allowed_domains
+                .iter()
+                .any(|d| domain_matches(&host_str, d))
+        {
+            return Err(PolicyViolation::DomainNotAllowed(host_str));
+        }
+
+        if let Some(port) = url.port_or_known_default() {
+            if !self.allowed_ports.is_empty() && !self.allowed_ports.contains(&port) {
+                return Err(PolicyViolation::PortNotAllowed {
+                    host: host_str,
+                    port,
+                });
+            }
+        }
+
+        // IP literals never go through DNS resolution, so check them here
+        match host {
+            Host::Ipv4(ip) => self.check_addr(&host_str, IpAddr::V4(ip)),
+            Host::Ipv6(ip) => self.check_addr(&host_str, IpAddr::V6(ip)),
+            Host::Domain(_) => Ok(()),
+        }
+    }
+
+    /// Check a resolved address against the policy
+    pub fn check_addr(&self, host: &str, addr: IpAddr) -> Result<(), PolicyViolation> {
+        if self.block_private_networks && !is_public(addr) {
+            return Err(PolicyViolation::AddressBlocked {
+                host: host.to_string(),
+                addr,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// DNS resolver that rejects hosts resolving to blocked addresses
+pub(crate) struct PolicyResolver {
+    policy: Arc<NetworkPolicy>,
+    /// Hosts resolved without checks (the configured proxy)
+    exempt_hosts: Vec<String>,
+}
+
+impl PolicyResolver {
+    pub(crate) fn new(policy: Arc<NetworkPolicy>) -> Self {
+        Self {
+            policy,
+            exempt_hosts: Vec::new(),
+        }
+    }
+
+    /// Skip the address check for a host, e.g. a proxy on the internal network
+    pub(crate) fn with_exempt_host(mut self, host: impl Into<String>) -> Self {
+        self.exempt_hosts.push(host.into().to_lowercase());
+        self
+    }
+}
+
+impl Resolve for PolicyResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let policy = Arc::clone(&self.policy);
+        let exempt = self
+            .exempt_hosts
+            .iter()
+            .any(|h| h.eq_ignore_ascii_case(name.as_str()));
+        Box::pin(async move {
+            let host = name.as_str().to_string();
+            let addrs = lookup(&host).await?;
+
+            if !exempt {
+                for addr in &addrs {
+                    policy.check_addr(&host, addr.ip())?;
+                }
+            }
+
+            let addrs: Addrs = Box::new(addrs.into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+impl NetworkPolicy {
+    /// Resolve a host locally and check its addresses
+    ///
+    /// Used when requests go through a proxy, where the target host is
+    /// resolved by the proxy rather than by our resolver. A host only the
+    /// proxy can resolve (an intranet name, say) is refused, since nothing
+    /// shows it is public.
+    pub(crate) async fn check_host(&self, host: &str) -> Result<(), PolicyViolation> {
+        if !self.block_private_networks {
+            return Ok(());
+        }
+        let addrs = lookup(host)
+            .await
+            .map_err(|_| PolicyViolation::Unresolved(host.to_string()))?;
+        for addr in addrs {
+            self.check_addr(host, addr.ip())?;
+        }
+        Ok(())
+    }
+}
+
+async fn lookup(host: &str) -> std::io::Result<Vec<SocketAddr>> {
+    Ok(tokio::net::lookup_host((host, 0)).await?.collect())
+}
+
+/// Find a policy violation in a request error's source chain
+pub(crate) fn find_violation(err: &reqwest::Error) -> Option<PolicyViolation> {
+    let mut source: Option<&(dyn StdError + 'static)> = Some(err);
+    while let Some(e) = source {
+        if let Some(violation) = e.downcast_ref::<PolicyViolation>() {
+            return Some(violation.clone());
+        }
+        source = e.source();
+    }
+    None
+}
+
+/// Check whether an address is publicly routable
+fn is_public(addr: IpAddr) -> bool {
+    match addr {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => is_public_v6(ip),
+    }
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let octets = ip.octets();
+    !(ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        // 0.0.0.0/8 "this network"
+        || octets[0] == 0
+        // 100.64.0.0/10 carrier-grade NAT
+        || (octets[0] == 100 && (octets[1] & 0xc0) == 64)
+        // 192.0.0.0/24 IETF protocol assignments
+        || (octets[0] == 192 && octets[1] == 0 && octets[2] == 0)
+        // 198.18.0.0/15 benchmarking
+        || (octets[0] == 198 && (octets[1] & 0xfe) == 18)
+        // 240.0.0.0/4 reserved
+        || octets[0] >= 240)
+}
+
+fn is_public_v6(ip: Ipv6Addr) -> bool {
+    if let Some(v4) = embedded_v4(ip) {
+        return is_public_v4(v4);
+    }
+    let first = ip.segments()[0];
+    !(ip.is_loopback()
+        || ip.is_unspecified()
+        // fc00::/7 unique local
+        || (first & 0xfe00) == 0xfc00
+        // fe80::/10 link-local
+        || (first & 0xffc0) == 0xfe80
+        // fec0::/10 site-local (deprecated)
+        || (first & 0xffc0) == 0xfec0)
+}
+
+/// The IPv4 address an IPv6 address leads to, if it embeds one
+fn embedded_v4(ip: Ipv6Addr) -> Option<Ipv4Addr> {
+    let [a, b, c, d, e, f, g, h] = ip.segments();
+    let v4 = |high: u16, low: u16| Ipv4Addr::from((u32::from(high) << 16) | u32::from(low));
+    match (a, b, c, d, e, f) {
+        // ::a.b.c.d IPv4-compatible and ::ffff:a.b.c.d IPv4-mapped
+        (0, 0, 0, 0, 0, 0 | 0xffff) if !ip.is_loopback() && !ip.is_unspecified() => Some(v4(g, h)),
+        // 64:ff9b::/96 NAT64
+        (0x64, 0xff9b, 0, 0, 0, 0) => Some(v4(g, h)),
+        // 2002::/16 6to4
+        (0x2002, ..) => Some(v4(b, c)),
+        _ => None,
+    }
+}
+
+fn normalize_domains(domains: &[String]) -> Vec<String> {
+    domains
+        .iter()
+        .map(|d| {
+            let d = d.trim().trim_start_matches('.');
+            d.strip_suffix('.').unwrap_or(d).to_lowercase()
+        })
+        .filter(|d| !d.is_empty())
+        .collect()
+}
+
+/// Check whether a host is the domain or one of its subdomains
+fn domain_matches(host: &str, domain: &str) -> bool {
+    host == domain || host.ends_with(&format!(".{}", domain))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(yaml: &str, block_private_default: bool) -> NetworkPolicy {
+        let config: NetworkPolicyConfig = serde_yaml::from_str(yaml).unwrap();
+        NetworkPolicy::from_config(&config, block_private_default)
+    }
+
+    fn check(policy: &NetworkPolicy, url: &str) -> Result<(), PolicyViolation> {
+        policy.check_url(&Url::parse(url).unwrap())
+    }
+
+    #[test]
+    fn test_default_policy_allows_everything() {
+        let policy = NetworkPolicy::default();
+        assert!(check(&policy, "http://127.0.0.1:8080/").is_ok());
+        assert!(check(&policy, "http://169.254.169.254/latest/meta-data").is_ok());
+    }
+
+    #[test]
+    fn test_block_private_addresses() {
+        let policy = policy("{}", true);
+        for url in [
+            "http://127.0.0.1/",
+            "http://10.1.2.3/",
+            "http://172.16.0.1/",
+            "http://192.168.1.1/",
+            "http://169.254.169.254/",
+            "http://0.0.0.0/",
+            "http://100.64.0.1/",
+            "http://[::1]/",
+            "http://[fe80::1]/",
+            "http://[fd00::1]/",
+            "http://[::ffff:127.0.0.1]/",
+        ] {
+            assert!(
+                matches!(
+                    check(&policy, url),
+                    Err(PolicyViolation::AddressBlocked { .. })
+                ),
+                "{} should be blocked",
+                url
+            );
+        }
+        assert!(check(&policy, "https://93.184.216.34/").is_ok());
+        assert!(check(&policy, "https://example.com/").is_ok());
+    }
+
+    #[test]
+    fn test_reserved_and_embedded_ranges() {
+        for (addr, public) in [
+            ("192.0.0.8", false),
+            ("198.18.0.1", false),
+            ("198.19.255.255", false),
+            ("198.20.0.1", true),
+            ("240.0.0.1", false),
+            ("255.255.255.255", false),
+            ("223.255.255.255", true),
+            ("64:ff9b::7f00:1", false),
+            ("64:ff9b::a9fe:a9fe", false),
+            ("64:ff9b::5db8:d822", true),
+            ("::127.0.0.1", false),
+            ("::10.0.0.1", false),
+            ("::93.184.216.34", true),
+            ("2002:7f00:1::1", false),
+            ("2002:c0a8:101::", false),
+            ("2002:5db8:d822::1", true),
+            ("fec0::1", false),
+            ("2606:2800:220:1::", true),
+        ] {
+            let ip: IpAddr = addr.parse().unwrap();
+            assert_eq!(is_public(ip), public, "{}", addr);
+        }
+    }
+
+    #[test]
+    fn test_config_overrides_block_private_default() {
+        let policy = policy("block_private_networks: false", true);
+        assert!(check(&policy, "http://127.0.0.1/").is_ok());
+    }
+
+    #[test]
+    fn test_domain_lists() {
+        let policy = policy(
+            "allowed_domains: [example.com, .docs.rs]\ndenied_domains: [internal.example.com]",
+            false,
+        );
+        assert!(check(&policy, "https://example.com/").is_ok());
+        assert!(check(&policy, "https://www.Example.com/").is_ok());
+        assert!(check(&policy, "https://docs.rs/").is_ok());
+        assert_eq!(
+            check(&policy, "https://api.internal.example.com/"),
+            Err(PolicyViolation::DomainDenied(
+                "api.internal.example.com".to_string()
+            ))
+        );
+        assert_eq!(
+            check(&policy, "https://notexample.com/"),
+            Err(PolicyViolation::DomainNotAllowed(
+                "notexample.com".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_trailing_dot_is_the_same_host() {
+        let policy = policy(
+            "allowed_domains: [example.com., internal.example.com]\n\
+             denied_domains: [internal.example.com]",
+            false,
+        );
+        assert_eq!(
+            check(&policy, "http://internal.example.com./"),
+            Err(PolicyViolation::DomainDenied(
+                "internal.example.com".to_string()
+            ))
+        );
+        assert_eq!(
+            check(&policy, "http://API.Internal.Example.com./"),
+            Err(PolicyViolation::DomainDenied(
+                "api.internal.example.com".to_string()
+            ))
+        );
+        assert!(check(&policy, "https://www.example.com./").is_ok());
+        assert!(check(&policy, "https://example.com/").is_ok());
+    }
+
+    #[test]
+    fn test_allowed_ports() {
+        let policy = policy("allowed_ports: [80, 443]", false);
+        assert!(check(&policy, "https://example.com/").is_ok());
+        assert!(check(&policy, "http://example.com/").is_ok());
+        assert_eq!(
+            check(&policy, "http://example.com:8080/"),
+            Err(PolicyViolation::PortNotAllowed {
+                host: "example.com".to_string(),
+                port: 8080
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_resolver_blocks_private_addresses() {
+        let resolver = PolicyResolver::new(Arc::new(policy("{}", true)));
+        let result = resolver.resolve("localhost".parse().unwrap()).await;
+        let err = result.err().expect("localhost should be blocked");
+        assert!(matches!(
+            err.downcast_ref::<PolicyViolation>(),
+            Some(PolicyViolation::AddressBlocked { .. })
+        ));
+
+        let resolver =
+            PolicyResolver::new(Arc::new(policy("{}", true))).with_exempt_host("localhost");
+        assert!(resolver.resolve("localhost".parse().unwrap()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_check_host() {
+        assert!(matches!(
+            policy("{}", true).check_host("localhost").await,
+            Err(PolicyViolation::AddressBlocked { .. })
+        ));
+        assert!(policy("{}", false).check_host("localhost").await.is_ok());
+
+        // Fails closed when the name can't be resolved locally
+        assert_eq!(
+            policy("{}", true).check_host("intranet.invalid").await,
+            Err(PolicyViolation::Unresolved("intranet.invalid".to_string()))
+        );
+        assert!(policy("{}", false)
+            .check_host("intranet.invalid")
+            .await
+            .is_ok());
+    }
+}
@@ -0,0 +1,150 @@
+//! Shared HTTP client construction
+//!
+//! Search providers and `fetch` build their clients here so the proxy, TLS
+//! and timeout settings from the `network:` config section apply to every
+//! outbound request.
+
+use crate::config::NetworkConfig;
+use crate::error::{Result, SearchError};
+use reqwest::{Certificate, Client, ClientBuilder, Identity, NoProxy, Proxy};
+use std::fs;
+use std::time::Duration;
+
+/// Create a client builder with the network settings applied
+pub fn client_builder(network: &NetworkConfig) -> Result<ClientBuilder> {
+    let mut builder = Client::builder();
+
+    if let Some(ref proxy_url) = network.proxy {
+        let proxy = Proxy::all(proxy_url)
+            .map_err(|e| SearchError::Config(format!("Invalid proxy URL {}: {}", proxy_url, e)))?
+            .no_proxy(NoProxy::from_string(&network.no_proxy.join(",")));
+        builder = builder.proxy(proxy);
+    }
+
+    for path in &network.root_certs {
+        let pem = read_file(path, "root certificate")?;
+        let certs = Certificate::from_pem_bundle(&pem).map_err(|e| {
+            SearchError::Config(format!("Invalid root certificate {}: {}", path, e))
+        })?;
+        for cert in certs {
+            builder = builder.add_root_certificate(cert);
+        }
+    }
+
+    if let Some(ref cert_path) = network.client_cert {
+        let cert = read_file(cert_path, "client certificate")?;
+        let identity = match network.client_key {
+            Some(ref key_path) => {
+                let key = read_file(key_path, "client key")?;
+                Identity::from_pkcs8_pem(&cert, &key)
+            }
+            None => Identity::from_pkcs12_der(
+                &cert,
+                network.client_cert_password.as_deref().unwrap_or(""),
+            ),
+        }
+        .map_err(|e| {
+            SearchError::Config(format!("Invalid client certificate {}: {}", cert_path, e))
+        })?;
+        builder = builder.identity(identity);
+    }
+
+    if let Some(seconds) = network.connect_timeout_seconds {
+        builder = builder.connect_timeout(Duration::from_secs(seconds));
+    }
+
+    Ok(builder)
+}
+
+/// Build a client with the network settings applied
+pub fn build_client(network: &NetworkConfig) -> Result<Client> {
+    client_builder(network)?
+        .build()
+        .map_err(|e| SearchError::Config(format!("Failed to build HTTP client: {}", e)))
+}
+
+fn read_file(path: &str, what: &str) -> Result<Vec<u8>> {
+    fs::read(path)
+        .map_err(|e| SearchError::Config(format!("Failed to read {} {}: {}", what, path, e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_client_default() {
+        assert!(build_client(&NetworkConfig::default()).is_ok());
+    }
+
+    #[test]
+    fn test_invalid_proxy_url() {
+        let network = NetworkConfig {
+            proxy: Some("not a url".to_string()),
+            ..Default::default()
+        };
+        assert!(matches!(
+            build_client(&network),
+            Err(SearchError::Config(_))
+        ));
+    }
+
+    #[test]
+    fn test_missing_root_cert() {
+        let network = NetworkConfig {
+            root_certs: vec!["/nonexistent/ca.pem".to_string()],
+            ..Default::default()
+        };
+        match build_client(&network) {
+            Err(SearchError::Config(message)) => {
+                assert!(message.contains("/nonexistent/ca.pem"))
+            }
+            _ => panic!("Expected Config error"),
+        }
+    }
+
+    #[test]
+    fn test_invalid_client_cert() {
+        let dir = tempfile::tempdir().unwrap();
+        let cert = dir.path().join("client.p12");
+        fs::write(&cert, b"not a certificate").unwrap();
+
+        let network = NetworkConfig {
+            client_cert: Some(cert.to_string_lossy().to_string()),
+            ..Default::default()
+        };
+        assert!(matches!(
+            build_client(&network),
+            Err(SearchError::Config(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_requests_go_through_proxy() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let proxy = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/search"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("proxied"))
+            .mount(&proxy)
+            .await;
+
+        let network = NetworkConfig {
+            proxy: Some(proxy.uri()),
+            ..Default::default()
+        };
+        let client = build_client(&network).unwrap();
+        let body = client
+            .get("http://search.invalid/search")
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert_eq!(body, "proxied");
+    }
+}
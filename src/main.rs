@@ -5,70 +5,114 @@ mod cli;
 mod config;
 mod error;
 mod fetch;
+mod http;
 #[cfg(feature = "mcp")]
 mod mcp;
 mod output;
 mod providers;
-
-use cache::SearchCache;
-use cli::{CacheCommands, Cli, Commands, ConfigCommands, FetchArgs, FetchFormat};
-use config::{config_path, get_config_value, load_config, set_config_value};
+mod schema;
+
+use cache::{FetchCache, SearchCache};
+use cli::{
+    CacheCommands, Cli, Commands, ConfigCommands, FetchArgs, FetchFormat, OutputFormat,
+    ProvidersArgs,
+};
+use config::{
+    config_chain, get_config_value, load_config, load_layered_config, migrate_config_files,
+    run_doctor, set_config_value, unset_config_value, EffectiveOptions, LayeredConfig, LoadOptions,
+    Severity, CONFIG_VERSION,
+};
 use error::{Result, SearchError};
-use fetch::{ContentFormat, FetchOptions, Fetcher};
-use output::{get_formatter, SearchResponse};
-use providers::{build_registry, SearchOptions};
+use fetch::{ContentFormat, CookieJar, FetchOptions, Fetcher, NetworkPolicy};
+use output::{get_formatter, ErrorResponse, SearchResponse, OUTPUT_FORMAT_VERSION};
+use providers::{build_registry, Attempt, CircuitState, SearchOptions};
 use std::fs;
 use std::time::{Duration, Instant};
 use tracing_subscriber::EnvFilter;
 
 #[tokio::main]
 async fn main() {
-    if let Err(e) = run().await {
-        eprintln!("Error: {}", e);
-        std::process::exit(1);
-    }
-}
-
-async fn run() -> Result<()> {
     let cli = Cli::parse_args();
-
     // Set up logging based on verbosity
     setup_logging(cli.verbose);
 
+    // Loaded once; a broken config only fails the commands that need it
+    let load_options = load_options(&cli);
+    let layered = load_layered_config(&load_options);
+    let json_errors = match cli.format {
+        Some(ref format) => *format == OutputFormat::Json,
+        // Errors follow the configured format too, if the config can be read
+        None => layered
+            .as_ref()
+            .is_ok_and(|layered| layered.config.defaults.format.eq_ignore_ascii_case("json")),
+    };
+    let mut attempts = Vec::new();
+
+    if let Err(e) = run(cli, &load_options, layered, &mut attempts).await {
+        if json_errors {
+            println!("{}", ErrorResponse::new(&e, attempts).to_json());
+        } else {
+            eprintln!("Error: {}", e);
+        }
+        std::process::exit(e.exit_code());
+    }
+}
+
+async fn run(
+    cli: Cli,
+    load_options: &LoadOptions,
+    layered: Result<LayeredConfig>,
+    attempts: &mut Vec<Attempt>,
+) -> Result<()> {
     // Handle subcommands first
     if let Some(command) = cli.command {
-        return handle_command(command).await;
+        return handle_command(command, load_options, layered).await;
     }
 
-    // If no query provided, show help
-    let query = match &cli.query {
-        Some(q) => q.clone(),
-        None => {
-            eprintln!("Error: No search query provided");
-            eprintln!("Usage: cli-web-search <QUERY>");
-            eprintln!("       cli-web-search --help");
-            std::process::exit(1);
+    // Resolve options: flags first, then config defaults
+    let layered = layered?;
+    let effective = EffectiveOptions::resolve(&cli, &layered)?;
+    if cli.show_effective_options {
+        for line in effective.describe() {
+            println!("{}", line);
         }
-    };
+        return Ok(());
+    }
+
+    let query = cli.query.clone().ok_or_else(|| {
+        SearchError::InvalidInput(
+            "No search query provided. Usage: cli-web-search <QUERY> (see --help)".to_string(),
+        )
+    })?;
 
     // Load configuration
-    let config = load_config()?;
+    let config = layered.config;
 
     // Build provider registry
-    let registry = build_registry(&config);
+    let registry = build_registry(&config)?;
 
     // Check if any providers are configured
     if registry.configured_providers().is_empty() {
         return Err(SearchError::NoProvidersConfigured);
     }
 
+    // Build search options
+    let options = SearchOptions::new()
+        .with_num_results(effective.num_results.value)
+        .with_safe_search(effective.safe_search.value.clone())
+        .with_date_range(cli.date_range.clone())
+        .with_include_domains(cli.include_domains.clone())
+        .with_exclude_domains(cli.exclude_domains.clone())
+        .with_timeout(std::time::Duration::from_secs(effective.timeout.value));
+
     // Set up cache
     let cache = SearchCache::new(config.cache.clone());
 
     // Check cache first (unless disabled)
     let provider_name = cli.provider.as_ref().map(|p| p.to_string());
     if !cli.no_cache {
-        if let Some((cached_results, cached_provider)) = cache.get(&query, provider_name.as_deref())
+        if let Some((cached_results, cached_provider)) =
+            cache.get(&query, provider_name.as_deref(), &options)
         {
             if !cli.quiet {
                 tracing::info!("Using cached results from {}", cached_provider);
@@ -79,42 +123,55 @@ async fn run() -> Result<()> {
                 cached_provider,
                 cached_results,
                 0, // No search time for cached results
-            );
+            )
+            .with_cached(true);
 
-            output_results(&cli, &response)?;
+            output_results(&cli, &effective, &response)?;
             return Ok(());
         }
     }
 
-    // Build search options
-    let options = SearchOptions::new()
-        .with_num_results(cli.num_results)
-        .with_safe_search(cli.safe_search.clone())
-        .with_date_range(cli.date_range.clone())
-        .with_timeout(std::time::Duration::from_secs(cli.timeout));
-
     // Execute search
     let start = Instant::now();
     let (results, provider_used) = registry
-        .search_with_fallback(&query, &options, provider_name.as_deref())
+        .search_with_fallback(&query, &options, provider_name.as_deref(), attempts)
         .await?;
     let search_time_ms = start.elapsed().as_millis() as u64;
 
     // Cache results
     if !cli.no_cache {
-        cache.set(&query, provider_used, results.clone());
+        cache.set(
+            &query,
+            provider_name.as_deref(),
+            &options,
+            provider_used,
+            results.clone(),
+        );
     }
 
     // Format and output results
-    let response = SearchResponse::new(query, provider_used.to_string(), results, search_time_ms);
+    let response = SearchResponse::new(query, provider_used.to_string(), results, search_time_ms)
+        .with_attempts(std::mem::take(attempts));
 
-    output_results(&cli, &response)?;
+    output_results(&cli, &effective, &response)?;
 
     Ok(())
 }
 
-fn output_results(cli: &Cli, response: &SearchResponse) -> Result<()> {
-    let formatter = get_formatter(&cli.format);
+/// Which config files to read, from the global flags
+fn load_options(cli: &Cli) -> LoadOptions {
+    LoadOptions {
+        config_file: cli.config.clone(),
+        profile: cli.profile.clone(),
+    }
+}
+
+fn output_results(
+    cli: &Cli,
+    effective: &EffectiveOptions,
+    response: &SearchResponse,
+) -> Result<()> {
+    let formatter = get_formatter(&effective.format.value, cli.verbose > 0);
     let output = formatter.format(response);
 
     // Write to file or stdout
@@ -130,50 +187,84 @@ fn output_results(cli: &Cli, response: &SearchResponse) -> Result<()> {
     Ok(())
 }
 
-async fn handle_command(command: Commands) -> Result<()> {
+async fn handle_command(
+    command: Commands,
+    load_options: &LoadOptions,
+    layered: Result<LayeredConfig>,
+) -> Result<()> {
     match command {
-        Commands::Config(args) => handle_config_command(args.command).await,
-        Commands::Providers => handle_providers_command().await,
-        Commands::Cache(args) => handle_cache_command(args.command).await,
-        Commands::Fetch(args) => handle_fetch_command(args).await,
+        Commands::Config(args) => handle_config_command(args.command, load_options, layered).await,
+        Commands::Providers(args) => handle_providers_command(args, load_options).await,
+        Commands::Cache(args) => handle_cache_command(args.command, load_options).await,
+        Commands::Fetch(args) => handle_fetch_command(args, load_options).await,
+        Commands::Schema(args) => {
+            let schema = schema::schema_for_kind(&args.kind);
+            println!("{}", serde_json::to_string_pretty(&schema)?);
+            Ok(())
+        }
         #[cfg(feature = "mcp")]
-        Commands::Serve => mcp::run_mcp_server().await,
+        Commands::Serve(ref args) => mcp::run_mcp_server(load_options.clone(), args).await,
     }
 }
 
-async fn handle_config_command(command: ConfigCommands) -> Result<()> {
+async fn handle_config_command(
+    command: ConfigCommands,
+    load_options: &LoadOptions,
+    layered: Result<LayeredConfig>,
+) -> Result<()> {
     match command {
-        ConfigCommands::Init => {
-            config::init_config_interactive()?;
-            let path = config_path()?;
-            println!("Configuration initialized at: {}", path.display());
+        ConfigCommands::Init(args) => {
+            if config::init_config(&args, load_options).await? {
+                let path = load_options.user_config_path()?;
+                println!("Configuration written to: {}", path.display());
+            }
             Ok(())
         }
         ConfigCommands::Set { key, value } => {
-            set_config_value(&key, &value)?;
-            println!("Set {} = {}", key, value);
+            let display = set_config_value(&key, &value, load_options)?;
+            println!("Set {} = {}", key, display);
+            Ok(())
+        }
+        ConfigCommands::Unset { key } => {
+            unset_config_value(&key, load_options)?;
+            println!("Unset {}", key);
             Ok(())
         }
         ConfigCommands::Get { key } => {
-            match get_config_value(&key)? {
+            match get_config_value(&key, load_options)? {
+                Some(value) if value.contains('\n') => {
+                    println!("{}:", key);
+                    for line in value.lines() {
+                        println!("  {}", line);
+                    }
+                }
                 Some(value) => println!("{}: {}", key, value),
                 None => println!("{}: (not set)", key),
             }
             Ok(())
         }
         ConfigCommands::List => {
-            let config = load_config()?;
-            let map = config.to_flat_map();
+            let layered = layered?;
+            let map = layered.config.to_flat_map();
             let mut keys: Vec<_> = map.keys().collect();
             keys.sort();
             for key in keys {
-                println!("{}: {}", key, map.get(key).unwrap());
+                // Once values come from more than one place, say where
+                match layered.is_layered() {
+                    true => println!(
+                        "{}: {}  ({})",
+                        key,
+                        map.get(key).unwrap(),
+                        layered.source_of(key)
+                    ),
+                    false => println!("{}: {}", key, map.get(key).unwrap()),
+                }
             }
             Ok(())
         }
         ConfigCommands::Validate => {
-            let config = load_config()?;
-            let registry = build_registry(&config);
+            let config = load_config(load_options)?;
+            let registry = build_registry(&config)?;
 
             println!("Validating API keys...\n");
 
@@ -188,17 +279,92 @@ async fn handle_config_command(command: ConfigCommands) -> Result<()> {
 
             Ok(())
         }
+        ConfigCommands::Doctor => {
+            let sections = run_doctor(load_options)?;
+            let (mut errors, mut warnings) = (0, 0);
+            for section in &sections {
+                println!("{}", section.title);
+                if section.diagnostics.is_empty() {
+                    println!("  ok");
+                }
+                for diagnostic in &section.diagnostics {
+                    match diagnostic.severity {
+                        Severity::Error => errors += 1,
+                        Severity::Warning => warnings += 1,
+                    }
+                    println!("  {}: {}", diagnostic.severity, diagnostic);
+                }
+            }
+            println!("\n{} error(s), {} warning(s)", errors, warnings);
+
+            match errors {
+                0 => Ok(()),
+                n => Err(SearchError::Config(format!(
+                    "config doctor found {} error(s)",
+                    n
+                ))),
+            }
+        }
+        ConfigCommands::Migrate { dry_run } => {
+            let files = migrate_config_files(load_options, dry_run)?;
+            if files.is_empty() {
+                println!(
+                    "No config files found (expected {})",
+                    load_options.user_config_path()?.display()
+                );
+            }
+            for (path, migration) in &files {
+                let Some(migration) = migration else {
+                    println!(
+                        "{}: up to date (version {})",
+                        path.display(),
+                        CONFIG_VERSION
+                    );
+                    continue;
+                };
+                println!(
+                    "{}: version {} -> {}",
+                    path.display(),
+                    migration.migrated.from,
+                    CONFIG_VERSION
+                );
+                for step in &migration.migrated.steps {
+                    println!("  - {}", step);
+                }
+                match migration.backup {
+                    Some(ref backup) => println!("  original saved as {}", backup.display()),
+                    None => print!("{}", migration.diff()),
+                }
+            }
+            if dry_run {
+                println!("\nDry run; no files were changed.");
+            }
+            Ok(())
+        }
         ConfigCommands::Path => {
-            let path = config_path()?;
-            println!("{}", path.display());
+            println!("Configuration sources (later ones take precedence):");
+            for (i, (name, path)) in config_chain(load_options)?.iter().enumerate() {
+                match path {
+                    Some(path) if path.exists() => {
+                        println!("  {}. {}: {}", i + 1, name, path.display())
+                    }
+                    Some(path) => println!("  {}. {}: {} (not found)", i + 1, name, path.display()),
+                    None => println!("  {}. {}", i + 1, name),
+                }
+            }
             Ok(())
         }
     }
 }
 
-async fn handle_providers_command() -> Result<()> {
-    let config = load_config()?;
-    let registry = build_registry(&config);
+async fn handle_providers_command(args: ProvidersArgs, load_options: &LoadOptions) -> Result<()> {
+    let config = load_config(load_options)?;
+    let registry = build_registry(&config)?;
+
+    if args.usage {
+        print_provider_usage(&registry).await;
+        return Ok(());
+    }
 
     println!("Available Search Providers:\n");
 
@@ -210,7 +376,12 @@ async fn handle_providers_command() -> Result<()> {
     } else {
         for status in &statuses {
             let indicator = if status.configured { "[x]" } else { "[ ]" };
-            println!("  {} {}", indicator, status.name);
+            println!(
+                "  {} {}{}",
+                indicator,
+                status.name,
+                describe_health(registry.health(), &status.name).await
+            );
         }
     }
 
@@ -244,25 +415,123 @@ async fn handle_providers_command() -> Result<()> {
     Ok(())
 }
 
-async fn handle_cache_command(command: CacheCommands) -> Result<()> {
-    let config = load_config()?;
+/// Summarize a provider's circuit state for the providers listing
+async fn describe_health(health: &providers::HealthTracker, provider: &str) -> String {
+    let record = health.get(provider).await;
+    let last_error = record
+        .last_error_kind
+        .as_deref()
+        .map(|kind| format!(", last error: {}", kind))
+        .unwrap_or_default();
+
+    match health.state(provider).await {
+        CircuitState::Open => format!(
+            " (circuit open until {}, {} consecutive failures{})",
+            record
+                .cooldown_until
+                .map(|t| t.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+                .unwrap_or_default(),
+            record.consecutive_failures,
+            last_error
+        ),
+        CircuitState::HalfOpen => format!(
+            " (circuit half-open, next request is a probe{})",
+            last_error
+        ),
+        CircuitState::Closed if record.consecutive_failures > 0 => format!(
+            " ({} recent failures{})",
+            record.consecutive_failures, last_error
+        ),
+        CircuitState::Closed => String::new(),
+    }
+}
+
+async fn print_provider_usage(registry: &providers::ProviderRegistry) {
+    let limiter = registry.rate_limiter();
+    let usage = limiter.usage().await;
+
+    let mut names: Vec<String> = registry
+        .list_providers()
+        .into_iter()
+        .map(|s| s.name)
+        .chain(usage.keys().cloned())
+        .collect();
+    names.sort();
+    names.dedup();
+
+    println!("Provider Usage (UTC):\n");
+    println!(
+        "  {:<12} {:>12} {:>12} {:>10}  Limits",
+        "Provider", "Today", "This month", "Total"
+    );
+
+    for name in names {
+        let counts = usage.get(&name).cloned().unwrap_or_default();
+        let limits = limiter.limits_for(&name);
+
+        let today = match limits.per_day {
+            Some(max) => format!("{}/{}", counts.day_count, max),
+            None => counts.day_count.to_string(),
+        };
+        let month = match limits.per_month {
+            Some(max) => format!("{}/{}", counts.month_count, max),
+            None => counts.month_count.to_string(),
+        };
+
+        let mut limit_desc = Vec::new();
+        if let Some(per_second) = limits.per_second {
+            limit_desc.push(format!("{}/s", per_second));
+        }
+        if let Some(per_day) = limits.per_day {
+            limit_desc.push(format!("{}/day", per_day));
+        }
+        if let Some(per_month) = limits.per_month {
+            limit_desc.push(format!("{}/month", per_month));
+        }
+        let mut limit_desc = if limit_desc.is_empty() {
+            "none".to_string()
+        } else {
+            limit_desc.join(", ")
+        };
+        if limiter.check(&name).await.is_err() {
+            limit_desc.push_str(" (exhausted)");
+        }
+
+        println!(
+            "  {:<12} {:>12} {:>12} {:>10}  {}",
+            name, today, month, counts.total, limit_desc
+        );
+    }
+}
+
+async fn handle_cache_command(command: CacheCommands, load_options: &LoadOptions) -> Result<()> {
+    let config = load_config(load_options)?;
     let cache = SearchCache::new(config.cache);
 
+    let fetch_cache = FetchCache::open()?;
+
     match command {
-        CacheCommands::Clear => {
-            cache.clear()?;
-            println!("Cache cleared.");
+        CacheCommands::Clear { search, fetch } => {
+            // Neither flag means both caches
+            if search || !fetch {
+                cache.clear()?;
+                println!("Search cache cleared.");
+            }
+            if fetch || !search {
+                let removed = fetch_cache.clear()?;
+                println!("Fetch cache cleared ({} entries removed).", removed);
+            }
             Ok(())
         }
         CacheCommands::Stats => {
-            let stats = cache.stats();
-            println!("{}", stats);
+            println!("{}", cache.stats());
+            println!("{}", fetch_cache.stats());
             Ok(())
         }
     }
 }
 
-async fn handle_fetch_command(args: FetchArgs) -> Result<()> {
+async fn handle_fetch_command(args: FetchArgs, load_options: &LoadOptions) -> Result<()> {
     // Convert CLI format to fetch format
     let content_format = match args.format {
         FetchFormat::Text => ContentFormat::Text,
@@ -270,13 +539,30 @@ async fn handle_fetch_command(args: FetchArgs) -> Result<()> {
         FetchFormat::Markdown => ContentFormat::Markdown,
     };
 
+    let config = load_config(load_options)?;
+
     // Build fetch options
-    let options = FetchOptions::new()
+    let mut options = FetchOptions::new()
         .with_timeout(Duration::from_secs(args.timeout))
         .with_format(content_format)
-        .with_max_length(args.max_length);
+        .with_max_length(args.max_length)
+        .with_refresh(args.refresh)
+        .with_follow_redirects(!args.no_redirects)
+        .with_headers(args.headers.clone())
+        .with_policy(NetworkPolicy::from_config(&config.fetch.policy, false))
+        .with_fetch_config(config.fetch.clone());
+
+    if let Some(ref user_agent) = args.user_agent {
+        options = options.with_user_agent(user_agent);
+    }
+    if let Some(ref cookie_file) = args.cookie_file {
+        options = options.with_cookies(CookieJar::load(cookie_file)?);
+    }
 
-    let fetcher = Fetcher::with_options(options);
+    let mut fetcher = Fetcher::with_network(options, &config.network)?;
+    if config.cache.enabled {
+        fetcher = fetcher.with_cache(FetchCache::open()?);
+    }
 
     // Fetch the URL
     if !args.quiet && !args.stdout {
@@ -287,9 +573,8 @@ async fn handle_fetch_command(args: FetchArgs) -> Result<()> {
 
     // Determine output content
     let output_content = if args.json {
-        serde_json::to_string_pretty(&response).map_err(|e| SearchError::Api {
-            provider: "fetch".to_string(),
-            message: format!("Failed to serialize response: {}", e),
+        serde_json::to_string_pretty(&response).map_err(|e| {
+            SearchError::api("fetch", format!("Failed to serialize response: {}", e))
         })?
     } else {
         response.content.clone()
@@ -331,6 +616,7 @@ async fn handle_fetch_command(args: FetchArgs) -> Result<()> {
         if args.json {
             // For JSON output, provide structured response
             println!("{{");
+            println!("  \"format_version\": {},", OUTPUT_FORMAT_VERSION);
             println!("  \"status\": \"success\",");
             println!("  \"file\": \"{}\",", output_path.display());
             println!("  \"url\": \"{}\",", response.url);
@@ -406,8 +692,10 @@ fn setup_logging(verbosity: u8) {
 
     let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(filter));
 
+    // stdout carries results (and MCP messages when serving)
     tracing_subscriber::fmt()
         .with_env_filter(filter)
         .with_target(false)
+        .with_writer(std::io::stderr)
         .init();
 }
@@ -0,0 +1,701 @@
+//! Streamable HTTP transport
+//!
+//! Everything goes through one endpoint, `/mcp`. Clients POST a JSON-RPC
+//! message or batch; the reply comes back as JSON or, for tool calls from
+//! clients that accept `text/event-stream`, as a short SSE stream whose
+//! keep-alive comments hold the connection open during slow searches.
+//! `initialize` starts a session: its id comes back in the `Mcp-Session-Id`
+//! header, every later request must send it, and DELETE ends it. The server
+//! never starts messages of its own, so GET (a standalone SSE stream) is
+//! answered with 405.
+//!
+//! Web pages can make browsers send requests to localhost, so requests whose
+//! `Origin` is neither local nor allowed with `--allow-origin` are refused.
+
+use super::{McpServer, SUPPORTED_PROTOCOL_VERSIONS};
+use crate::error::Result;
+use bytes::Bytes;
+use http_body_util::{BodyExt, Channel, Either, Full, LengthLimitError, Limited};
+use hyper::body::Incoming;
+use hyper::header::{self, HeaderMap, HeaderValue};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use std::collections::HashSet;
+use std::convert::Infallible;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::TcpListener;
+
+/// Path of the MCP endpoint
+pub const ENDPOINT: &str = "/mcp";
+
+const SESSION_HEADER: &str = "mcp-session-id";
+const PROTOCOL_VERSION_HEADER: &str = "mcp-protocol-version";
+const MAX_BODY_BYTES: usize = 4 * 1024 * 1024;
+const KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+type Body = Either<Full<Bytes>, Channel<Bytes>>;
+
+/// Access settings for the HTTP transport
+#[derive(Debug, Clone, Default)]
+pub struct HttpOptions {
+    /// Token every request must send as `Authorization: Bearer TOKEN`
+    pub auth_token: Option<String>,
+    /// Browser origins accepted besides localhost
+    pub allowed_origins: Vec<String>,
+}
+
+struct HttpState {
+    server: Arc<McpServer>,
+    options: HttpOptions,
+    /// Ids handed out by `initialize` and not yet ended with DELETE
+    sessions: Mutex<HashSet<String>>,
+}
+
+/// Serve MCP requests from `listener` until an accept fails
+pub async fn serve(
+    server: Arc<McpServer>,
+    listener: TcpListener,
+    options: HttpOptions,
+) -> Result<()> {
+    let state = Arc::new(HttpState {
+        server,
+        options,
+        sessions: Mutex::new(HashSet::new()),
+    });
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let state = Arc::clone(&state);
+        tokio::spawn(async move {
+            let service = service_fn(move |request| {
+                let state = Arc::clone(&state);
+                async move { Ok::<_, Infallible>(handle(&state, request).await) }
+            });
+            // A failed connection (say, a client hanging up) only affects
+            // that client
+            let _ = http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service)
+                .await;
+        });
+    }
+}
+
+async fn handle(state: &Arc<HttpState>, request: Request<Incoming>) -> Response<Body> {
+    if request.uri().path() != ENDPOINT {
+        return text(StatusCode::NOT_FOUND, "Not found: the MCP endpoint is /mcp");
+    }
+
+    let headers = request.headers();
+    if let Some(origin) = headers.get(header::ORIGIN) {
+        if !state.origin_allowed(origin.to_str().unwrap_or_default()) {
+            return text(StatusCode::FORBIDDEN, "Origin not allowed");
+        }
+    }
+    if !state.authorized(headers) {
+        let mut response = text(StatusCode::UNAUTHORIZED, "Missing or wrong bearer token");
+        response
+            .headers_mut()
+            .insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+        return response;
+    }
+    if let Some(version) = headers.get(PROTOCOL_VERSION_HEADER) {
+        let version = version.to_str().unwrap_or_default();
+        if !SUPPORTED_PROTOCOL_VERSIONS.contains(&version) {
+            return text(
+                StatusCode::BAD_REQUEST,
+                &format!("Unsupported MCP-Protocol-Version '{}'", version),
+            );
+        }
+    }
+
+    match *request.method() {
+        Method::POST => post(state, request).await,
+        Method::DELETE => delete(state, request.headers()),
+        _ => {
+            let mut response = text(
+                StatusCode::METHOD_NOT_ALLOWED,
+                "Use POST to send messages and DELETE to end a session",
+            );
+            response
+                .headers_mut()
+                .insert(header::ALLOW, HeaderValue::from_static("POST, DELETE"));
+            response
+        }
+    }
+}
+
+async fn post(state: &Arc<HttpState>, request: Request<Incoming>) -> Response<Body> {
+    let headers = request.headers().clone();
+    let streams = accepts(&headers, "text/event-stream");
+    if !streams && !accepts(&headers, "application/json") {
+        return text(
+            StatusCode::NOT_ACCEPTABLE,
+            "Accept must allow application/json or text/event-stream",
+        );
+    }
+    if let Some(content_type) = headers.get(header::CONTENT_TYPE) {
+        if !content_type
+            .to_str()
+            .unwrap_or_default()
+            .starts_with("application/json")
+        {
+            return text(
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                "Content-Type must be application/json",
+            );
+        }
+    }
+
+    let body = match Limited::new(request.into_body(), MAX_BODY_BYTES)
+        .collect()
+        .await
+    {
+        Ok(body) => body.to_bytes(),
+        Err(e) if e.is::<LengthLimitError>() => {
+            return text(StatusCode::PAYLOAD_TOO_LARGE, "Message too large")
+        }
+        Err(e) => return text(StatusCode::BAD_REQUEST, &e.to_string()),
+    };
+    let message = String::from_utf8_lossy(&body).into_owned();
+    let parsed: serde_json::Value = match serde_json::from_str(&message) {
+        Ok(parsed) => parsed,
+        // The dispatcher words the parse error
+        Err(_) => {
+            let reply = state.server.handle_message(None, &message).await;
+            return json(StatusCode::BAD_REQUEST, reply.unwrap_or_default());
+        }
+    };
+
+    // initialize opens a session; everything else must belong to one
+    let initialize = parsed.get("method").and_then(|m| m.as_str()) == Some("initialize");
+    let session = match headers.get(SESSION_HEADER) {
+        _ if initialize => new_session_id(),
+        None => {
+            return text(
+                StatusCode::BAD_REQUEST,
+                "Missing Mcp-Session-Id header (send initialize first)",
+            )
+        }
+        Some(id) => {
+            let id = id.to_str().unwrap_or_default();
+            if !state.sessions.lock().unwrap().contains(id) {
+                return text(StatusCode::NOT_FOUND, "Unknown or ended session");
+            }
+            id.to_string()
+        }
+    };
+
+    // Notifications and responses only need an acknowledgement
+    if !messages(&parsed).any(is_request) {
+        state.server.handle_message(Some(&session), &message).await;
+        return empty(StatusCode::ACCEPTED);
+    }
+
+    if streams && messages(&parsed).any(|m| m.get("method") == Some(&"tools/call".into())) {
+        return stream_reply(state, session, message);
+    }
+
+    let reply = state.server.handle_message(Some(&session), &message).await;
+    let Some(reply) = reply else {
+        // Every request in it was cancelled
+        return empty(StatusCode::ACCEPTED);
+    };
+    let opened = initialize
+        && serde_json::from_str::<serde_json::Value>(&reply)
+            .is_ok_and(|reply| reply.get("result").is_some());
+    let mut response = json(StatusCode::OK, reply);
+    if opened {
+        state.sessions.lock().unwrap().insert(session.clone());
+        let id = HeaderValue::from_str(&session).expect("session ids are hex");
+        response.headers_mut().insert(SESSION_HEADER, id);
+    }
+    response
+}
+
+/// Reply over SSE, sending keep-alive comments until the reply is ready
+///
+/// If the client disconnects, the call is dropped (and so aborted).
+fn stream_reply(state: &Arc<HttpState>, session: String, message: String) -> Response<Body> {
+    let (mut sender, body) = Channel::new(1);
+    let state = Arc::clone(state);
+    tokio::spawn(async move {
+        let handling = state.server.handle_message(Some(&session), &message);
+        tokio::pin!(handling);
+        let mut keep_alive =
+            tokio::time::interval_at(tokio::time::Instant::now() + KEEP_ALIVE, KEEP_ALIVE);
+        let reply = loop {
+            tokio::select! {
+                reply = &mut handling => break reply,
+                _ = keep_alive.tick() => {
+                    let comment = Bytes::from_static(b": keep-alive\n\n");
+                    if sender.send_data(comment).await.is_err() {
+                        return;
+                    }
+                }
+            }
+        };
+        if let Some(reply) = reply {
+            let event = format!("event: message\ndata: {}\n\n", reply);
+            let _ = sender.send_data(Bytes::from(event)).await;
+        }
+    });
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "text/event-stream")
+        .header(header::CACHE_CONTROL, "no-cache")
+        .body(Either::Right(body))
+        .expect("static headers are valid")
+}
+
+fn delete(state: &HttpState, headers: &HeaderMap) -> Response<Body> {
+    let Some(id) = headers.get(SESSION_HEADER) else {
+        return text(StatusCode::BAD_REQUEST, "Missing Mcp-Session-Id header");
+    };
+    let id = id.to_str().unwrap_or_default();
+    if !state.sessions.lock().unwrap().remove(id) {
+        return text(StatusCode::NOT_FOUND, "Unknown or ended session");
+    }
+    state.server.cancel_session(id);
+    empty(StatusCode::NO_CONTENT)
+}
+
+impl HttpState {
+    fn origin_allowed(&self, origin: &str) -> bool {
+        let origin = origin.trim_end_matches('/');
+        if self
+            .options
+            .allowed_origins
+            .iter()
+            .any(|allowed| allowed.trim_end_matches('/').eq_ignore_ascii_case(origin))
+        {
+            return true;
+        }
+        url::Url::parse(origin)
+            .ok()
+            .and_then(|url| url.host_str().map(is_local_host))
+            .unwrap_or(false)
+    }
+
+    fn authorized(&self, headers: &HeaderMap) -> bool {
+        let Some(ref token) = self.options.auth_token else {
+            return true;
+        };
+        headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .is_some_and(|given| constant_time_eq(given.as_bytes(), token.as_bytes()))
+    }
+}
+
+fn is_local_host(host: &str) -> bool {
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    host.eq_ignore_ascii_case("localhost")
+        || host.parse::<IpAddr>().is_ok_and(|ip| ip.is_loopback())
+}
+
+/// Compare tokens without stopping at the first difference
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+fn new_session_id() -> String {
+    format!("{:032x}", rand::random::<u128>())
+}
+
+/// Whether the Accept headers allow `media_type` (no header allows anything)
+fn accepts(headers: &HeaderMap, media_type: &str) -> bool {
+    if !headers.contains_key(header::ACCEPT) {
+        return true;
+    }
+    headers
+        .get_all(header::ACCEPT)
+        .iter()
+        .flat_map(|accept| accept.to_str().unwrap_or_default().split(','))
+        .map(|range| range.split(';').next().unwrap_or_default().trim())
+        .any(|range| range == media_type || range == "*/*")
+}
+
+/// The messages in a single message or a batch
+fn messages(message: &serde_json::Value) -> impl Iterator<Item = &serde_json::Value> {
+    match message {
+        serde_json::Value::Array(batch) => batch.iter(),
+        message => std::slice::from_ref(message).iter(),
+    }
+}
+
+/// A message that expects a reply, as opposed to a notification or response
+fn is_request(message: &serde_json::Value) -> bool {
+    message.get("method").is_some() && message.get("id").is_some_and(|id| !id.is_null())
+}
+
+fn json(status: StatusCode, body: String) -> Response<Body> {
+    full(status, "application/json", body)
+}
+
+fn text(status: StatusCode, message: &str) -> Response<Body> {
+    full(
+        status,
+        "text/plain; charset=utf-8",
+        format!("{}\n", message),
+    )
+}
+
+fn full(status: StatusCode, content_type: &'static str, body: String) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, content_type)
+        .body(Either::Left(Full::new(Bytes::from(body))))
+        .expect("static headers are valid")
+}
+
+fn empty(status: StatusCode) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .body(Either::Left(Full::new(Bytes::new())))
+        .expect("static headers are valid")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::LoadOptions;
+    use serde_json::{json, Value};
+    use tempfile::TempDir;
+
+    const BOTH: &str = "application/json, text/event-stream";
+
+    struct TestServer {
+        url: String,
+        client: reqwest::Client,
+        _config: TempDir,
+    }
+
+    impl TestServer {
+        async fn start(options: HttpOptions) -> Self {
+            // An empty config, so tool calls never reach a real provider
+            let config = TempDir::new().unwrap();
+            let path = config.path().join("config.yaml");
+            std::fs::write(&path, "").unwrap();
+            let server = McpServer::new().with_load_options(LoadOptions {
+                config_file: Some(path),
+                profile: None,
+            });
+
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let url = format!("http://{}{}", listener.local_addr().unwrap(), ENDPOINT);
+            tokio::spawn(serve(Arc::new(server), listener, options));
+            Self {
+                url,
+                client: reqwest::Client::new(),
+                _config: config,
+            }
+        }
+
+        fn post(&self, session: Option<&str>, body: &Value) -> reqwest::RequestBuilder {
+            self.post_accepting(session, body, BOTH)
+        }
+
+        fn post_accepting(
+            &self,
+            session: Option<&str>,
+            body: &Value,
+            accept: &str,
+        ) -> reqwest::RequestBuilder {
+            let request = self
+                .client
+                .post(&self.url)
+                .header("Accept", accept)
+                .json(body);
+            match session {
+                Some(session) => request.header("Mcp-Session-Id", session),
+                None => request,
+            }
+        }
+
+        async fn initialize(&self) -> String {
+            let response = self
+                .post(
+                    None,
+                    &json!({"jsonrpc": "2.0", "id": 1, "method": "initialize",
+                            "params": {"protocolVersion": "2025-06-18"}}),
+                )
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(response.status(), 200);
+            response.headers()["mcp-session-id"]
+                .to_str()
+                .unwrap()
+                .to_string()
+        }
+    }
+
+    fn list_tools(id: u64) -> Value {
+        json!({"jsonrpc": "2.0", "id": id, "method": "tools/list"})
+    }
+
+    #[tokio::test]
+    async fn test_initialize_opens_session() {
+        let server = TestServer::start(HttpOptions::default()).await;
+        let session = server.initialize().await;
+        assert_eq!(session.len(), 32);
+        assert_ne!(session, server.initialize().await);
+
+        let response = server
+            .post(Some(&session), &list_tools(2))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.headers()["content-type"], "application/json");
+        let reply: Value = response.json().await.unwrap();
+        assert_eq!(reply["id"], 2);
+        assert!(reply["result"]["tools"].is_array());
+    }
+
+    #[tokio::test]
+    async fn test_failed_initialize_opens_no_session() {
+        let server = TestServer::start(HttpOptions::default()).await;
+        let response = server
+            .post(
+                None,
+                &json!({"jsonrpc": "2.0", "id": 1, "method": "initialize"}),
+            )
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+        assert!(response.headers().get("mcp-session-id").is_none());
+        let reply: Value = response.json().await.unwrap();
+        assert_eq!(reply["error"]["code"], -32602);
+    }
+
+    #[tokio::test]
+    async fn test_requests_need_a_session() {
+        let server = TestServer::start(HttpOptions::default()).await;
+        let missing = server.post(None, &list_tools(1)).send().await.unwrap();
+        assert_eq!(missing.status(), 400);
+        let unknown = server
+            .post(Some("not-a-session"), &list_tools(1))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(unknown.status(), 404);
+    }
+
+    #[tokio::test]
+    async fn test_delete_ends_session() {
+        let server = TestServer::start(HttpOptions::default()).await;
+        let session = server.initialize().await;
+
+        let deleted = server
+            .client
+            .delete(&server.url)
+            .header("Mcp-Session-Id", &session)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(deleted.status(), 204);
+
+        let after = server
+            .post(Some(&session), &list_tools(2))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(after.status(), 404);
+        let again = server
+            .client
+            .delete(&server.url)
+            .header("Mcp-Session-Id", &session)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(again.status(), 404);
+    }
+
+    #[tokio::test]
+    async fn test_notifications_are_accepted() {
+        let server = TestServer::start(HttpOptions::default()).await;
+        let session = server.initialize().await;
+        let response = server
+            .post(
+                Some(&session),
+                &json!({"jsonrpc": "2.0", "method": "notifications/initialized"}),
+            )
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 202);
+        assert!(response.text().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_batch_replies_with_array() {
+        let server = TestServer::start(HttpOptions::default()).await;
+        let session = server.initialize().await;
+        let batch = json!([
+            list_tools(2),
+            {"jsonrpc": "2.0", "method": "notifications/initialized"},
+            {"jsonrpc": "2.0", "id": 3, "method": "ping"},
+        ]);
+        let response = server.post(Some(&session), &batch).send().await.unwrap();
+        let replies: Value = response.json().await.unwrap();
+        let ids: Vec<_> = replies
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| r["id"].clone())
+            .collect();
+        assert_eq!(ids, vec![json!(2), json!(3)]);
+    }
+
+    #[tokio::test]
+    async fn test_tool_call_streams_over_sse() {
+        let server = TestServer::start(HttpOptions::default()).await;
+        let session = server.initialize().await;
+        let call = json!({"jsonrpc": "2.0", "id": 7, "method": "tools/call",
+                          "params": {"name": "web_search", "arguments": {"query": "rust"}}});
+
+        let response = server.post(Some(&session), &call).send().await.unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.headers()["content-type"], "text/event-stream");
+        let body = response.text().await.unwrap();
+        let data = body
+            .strip_prefix("event: message\ndata: ")
+            .and_then(|rest| rest.strip_suffix("\n\n"))
+            .unwrap_or_else(|| panic!("not one SSE event: {:?}", body));
+        let reply: Value = serde_json::from_str(data).unwrap();
+        assert_eq!(reply["id"], 7);
+        // No providers are configured
+        assert_eq!(reply["result"]["isError"], true);
+
+        // Clients that only take JSON get JSON
+        let response = server
+            .post_accepting(Some(&session), &call, "application/json")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.headers()["content-type"], "application/json");
+        let reply: Value = response.json().await.unwrap();
+        assert_eq!(reply["id"], 7);
+    }
+
+    #[tokio::test]
+    async fn test_origin_check() {
+        let server = TestServer::start(HttpOptions {
+            allowed_origins: vec!["https://app.example.com".to_string()],
+            ..Default::default()
+        })
+        .await;
+        for (origin, status) in [
+            ("https://evil.example.com", 403),
+            ("null", 403),
+            ("http://localhost:3000", 200),
+            ("http://127.0.0.1", 200),
+            ("http://[::1]:8080", 200),
+            ("https://app.example.com", 200),
+        ] {
+            let response = server
+                .post(
+                    None,
+                    &json!({"jsonrpc": "2.0", "id": 1, "method": "initialize",
+                            "params": {"protocolVersion": "2025-06-18"}}),
+                )
+                .header("Origin", origin)
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(response.status(), status, "origin {}", origin);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_bearer_token() {
+        let server = TestServer::start(HttpOptions {
+            auth_token: Some("s3cret".to_string()),
+            ..Default::default()
+        })
+        .await;
+        let initialize = json!({"jsonrpc": "2.0", "id": 1, "method": "initialize",
+                                "params": {"protocolVersion": "2025-06-18"}});
+
+        let missing = server.post(None, &initialize).send().await.unwrap();
+        assert_eq!(missing.status(), 401);
+        assert_eq!(missing.headers()["www-authenticate"], "Bearer");
+        let wrong = server
+            .post(None, &initialize)
+            .bearer_auth("guess")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(wrong.status(), 401);
+        let right = server
+            .post(None, &initialize)
+            .bearer_auth("s3cret")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(right.status(), 200);
+    }
+
+    #[tokio::test]
+    async fn test_rejected_requests() {
+        let server = TestServer::start(HttpOptions::default()).await;
+        let session = server.initialize().await;
+
+        let get = server.client.get(&server.url).send().await.unwrap();
+        assert_eq!(get.status(), 405);
+        assert_eq!(get.headers()["allow"], "POST, DELETE");
+
+        let elsewhere = server.url.replace(ENDPOINT, "/other");
+        let response = server.client.post(elsewhere).send().await.unwrap();
+        assert_eq!(response.status(), 404);
+
+        let version = server
+            .post(Some(&session), &list_tools(2))
+            .header("MCP-Protocol-Version", "1999-01-01")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(version.status(), 400);
+
+        let accept = server
+            .post_accepting(Some(&session), &list_tools(2), "text/html")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(accept.status(), 406);
+
+        let malformed = server
+            .client
+            .post(&server.url)
+            .header("Content-Type", "application/json")
+            .body("{not json")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(malformed.status(), 400);
+        let reply: Value = malformed.json().await.unwrap();
+        assert_eq!(reply["error"]["code"], -32700);
+    }
+
+    #[test]
+    fn test_accepts() {
+        let mut headers = HeaderMap::new();
+        assert!(accepts(&headers, "text/event-stream"));
+        headers.insert(
+            header::ACCEPT,
+            HeaderValue::from_static("application/json;q=0.9, text/plain"),
+        );
+        assert!(accepts(&headers, "application/json"));
+        assert!(!accepts(&headers, "text/event-stream"));
+        headers.insert(header::ACCEPT, HeaderValue::from_static("*/*"));
+        assert!(accepts(&headers, "text/event-stream"));
+    }
+}
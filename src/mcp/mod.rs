@@ -0,0 +1,1576 @@
+//! MCP (Model Context Protocol) server implementation
+//!
+//! This module provides an MCP server that exposes web search and URL fetch
+//! capabilities as tools for AI agents. It implements the MCP specification
+//! using JSON-RPC 2.0 over stdio or Streamable HTTP; both transports hand
+//! messages to the same `McpServer` dispatcher.
+//!
+//! Reference: https://modelcontextprotocol.io/
+
+mod http;
+mod state;
+mod stdio;
+
+use crate::cli::{
+    DateRange, FetchFormat, OutputFormat, Provider, SafeSearch, SchemaKind, ServeArgs, Transport,
+};
+use crate::config::{DefaultsConfig, LoadOptions};
+use crate::error::{Result, SearchError};
+use crate::fetch::{ContentFormat, FetchResponse};
+use crate::output::{get_formatter, SearchResponse};
+use crate::providers::SearchOptions;
+use crate::schema::schema_for_kind;
+use clap::ValueEnum;
+use schemars::gen::SchemaGenerator;
+use schemars::schema::{InstanceType, Schema, SchemaObject};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use state::ServerState;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::{oneshot, Semaphore};
+
+/// MCP revisions this server speaks, newest first
+pub const SUPPORTED_PROTOCOL_VERSIONS: &[&str] = &["2025-06-18", "2025-03-26", "2024-11-05"];
+
+/// Tool calls that run at once unless `--max-in-flight` says otherwise
+pub const DEFAULT_MAX_IN_FLIGHT: usize = 8;
+
+/// JSON-RPC 2.0 request structure
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonRpcRequest {
+    pub jsonrpc: String,
+    /// Missing for notifications, which never get a response
+    #[serde(default)]
+    pub id: Option<serde_json::Value>,
+    pub method: String,
+    #[serde(default)]
+    pub params: Option<serde_json::Value>,
+}
+
+/// JSON-RPC 2.0 response structure
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonRpcResponse {
+    pub jsonrpc: String,
+    /// `null` when the request's id couldn't be read
+    pub id: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<JsonRpcError>,
+}
+
+/// JSON-RPC 2.0 error structure
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonRpcError {
+    pub code: i32,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<serde_json::Value>,
+}
+
+impl JsonRpcResponse {
+    /// Create a success response
+    pub fn success(id: Option<serde_json::Value>, result: serde_json::Value) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            id,
+            result: Some(result),
+            error: None,
+        }
+    }
+
+    /// Create an error response
+    pub fn error(id: Option<serde_json::Value>, code: i32, message: String) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            id,
+            result: None,
+            error: Some(JsonRpcError {
+                code,
+                message,
+                data: None,
+            }),
+        }
+    }
+}
+
+// MCP Protocol Types
+
+/// MCP Server capabilities
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerCapabilities {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tools: Option<ToolsCapability>,
+}
+
+/// Tools capability
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ToolsCapability {
+    #[serde(rename = "listChanged", skip_serializing_if = "Option::is_none")]
+    pub list_changed: Option<bool>,
+}
+
+/// MCP Server info
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerInfo {
+    pub name: String,
+    pub version: String,
+}
+
+/// MCP Initialize result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InitializeResult {
+    #[serde(rename = "protocolVersion")]
+    pub protocol_version: String,
+    pub capabilities: ServerCapabilities,
+    #[serde(rename = "serverInfo")]
+    pub server_info: ServerInfo,
+}
+
+/// MCP Tool definition
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tool {
+    pub name: String,
+    pub description: String,
+    #[serde(rename = "inputSchema")]
+    pub input_schema: serde_json::Value,
+    /// Schema of the tool's `structuredContent`
+    #[serde(rename = "outputSchema", skip_serializing_if = "Option::is_none")]
+    pub output_schema: Option<serde_json::Value>,
+}
+
+/// MCP List tools result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListToolsResult {
+    pub tools: Vec<Tool>,
+}
+
+/// MCP Tool content
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolContent {
+    #[serde(rename = "type")]
+    pub content_type: String,
+    pub text: String,
+}
+
+/// MCP Call tool result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CallToolResult {
+    pub content: Vec<ToolContent>,
+    /// The result as JSON matching the tool's `outputSchema`; `content`
+    /// carries the same result as text for clients that predate it
+    #[serde(rename = "structuredContent", skip_serializing_if = "Option::is_none")]
+    pub structured_content: Option<serde_json::Value>,
+    #[serde(rename = "isError", skip_serializing_if = "Option::is_none")]
+    pub is_error: Option<bool>,
+}
+
+// Tool Input Types
+
+/// Input parameters for the web_search tool
+///
+/// Options left out fall back to the config's `defaults` section, as they do
+/// on the command line.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct WebSearchInput {
+    /// The search query string
+    pub query: String,
+    /// Number of results to return, at least 1 (default: `defaults.num_results`
+    /// from the config, usually 10)
+    #[serde(default)]
+    #[schemars(range(min = 1))]
+    pub num_results: Option<usize>,
+    /// Search provider to try first; if it fails, the other configured
+    /// providers are tried in fallback order (default: the configured default
+    /// provider)
+    #[serde(default)]
+    #[schemars(schema_with = "provider_schema")]
+    pub provider: Option<String>,
+    /// Only return results published within this period: "day" (past 24
+    /// hours), "week", "month" or "year". Omit for no date restriction.
+    #[serde(default)]
+    #[schemars(schema_with = "value_enum_schema::<DateRange>")]
+    pub date_range: Option<DateRange>,
+    /// Filtering of adult content: "off", "moderate" or "strict" (default:
+    /// `defaults.safe_search` from the config, usually "moderate")
+    #[serde(default)]
+    #[schemars(schema_with = "value_enum_schema::<SafeSearch>")]
+    pub safe_search: Option<SafeSearch>,
+    /// Only return results from these domains, e.g. ["rust-lang.org"].
+    /// Supported by the google and tavily providers; others ignore it.
+    #[serde(default)]
+    pub include_domains: Option<Vec<String>>,
+    /// Leave out results from these domains, e.g. ["pinterest.com"].
+    /// Supported by the tavily provider; others ignore it.
+    #[serde(default)]
+    pub exclude_domains: Option<Vec<String>>,
+    /// Seconds to wait for each provider, at least 1 (default:
+    /// `defaults.timeout` from the config, usually 30)
+    #[serde(default)]
+    #[schemars(range(min = 1))]
+    pub timeout: Option<u64>,
+    /// How to format the results: "text" (a numbered list, the default),
+    /// "markdown", or "json" (the full response with metadata, as printed by
+    /// `--format json`)
+    #[serde(default)]
+    #[schemars(schema_with = "value_enum_schema::<OutputFormat>")]
+    pub format: Option<OutputFormat>,
+}
+
+impl WebSearchInput {
+    /// Parse tool arguments, rejecting values the search can't use
+    ///
+    /// Provider names are normalized to the registry's names (`ddg` becomes
+    /// `duckduckgo`).
+    pub fn parse(arguments: serde_json::Value) -> std::result::Result<Self, String> {
+        let mut input: Self = serde_json::from_value(arguments).map_err(|e| e.to_string())?;
+
+        if input.query.trim().is_empty() {
+            return Err("query must not be empty".to_string());
+        }
+        if input.num_results == Some(0) {
+            return Err("num_results must be at least 1".to_string());
+        }
+        if input.timeout == Some(0) {
+            return Err("timeout must be at least 1 second".to_string());
+        }
+        if let Some(ref name) = input.provider {
+            input.provider = Some(provider_name(name)?);
+        }
+        for (field, domains) in [
+            ("include_domains", &input.include_domains),
+            ("exclude_domains", &input.exclude_domains),
+        ] {
+            for domain in domains.iter().flatten() {
+                if domain.is_empty() || domain.contains(|c: char| c.is_whitespace() || c == '/') {
+                    return Err(format!(
+                        "invalid domain '{}' in {} (expected a bare domain such as example.com)",
+                        domain, field
+                    ));
+                }
+            }
+        }
+
+        Ok(input)
+    }
+
+    /// Search options for this input, using `defaults` for what it leaves out
+    pub fn search_options(&self, defaults: &DefaultsConfig) -> SearchOptions {
+        let safe_search = self.safe_search.clone().unwrap_or_else(|| {
+            SafeSearch::from_str(&defaults.safe_search, true).unwrap_or_default()
+        });
+
+        SearchOptions::new()
+            .with_num_results(self.num_results.unwrap_or(defaults.num_results))
+            .with_safe_search(safe_search)
+            .with_date_range(self.date_range.clone())
+            .with_include_domains(self.include_domains.clone())
+            .with_exclude_domains(self.exclude_domains.clone())
+            .with_timeout(Duration::from_secs(
+                self.timeout.unwrap_or(defaults.timeout),
+            ))
+    }
+}
+
+/// The registry name for a provider, accepting the CLI's names too
+fn provider_name(name: &str) -> std::result::Result<String, String> {
+    Provider::value_variants()
+        .iter()
+        .find(|p| p.to_string() == name.to_lowercase())
+        .cloned()
+        .or_else(|| Provider::from_str(name, true).ok())
+        .map(|p| p.to_string())
+        .ok_or_else(|| {
+            format!(
+                "unknown provider '{}' (expected one of: {})",
+                name,
+                provider_names().join(", ")
+            )
+        })
+}
+
+fn provider_names() -> Vec<String> {
+    Provider::value_variants()
+        .iter()
+        .map(ToString::to_string)
+        .collect()
+}
+
+/// A plain string enum, which clients handle better than `oneOf` variants
+fn string_enum_schema(values: Vec<String>) -> Schema {
+    SchemaObject {
+        instance_type: Some(InstanceType::String.into()),
+        enum_values: Some(values.into_iter().map(Into::into).collect()),
+        ..Default::default()
+    }
+    .into()
+}
+
+fn value_enum_schema<T: ValueEnum>(_: &mut SchemaGenerator) -> Schema {
+    string_enum_schema(
+        T::value_variants()
+            .iter()
+            .filter_map(|v| v.to_possible_value())
+            .map(|v| v.get_name().to_string())
+            .collect(),
+    )
+}
+
+fn provider_schema(_: &mut SchemaGenerator) -> Schema {
+    string_enum_schema(provider_names())
+}
+
+/// Input parameters for the fetch_url tool
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct FetchUrlInput {
+    /// The URL to fetch
+    pub url: String,
+    /// Output format: "text" (the default), "html", or "markdown"
+    #[serde(default)]
+    #[schemars(schema_with = "value_enum_schema::<FetchFormat>")]
+    pub format: Option<FetchFormat>,
+    /// Maximum content length in bytes (0 = no limit)
+    #[serde(default)]
+    pub max_length: Option<usize>,
+}
+
+impl FetchUrlInput {
+    /// Format the page content is converted to
+    fn content_format(&self) -> ContentFormat {
+        match self.format.clone().unwrap_or_default() {
+            FetchFormat::Text => ContentFormat::Text,
+            FetchFormat::Html => ContentFormat::Html,
+            FetchFormat::Markdown => ContentFormat::Markdown,
+        }
+    }
+}
+
+/// What a tool call produced, as text and as structured data
+#[derive(Debug, Clone)]
+pub struct ToolOutput {
+    pub text: String,
+    pub structured_content: serde_json::Value,
+}
+
+impl ToolOutput {
+    fn new(text: String, response: &impl Serialize) -> Result<Self> {
+        let structured_content = serde_json::to_value(response)
+            .map_err(|e| SearchError::api("mcp", format!("Failed to serialize result: {}", e)))?;
+        Ok(Self {
+            text,
+            structured_content,
+        })
+    }
+}
+
+/// MCP Server for cli-web-search
+pub struct McpServer {
+    /// Server name
+    name: String,
+    /// Server version
+    version: String,
+    /// Which configuration to load
+    load_options: LoadOptions,
+    /// Config, providers, cache and clients, loaded by the first tool call
+    state: RwLock<Option<Arc<ServerState>>>,
+    /// Tool calls that `notifications/cancelled` can abort, by session and
+    /// request id
+    in_flight: Mutex<HashMap<(String, String), oneshot::Sender<()>>>,
+    /// One permit per tool call allowed to run at once
+    call_slots: Semaphore,
+}
+
+/// A tools/call request whose arguments have been checked
+enum ToolCall {
+    WebSearch(WebSearchInput),
+    FetchUrl(FetchUrlInput),
+}
+
+impl Default for McpServer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl McpServer {
+    /// Create a new MCP server instance
+    pub fn new() -> Self {
+        Self {
+            name: "cli-web-search".to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            load_options: LoadOptions::default(),
+            state: RwLock::new(None),
+            in_flight: Mutex::new(HashMap::new()),
+            call_slots: Semaphore::new(DEFAULT_MAX_IN_FLIGHT),
+        }
+    }
+
+    /// Run at most `max` tool calls at once (at least one)
+    pub fn with_max_in_flight(mut self, max: usize) -> Self {
+        self.call_slots = Semaphore::new(max.max(1));
+        self
+    }
+
+    /// Load configuration with these options (e.g. a profile)
+    pub fn with_load_options(mut self, load_options: LoadOptions) -> Self {
+        self.load_options = load_options;
+        self
+    }
+
+    /// The loaded state, loading it on first use (and after a failed load)
+    fn state(&self) -> Result<Arc<ServerState>> {
+        if let Some(ref state) = *self.state.read().unwrap() {
+            return Ok(Arc::clone(state));
+        }
+        let mut slot = self.state.write().unwrap();
+        if let Some(ref state) = *slot {
+            return Ok(Arc::clone(state));
+        }
+        let state = Arc::new(ServerState::load(&self.load_options, None)?);
+        *slot = Some(Arc::clone(&state));
+        Ok(state)
+    }
+
+    /// Reload the configuration; on failure the previous state stays in use.
+    /// Calls already running finish with the state they started with.
+    pub fn reload(&self) -> Result<()> {
+        let previous = self.state.read().unwrap().clone();
+        let state = ServerState::load(&self.load_options, previous.as_deref())?;
+        *self.state.write().unwrap() = Some(Arc::new(state));
+        Ok(())
+    }
+
+    /// Get the list of available tools
+    pub fn list_tools(&self) -> ListToolsResult {
+        let web_search_schema = schemars::schema_for!(WebSearchInput);
+        let fetch_url_schema = schemars::schema_for!(FetchUrlInput);
+
+        ListToolsResult {
+            tools: vec![
+                Tool {
+                    name: "web_search".to_string(),
+                    description: "Search the web using configured search providers. Returns a list of search results with titles, URLs, and snippets. Results can be limited by date range and domain, and formatted as text, markdown or JSON.".to_string(),
+                    input_schema: serde_json::to_value(web_search_schema).unwrap_or_default(),
+                    output_schema: Some(schema_for_kind(&SchemaKind::SearchResponse)),
+                },
+                Tool {
+                    name: "fetch_url".to_string(),
+                    description: "Fetch the content of a web page and convert it to text or markdown. Useful for reading web pages.".to_string(),
+                    input_schema: serde_json::to_value(fetch_url_schema).unwrap_or_default(),
+                    output_schema: Some(schema_for_kind(&SchemaKind::FetchResponse)),
+                },
+            ],
+        }
+    }
+
+    /// Handle one message from a transport: a request, a notification or a
+    /// batch of them
+    ///
+    /// Returns the serialized reply, or `None` when nothing should be sent
+    /// back (notifications, cancelled requests and batches of only those).
+    /// `session` keeps the request ids of different HTTP sessions apart; the
+    /// stdio transport has none.
+    pub async fn handle_message(&self, session: Option<&str>, message: &str) -> Option<String> {
+        let message: serde_json::Value = match serde_json::from_str(message) {
+            Ok(message) => message,
+            Err(e) => {
+                let response = JsonRpcResponse::error(None, -32700, format!("Parse error: {}", e));
+                return Some(serde_json::to_string(&response).unwrap_or_default());
+            }
+        };
+
+        let reply = match message {
+            serde_json::Value::Array(batch) if batch.is_empty() => serde_json::to_string(
+                &JsonRpcResponse::error(None, -32600, "Invalid Request: empty batch".to_string()),
+            ),
+            serde_json::Value::Array(batch) => {
+                let mut responses = Vec::new();
+                for message in batch {
+                    responses.extend(self.handle_value(session, message).await);
+                }
+                if responses.is_empty() {
+                    return None;
+                }
+                serde_json::to_string(&responses)
+            }
+            message => serde_json::to_string(&self.handle_value(session, message).await?),
+        };
+        reply.ok()
+    }
+
+    /// Handle a single (non-batch) message
+    async fn handle_value(
+        &self,
+        session: Option<&str>,
+        message: serde_json::Value,
+    ) -> Option<JsonRpcResponse> {
+        // Reply to malformed requests with their id, when there is one
+        let id = message.get("id").cloned();
+        match serde_json::from_value::<JsonRpcRequest>(message) {
+            Ok(request) if request.jsonrpc == "2.0" => self.handle_request(session, request).await,
+            Ok(_) => Some(JsonRpcResponse::error(
+                id,
+                -32600,
+                "Invalid Request: jsonrpc must be \"2.0\"".to_string(),
+            )),
+            Err(e) => Some(JsonRpcResponse::error(
+                id,
+                -32600,
+                format!("Invalid Request: {}", e),
+            )),
+        }
+    }
+
+    /// Handle a JSON-RPC request, or a notification (which gets no response)
+    pub async fn handle_request(
+        &self,
+        session: Option<&str>,
+        request: JsonRpcRequest,
+    ) -> Option<JsonRpcResponse> {
+        let id = match request.id {
+            Some(id) if !id.is_null() => id,
+            _ => {
+                self.handle_notification(session, &request.method, request.params);
+                return None;
+            }
+        };
+
+        let response = match request.method.as_str() {
+            "initialize" => self.handle_initialize(Some(id), request.params),
+            "tools/list" => self.handle_list_tools(Some(id)),
+            "tools/call" => return self.handle_call_tool(session, id, request.params).await,
+            "ping" => JsonRpcResponse::success(Some(id), serde_json::json!({})),
+            method => {
+                // Unknown method
+                JsonRpcResponse::error(Some(id), -32601, format!("Method not found: {}", method))
+            }
+        };
+        Some(response)
+    }
+
+    fn handle_notification(
+        &self,
+        session: Option<&str>,
+        method: &str,
+        params: Option<serde_json::Value>,
+    ) {
+        // notifications/initialized and anything unknown need no action
+        if method == "notifications/cancelled" {
+            if let Some(id) = params.as_ref().and_then(|p| p.get("requestId")) {
+                self.cancel(session, id);
+            }
+        }
+    }
+
+    /// Abort the tool call with this request id, if it is still running
+    fn cancel(&self, session: Option<&str>, id: &serde_json::Value) -> bool {
+        let cancel = self
+            .in_flight
+            .lock()
+            .unwrap()
+            .remove(&in_flight_key(session, id));
+        cancel.is_some_and(|cancel| cancel.send(()).is_ok())
+    }
+
+    /// Abort every tool call still running in an ended HTTP session
+    fn cancel_session(&self, session: &str) {
+        let mut in_flight = self.in_flight.lock().unwrap();
+        let ids: Vec<_> = in_flight
+            .keys()
+            .filter(|(s, _)| s == session)
+            .cloned()
+            .collect();
+        for id in ids {
+            if let Some(cancel) = in_flight.remove(&id) {
+                let _ = cancel.send(());
+            }
+        }
+    }
+
+    /// Run `future` unless the request `id` is cancelled first
+    async fn cancellable<T>(
+        &self,
+        session: Option<&str>,
+        id: &serde_json::Value,
+        future: impl Future<Output = T>,
+    ) -> Option<T> {
+        let key = in_flight_key(session, id);
+        let (cancel, cancelled) = oneshot::channel();
+        self.in_flight.lock().unwrap().insert(key.clone(), cancel);
+
+        let result = tokio::select! {
+            result = future => Some(result),
+            Ok(()) = cancelled => None,
+        };
+        self.in_flight.lock().unwrap().remove(&key);
+        result
+    }
+
+    fn handle_initialize(
+        &self,
+        id: Option<serde_json::Value>,
+        params: Option<serde_json::Value>,
+    ) -> JsonRpcResponse {
+        let requested = params
+            .as_ref()
+            .and_then(|p| p.get("protocolVersion"))
+            .and_then(|v| v.as_str());
+        let requested = match requested {
+            Some(version) => version,
+            None => {
+                return JsonRpcResponse::error(id, -32602, "Missing protocolVersion".to_string());
+            }
+        };
+
+        let result = InitializeResult {
+            protocol_version: negotiate_protocol_version(requested).to_string(),
+            capabilities: ServerCapabilities {
+                tools: Some(ToolsCapability::default()),
+            },
+            server_info: ServerInfo {
+                name: self.name.clone(),
+                version: self.version.clone(),
+            },
+        };
+
+        JsonRpcResponse::success(id, serde_json::to_value(result).unwrap_or_default())
+    }
+
+    fn handle_list_tools(&self, id: Option<serde_json::Value>) -> JsonRpcResponse {
+        let result = self.list_tools();
+        JsonRpcResponse::success(id, serde_json::to_value(result).unwrap_or_default())
+    }
+
+    /// Run a tool; `None` means the call was cancelled and gets no response
+    async fn handle_call_tool(
+        &self,
+        session: Option<&str>,
+        id: serde_json::Value,
+        params: Option<serde_json::Value>,
+    ) -> Option<JsonRpcResponse> {
+        let call = match parse_tool_call(params) {
+            Ok(call) => call,
+            Err(message) => return Some(JsonRpcResponse::error(Some(id), -32602, message)),
+        };
+
+        // Waiting for a slot counts as in flight, so queued calls can be
+        // cancelled too
+        let run = async {
+            let _slot = self.call_slots.acquire().await;
+            self.run_tool(call).await
+        };
+        let result = self.cancellable(session, &id, run).await?;
+
+        let call_result = match result {
+            Ok(output) => CallToolResult {
+                content: vec![ToolContent {
+                    content_type: "text".to_string(),
+                    text: output.text,
+                }],
+                structured_content: Some(output.structured_content),
+                is_error: None,
+            },
+            Err(e) => CallToolResult {
+                content: vec![ToolContent {
+                    content_type: "text".to_string(),
+                    text: format!("Error: {}", e),
+                }],
+                structured_content: None,
+                is_error: Some(true),
+            },
+        };
+        Some(JsonRpcResponse::success(
+            Some(id),
+            serde_json::to_value(call_result).unwrap_or_default(),
+        ))
+    }
+
+    async fn run_tool(&self, call: ToolCall) -> Result<ToolOutput> {
+        match call {
+            ToolCall::WebSearch(input) => self.execute_web_search(input).await,
+            ToolCall::FetchUrl(input) => self.execute_fetch_url(input).await,
+        }
+    }
+
+    async fn execute_web_search(&self, input: WebSearchInput) -> Result<ToolOutput> {
+        let state = self.state()?;
+
+        // Check if any providers are configured
+        if state.registry.configured_providers().is_empty() {
+            return Err(SearchError::NoProvidersConfigured);
+        }
+
+        let options = input.search_options(&state.config.defaults);
+        let provider = input.provider.as_deref();
+
+        let response = match state.cache.get(&input.query, provider, &options) {
+            Some((results, cached_provider)) => {
+                SearchResponse::new(input.query.clone(), cached_provider, results, 0)
+                    .with_cached(true)
+            }
+            None => {
+                let start = Instant::now();
+                let mut attempts = Vec::new();
+                let (results, provider_used) = state
+                    .registry
+                    .search_with_fallback(&input.query, &options, provider, &mut attempts)
+                    .await?;
+                let search_time_ms = start.elapsed().as_millis() as u64;
+                state.cache.set(
+                    &input.query,
+                    provider,
+                    &options,
+                    provider_used,
+                    results.clone(),
+                );
+
+                SearchResponse::new(
+                    input.query.clone(),
+                    provider_used.to_string(),
+                    results,
+                    search_time_ms,
+                )
+                .with_attempts(attempts)
+            }
+        };
+
+        let text = match input.format {
+            None | Some(OutputFormat::Text) => search_text(&response),
+            Some(ref format) => get_formatter(format, false).format(&response),
+        };
+        ToolOutput::new(text, &response)
+    }
+
+    async fn execute_fetch_url(&self, input: FetchUrlInput) -> Result<ToolOutput> {
+        let fetcher = self
+            .state()?
+            .fetcher
+            .with_output(input.content_format(), input.max_length.unwrap_or(0));
+
+        // Fetch the URL
+        let response = fetcher.fetch(&input.url).await?;
+
+        ToolOutput::new(fetch_text(&response), &response)
+    }
+}
+
+/// Search results as a numbered list for the AI
+fn search_text(response: &SearchResponse) -> String {
+    let timing = if response.metadata.cached {
+        "cached".to_string()
+    } else {
+        format!("{}ms", response.metadata.search_time_ms)
+    };
+    let mut output = format!(
+        "Search results for: \"{}\"\nProvider: {} | Results: {} | Time: {}\n\n",
+        response.metadata.query,
+        response.metadata.provider,
+        response.results.len(),
+        timing
+    );
+
+    for (i, result) in response.results.iter().enumerate() {
+        let snippet = if result.snippet.is_empty() {
+            "No description available"
+        } else {
+            &result.snippet
+        };
+        output.push_str(&format!(
+            "{}. {}\n   URL: {}\n   {}\n\n",
+            i + 1,
+            result.title,
+            result.url,
+            snippet
+        ));
+    }
+
+    output
+}
+
+/// Page content with a short metadata header
+fn fetch_text(response: &FetchResponse) -> String {
+    let mut output = String::new();
+
+    if let Some(title) = &response.title {
+        output.push_str(&format!("Title: {}\n", title));
+    }
+    output.push_str(&format!("URL: {}\n", response.final_url));
+    output.push_str(&format!(
+        "Content Length: {} bytes\n",
+        response.content_length
+    ));
+    output.push_str("---\n\n");
+    output.push_str(&response.content);
+
+    output
+}
+
+/// The version to answer `initialize` with: the client's if this server
+/// speaks it, otherwise the newest one (the client then decides whether to
+/// carry on)
+pub fn negotiate_protocol_version(requested: &str) -> &'static str {
+    SUPPORTED_PROTOCOL_VERSIONS
+        .iter()
+        .find(|version| **version == requested)
+        .unwrap_or(&SUPPORTED_PROTOCOL_VERSIONS[0])
+}
+
+fn in_flight_key(session: Option<&str>, id: &serde_json::Value) -> (String, String) {
+    (session.unwrap_or_default().to_string(), id.to_string())
+}
+
+/// Check tools/call params, giving the message for an "invalid params" error
+fn parse_tool_call(params: Option<serde_json::Value>) -> std::result::Result<ToolCall, String> {
+    let params = params.ok_or("Missing params")?;
+    let tool_name = params
+        .get("name")
+        .and_then(|v| v.as_str())
+        .ok_or("Missing tool name")?;
+    let arguments = params.get("arguments").cloned().unwrap_or_default();
+
+    let invalid = |message: String| format!("Invalid arguments for {}: {}", tool_name, message);
+    match tool_name {
+        "web_search" => WebSearchInput::parse(arguments)
+            .map(ToolCall::WebSearch)
+            .map_err(invalid),
+        "fetch_url" => serde_json::from_value(arguments)
+            .map(ToolCall::FetchUrl)
+            .map_err(|e| invalid(e.to_string())),
+        _ => Err(format!("Unknown tool: {}", tool_name)),
+    }
+}
+
+/// Run the MCP server on the transport `args` chooses
+pub async fn run_mcp_server(load_options: LoadOptions, args: &ServeArgs) -> Result<()> {
+    let server = McpServer::new()
+        .with_load_options(load_options)
+        .with_max_in_flight(args.max_in_flight as usize);
+    let server = Arc::new(server);
+    let watcher = tokio::spawn(state::watch_config(Arc::clone(&server)));
+
+    let result = match args.transport {
+        Transport::Stdio => {
+            let stdin = tokio::io::BufReader::new(tokio::io::stdin());
+            stdio::serve(server, stdin, tokio::io::stdout()).await
+        }
+        Transport::Http => {
+            let listener = tokio::net::TcpListener::bind(args.bind).await?;
+            if !args.bind.ip().is_loopback() && args.auth_token.is_none() {
+                tracing::warn!(
+                    "Listening on {} without --auth-token: anyone who can reach it can \
+                     search with your API keys",
+                    args.bind
+                );
+            }
+            eprintln!(
+                "MCP server listening on http://{}{}",
+                listener.local_addr()?,
+                http::ENDPOINT
+            );
+            let options = http::HttpOptions {
+                auth_token: args.auth_token.clone(),
+                allowed_origins: args.allowed_origins.clone(),
+            };
+            http::serve(server, listener, options).await
+        }
+    };
+    watcher.abort();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_web_search_input_defaults() {
+        let json = r#"{"query": "test"}"#;
+        let input: WebSearchInput = serde_json::from_str(json).unwrap();
+        assert_eq!(input.query, "test");
+        assert_eq!(input.num_results, None);
+        assert!(input.provider.is_none());
+
+        // Left-out options come from the config defaults
+        let defaults = DefaultsConfig {
+            num_results: 7,
+            safe_search: "strict".to_string(),
+            timeout: 12,
+            format: "json".to_string(),
+        };
+        let options = input.search_options(&defaults);
+        assert_eq!(options.num_results, 7);
+        assert_eq!(options.safe_search, SafeSearch::Strict);
+        assert_eq!(options.timeout, Duration::from_secs(12));
+        assert!(options.date_range.is_none());
+    }
+
+    #[test]
+    fn test_web_search_input_all_options() {
+        let input = WebSearchInput::parse(serde_json::json!({
+            "query": "rust",
+            "num_results": 3,
+            "provider": "ddg",
+            "date_range": "week",
+            "safe_search": "off",
+            "include_domains": ["rust-lang.org"],
+            "exclude_domains": ["example.com"],
+            "timeout": 5,
+            "format": "json"
+        }))
+        .unwrap();
+        assert_eq!(input.provider.as_deref(), Some("duckduckgo"));
+        assert_eq!(input.format, Some(OutputFormat::Json));
+
+        let options = input.search_options(&DefaultsConfig::default());
+        assert_eq!(options.num_results, 3);
+        assert_eq!(options.safe_search, SafeSearch::Off);
+        assert_eq!(options.date_range, Some(DateRange::Week));
+        assert_eq!(
+            options.include_domains,
+            Some(vec!["rust-lang.org".to_string()])
+        );
+        assert_eq!(
+            options.exclude_domains,
+            Some(vec!["example.com".to_string()])
+        );
+        assert_eq!(options.timeout, Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_web_search_input_invalid_values() {
+        let cases = [
+            (
+                serde_json::json!({"query": "q", "date_range": "decade"}),
+                "unknown variant `decade`",
+            ),
+            (
+                serde_json::json!({"query": "q", "safe_search": "high"}),
+                "unknown variant `high`",
+            ),
+            (
+                serde_json::json!({"query": "q", "num_results": 0}),
+                "num_results must be at least 1",
+            ),
+            (
+                serde_json::json!({"query": "q", "timeout": 0}),
+                "timeout must be at least 1",
+            ),
+            (
+                serde_json::json!({"query": "q", "provider": "altavista"}),
+                "unknown provider 'altavista'",
+            ),
+            (
+                serde_json::json!({"query": "q", "include_domains": ["https://a.com/"]}),
+                "invalid domain 'https://a.com/' in include_domains",
+            ),
+            (
+                serde_json::json!({"query": "q", "num_result": 5}),
+                "unknown field `num_result`",
+            ),
+            (serde_json::json!({"query": " "}), "query must not be empty"),
+        ];
+        for (arguments, expected) in cases {
+            let err = WebSearchInput::parse(arguments).unwrap_err();
+            assert!(err.contains(expected), "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_web_search_schema_enums() {
+        let schema = &McpServer::new().list_tools().tools[0].input_schema;
+        let properties = &schema["properties"];
+        assert_eq!(
+            properties["date_range"]["enum"],
+            serde_json::json!(["day", "week", "month", "year"])
+        );
+        assert_eq!(
+            properties["safe_search"]["enum"],
+            serde_json::json!(["off", "moderate", "strict"])
+        );
+        assert_eq!(
+            properties["format"]["enum"],
+            serde_json::json!(["json", "markdown", "text"])
+        );
+        assert!(properties["provider"]["enum"]
+            .as_array()
+            .unwrap()
+            .contains(&serde_json::json!("duckduckgo")));
+        assert!(properties["date_range"]["description"]
+            .as_str()
+            .unwrap()
+            .contains("past 24"));
+        assert_eq!(properties["num_results"]["minimum"], 1.0);
+        assert_eq!(schema["additionalProperties"], false);
+        assert_eq!(schema["required"], serde_json::json!(["query"]));
+    }
+
+    #[test]
+    fn test_fetch_url_schema() {
+        let schema = &McpServer::new().list_tools().tools[1].input_schema;
+        assert_eq!(
+            schema["properties"]["format"]["enum"],
+            serde_json::json!(["text", "html", "markdown"])
+        );
+        assert_eq!(schema["additionalProperties"], false);
+        assert_eq!(schema["required"], serde_json::json!(["url"]));
+    }
+
+    #[test]
+    fn test_web_search_input_with_options() {
+        let json = r#"{"query": "rust programming", "num_results": 5, "provider": "brave"}"#;
+        let input: WebSearchInput = serde_json::from_str(json).unwrap();
+        assert_eq!(input.query, "rust programming");
+        assert_eq!(input.num_results, Some(5));
+        assert_eq!(input.provider, Some("brave".to_string()));
+    }
+
+    #[test]
+    fn test_fetch_url_input_defaults() {
+        let json = r#"{"url": "https://example.com"}"#;
+        let input: FetchUrlInput = serde_json::from_str(json).unwrap();
+        assert_eq!(input.url, "https://example.com");
+        assert_eq!(input.format, None);
+        assert_eq!(input.content_format(), ContentFormat::Text);
+        assert_eq!(input.max_length, None);
+    }
+
+    #[test]
+    fn test_fetch_url_input_with_options() {
+        let json = r#"{"url": "https://example.com", "format": "markdown", "max_length": 10000}"#;
+        let input: FetchUrlInput = serde_json::from_str(json).unwrap();
+        assert_eq!(input.url, "https://example.com");
+        assert_eq!(input.format, Some(FetchFormat::Markdown));
+        assert_eq!(input.content_format(), ContentFormat::Markdown);
+        assert_eq!(input.max_length, Some(10000));
+    }
+
+    #[test]
+    fn test_mcp_server_new() {
+        let server = McpServer::new();
+        assert_eq!(server.name, "cli-web-search");
+        assert!(!server.version.is_empty());
+    }
+
+    #[test]
+    fn test_mcp_server_default() {
+        let server = McpServer::default();
+        assert_eq!(server.name, "cli-web-search");
+    }
+
+    #[test]
+    fn test_list_tools() {
+        let server = McpServer::new();
+        let result = server.list_tools();
+
+        assert_eq!(result.tools.len(), 2);
+
+        let tool_names: Vec<&str> = result.tools.iter().map(|t| t.name.as_str()).collect();
+        assert!(tool_names.contains(&"web_search"));
+        assert!(tool_names.contains(&"fetch_url"));
+    }
+
+    #[test]
+    fn test_list_tools_schemas() {
+        let server = McpServer::new();
+        let result = server.list_tools();
+
+        for tool in &result.tools {
+            // Each tool should have a valid input schema
+            assert!(tool.input_schema.is_object());
+
+            // Schema should have properties
+            let schema = tool.input_schema.as_object().unwrap();
+            assert!(schema.contains_key("properties") || schema.contains_key("$schema"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_handle_initialize() {
+        let server = McpServer::new();
+        let request = JsonRpcRequest {
+            jsonrpc: "2.0".to_string(),
+            id: Some(serde_json::json!(1)),
+            method: "initialize".to_string(),
+            params: Some(serde_json::json!({
+                "protocolVersion": "2024-11-05",
+                "capabilities": {},
+                "clientInfo": {
+                    "name": "test-client",
+                    "version": "1.0.0"
+                }
+            })),
+        };
+
+        let response = server.handle_request(None, request).await.unwrap();
+
+        assert!(response.error.is_none());
+        assert!(response.result.is_some());
+
+        let result = response.result.unwrap();
+        assert_eq!(result["protocolVersion"], "2024-11-05");
+        assert_eq!(result["serverInfo"]["name"], "cli-web-search");
+    }
+
+    #[tokio::test]
+    async fn test_handle_list_tools() {
+        let server = McpServer::new();
+        let request = JsonRpcRequest {
+            jsonrpc: "2.0".to_string(),
+            id: Some(serde_json::json!(2)),
+            method: "tools/list".to_string(),
+            params: None,
+        };
+
+        let response = server.handle_request(None, request).await.unwrap();
+
+        assert!(response.error.is_none());
+        assert!(response.result.is_some());
+
+        let result = response.result.unwrap();
+        let tools = result["tools"].as_array().unwrap();
+        assert_eq!(tools.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_handle_ping() {
+        let server = McpServer::new();
+        let request = JsonRpcRequest {
+            jsonrpc: "2.0".to_string(),
+            id: Some(serde_json::json!(3)),
+            method: "ping".to_string(),
+            params: None,
+        };
+
+        let response = server.handle_request(None, request).await.unwrap();
+
+        assert!(response.error.is_none());
+        assert!(response.result.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_handle_unknown_method() {
+        let server = McpServer::new();
+        let request = JsonRpcRequest {
+            jsonrpc: "2.0".to_string(),
+            id: Some(serde_json::json!(4)),
+            method: "unknown/method".to_string(),
+            params: None,
+        };
+
+        let response = server.handle_request(None, request).await.unwrap();
+
+        assert!(response.error.is_some());
+        let error = response.error.unwrap();
+        assert_eq!(error.code, -32601);
+        assert!(error.message.contains("Method not found"));
+    }
+
+    #[tokio::test]
+    async fn test_handle_call_tool_missing_params() {
+        let server = McpServer::new();
+        let request = JsonRpcRequest {
+            jsonrpc: "2.0".to_string(),
+            id: Some(serde_json::json!(5)),
+            method: "tools/call".to_string(),
+            params: None,
+        };
+
+        let response = server.handle_request(None, request).await.unwrap();
+
+        assert!(response.error.is_some());
+        let error = response.error.unwrap();
+        assert_eq!(error.code, -32602);
+    }
+
+    #[tokio::test]
+    async fn test_handle_call_tool_missing_name() {
+        let server = McpServer::new();
+        let request = JsonRpcRequest {
+            jsonrpc: "2.0".to_string(),
+            id: Some(serde_json::json!(6)),
+            method: "tools/call".to_string(),
+            params: Some(serde_json::json!({})),
+        };
+
+        let response = server.handle_request(None, request).await.unwrap();
+
+        assert!(response.error.is_some());
+        let error = response.error.unwrap();
+        assert_eq!(error.code, -32602);
+        assert!(error.message.contains("Missing tool name"));
+    }
+
+    #[tokio::test]
+    async fn test_handle_call_tool_unknown_tool() {
+        let server = McpServer::new();
+        let request = JsonRpcRequest {
+            jsonrpc: "2.0".to_string(),
+            id: Some(serde_json::json!(7)),
+            method: "tools/call".to_string(),
+            params: Some(serde_json::json!({
+                "name": "unknown_tool",
+                "arguments": {}
+            })),
+        };
+
+        let response = server.handle_request(None, request).await.unwrap();
+
+        assert!(response.error.is_some());
+        let error = response.error.unwrap();
+        assert_eq!(error.code, -32602);
+        assert!(error.message.contains("Unknown tool"));
+    }
+
+    #[tokio::test]
+    async fn test_handle_call_tool_invalid_arguments() {
+        let server = McpServer::new();
+        let request = JsonRpcRequest {
+            jsonrpc: "2.0".to_string(),
+            id: Some(serde_json::json!(8)),
+            method: "tools/call".to_string(),
+            params: Some(serde_json::json!({
+                "name": "web_search",
+                "arguments": {"query": "rust", "date_range": "fortnight"}
+            })),
+        };
+
+        let response = server.handle_request(None, request).await.unwrap();
+
+        let error = response.error.unwrap();
+        assert_eq!(error.code, -32602);
+        assert!(error
+            .message
+            .starts_with("Invalid arguments for web_search: unknown variant `fortnight`"));
+    }
+
+    #[tokio::test]
+    async fn test_handle_call_fetch_url_invalid_arguments() {
+        let server = McpServer::new();
+        for (arguments, expected) in [
+            (
+                serde_json::json!({"url": "https://example.com", "format": "pdf"}),
+                "unknown variant `pdf`",
+            ),
+            (
+                serde_json::json!({"url": "https://example.com", "max_lenght": 100}),
+                "unknown field `max_lenght`",
+            ),
+        ] {
+            let request = JsonRpcRequest {
+                jsonrpc: "2.0".to_string(),
+                id: Some(serde_json::json!(9)),
+                method: "tools/call".to_string(),
+                params: Some(serde_json::json!({"name": "fetch_url", "arguments": arguments})),
+            };
+
+            let error = server
+                .handle_request(None, request)
+                .await
+                .unwrap()
+                .error
+                .unwrap();
+            assert_eq!(error.code, -32602);
+            assert!(
+                error
+                    .message
+                    .starts_with(&format!("Invalid arguments for fetch_url: {}", expected)),
+                "{}",
+                error.message
+            );
+        }
+    }
+
+    #[test]
+    fn test_json_rpc_response_success() {
+        let response = JsonRpcResponse::success(
+            Some(serde_json::json!(1)),
+            serde_json::json!({"data": "test"}),
+        );
+
+        assert_eq!(response.jsonrpc, "2.0");
+        assert!(response.result.is_some());
+        assert!(response.error.is_none());
+    }
+
+    #[test]
+    fn test_json_rpc_response_error() {
+        let response = JsonRpcResponse::error(
+            Some(serde_json::json!(1)),
+            -32600,
+            "Invalid Request".to_string(),
+        );
+
+        assert_eq!(response.jsonrpc, "2.0");
+        assert!(response.result.is_none());
+        assert!(response.error.is_some());
+
+        let error = response.error.unwrap();
+        assert_eq!(error.code, -32600);
+        assert_eq!(error.message, "Invalid Request");
+    }
+
+    #[test]
+    fn test_json_rpc_request_parse() {
+        let json = r#"{"jsonrpc": "2.0", "id": 1, "method": "test", "params": {"key": "value"}}"#;
+        let request: JsonRpcRequest = serde_json::from_str(json).unwrap();
+
+        assert_eq!(request.jsonrpc, "2.0");
+        assert_eq!(request.id, Some(serde_json::json!(1)));
+        assert_eq!(request.method, "test");
+        assert!(request.params.is_some());
+    }
+
+    #[test]
+    fn test_json_rpc_request_parse_minimal() {
+        let json = r#"{"jsonrpc": "2.0", "method": "test"}"#;
+        let request: JsonRpcRequest = serde_json::from_str(json).unwrap();
+
+        assert_eq!(request.jsonrpc, "2.0");
+        assert!(request.id.is_none());
+        assert_eq!(request.method, "test");
+        assert!(request.params.is_none());
+    }
+
+    #[test]
+    fn test_server_capabilities_serialize() {
+        let capabilities = ServerCapabilities {
+            tools: Some(ToolsCapability::default()),
+        };
+
+        let json = serde_json::to_value(&capabilities).unwrap();
+        assert!(json.get("tools").is_some());
+    }
+
+    #[test]
+    fn test_initialize_result_serialize() {
+        let result = InitializeResult {
+            protocol_version: "2024-11-05".to_string(),
+            capabilities: ServerCapabilities {
+                tools: Some(ToolsCapability::default()),
+            },
+            server_info: ServerInfo {
+                name: "test".to_string(),
+                version: "1.0.0".to_string(),
+            },
+        };
+
+        let json = serde_json::to_value(&result).unwrap();
+        assert_eq!(json["protocolVersion"], "2024-11-05");
+        assert_eq!(json["serverInfo"]["name"], "test");
+    }
+
+    #[test]
+    fn test_call_tool_result_serialize() {
+        let result = CallToolResult {
+            content: vec![ToolContent {
+                content_type: "text".to_string(),
+                text: "Hello, world!".to_string(),
+            }],
+            structured_content: None,
+            is_error: None,
+        };
+
+        let json = serde_json::to_value(&result).unwrap();
+        assert_eq!(json["content"][0]["type"], "text");
+        assert_eq!(json["content"][0]["text"], "Hello, world!");
+        // is_error and structured_content should be skipped when None
+        assert!(!json.as_object().unwrap().contains_key("isError"));
+        assert!(!json.as_object().unwrap().contains_key("structuredContent"));
+    }
+
+    #[test]
+    fn test_call_tool_result_with_error() {
+        let result = CallToolResult {
+            content: vec![ToolContent {
+                content_type: "text".to_string(),
+                text: "Error: something went wrong".to_string(),
+            }],
+            structured_content: None,
+            is_error: Some(true),
+        };
+
+        let json = serde_json::to_value(&result).unwrap();
+        assert_eq!(json["isError"], true);
+    }
+
+    #[test]
+    fn test_tool_serialize() {
+        let tool = Tool {
+            name: "test_tool".to_string(),
+            description: "A test tool".to_string(),
+            input_schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "input": {"type": "string"}
+                }
+            }),
+            output_schema: None,
+        };
+
+        let json = serde_json::to_value(&tool).unwrap();
+        assert_eq!(json["name"], "test_tool");
+        assert_eq!(json["description"], "A test tool");
+        assert!(json["inputSchema"].is_object());
+        assert!(!json.as_object().unwrap().contains_key("outputSchema"));
+    }
+
+    #[test]
+    fn test_tools_declare_output_schemas() {
+        let result = McpServer::new().list_tools();
+        for tool in &result.tools {
+            let schema = tool.output_schema.as_ref().unwrap();
+            assert_eq!(schema["type"], "object");
+            assert_eq!(
+                schema["properties"]["format_version"]["const"],
+                serde_json::json!(crate::output::OUTPUT_FORMAT_VERSION)
+            );
+        }
+        let web_search = result.tools[0].output_schema.as_ref().unwrap();
+        assert!(web_search["properties"]["results"].is_object());
+        let fetch_url = result.tools[1].output_schema.as_ref().unwrap();
+        assert!(fetch_url["properties"]["content"].is_object());
+    }
+
+    #[test]
+    fn test_tool_output_structured_content() {
+        let response = SearchResponse::new(
+            "rust".to_string(),
+            "brave".to_string(),
+            vec![crate::providers::SearchResult {
+                title: "Rust".to_string(),
+                url: "https://www.rust-lang.org".to_string(),
+                snippet: String::new(),
+                position: 1,
+                published_date: None,
+                source: None,
+            }],
+            42,
+        );
+        let output = ToolOutput::new(search_text(&response), &response).unwrap();
+
+        assert!(output.text.starts_with("Search results for: \"rust\""));
+        assert!(output
+            .text
+            .contains("1. Rust\n   URL: https://www.rust-lang.org\n   No description available"));
+        let structured = &output.structured_content;
+        assert_eq!(
+            structured["format_version"],
+            crate::output::OUTPUT_FORMAT_VERSION
+        );
+        assert_eq!(structured["query"], "rust");
+        assert_eq!(structured["results"][0]["url"], "https://www.rust-lang.org");
+        assert_eq!(structured["search_time_ms"], 42);
+    }
+
+    #[tokio::test]
+    async fn test_handle_initialized_notification() {
+        let server = McpServer::new();
+        let request = JsonRpcRequest {
+            jsonrpc: "2.0".to_string(),
+            id: None,
+            method: "initialized".to_string(),
+            params: None,
+        };
+
+        let response = server.handle_request(None, request).await;
+
+        // initialized is a notification, so it gets no response
+        assert!(response.is_none());
+    }
+
+    fn server_with_config(dir: &tempfile::TempDir, yaml: &str) -> McpServer {
+        let path = dir.path().join("config.yaml");
+        std::fs::write(&path, yaml).unwrap();
+        McpServer::new().with_load_options(LoadOptions {
+            config_file: Some(path),
+            profile: None,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_web_search_reports_cache_hits() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let server = server_with_config(&dir, "providers:\n  brave:\n    api_key: test-key\n");
+        let arguments = serde_json::json!({"query": "Rust", "num_results": 3});
+
+        // Seed the shared cache as an earlier search would have
+        let state = server.state().unwrap();
+        let options = WebSearchInput::parse(arguments.clone())
+            .unwrap()
+            .search_options(&state.config.defaults);
+        let result = crate::providers::SearchResult {
+            title: "Rust".to_string(),
+            url: "https://www.rust-lang.org".to_string(),
+            snippet: String::new(),
+            position: 1,
+            published_date: None,
+            source: None,
+        };
+        state
+            .cache
+            .set("rust", None, &options, "brave", vec![result]);
+
+        let request = JsonRpcRequest {
+            jsonrpc: "2.0".to_string(),
+            id: Some(serde_json::json!(1)),
+            method: "tools/call".to_string(),
+            params: Some(serde_json::json!({"name": "web_search", "arguments": arguments})),
+        };
+        let response = server.handle_request(None, request).await.unwrap();
+
+        let result = response.result.unwrap();
+        assert!(result.get("isError").is_none());
+        assert!(result["content"][0]["text"]
+            .as_str()
+            .unwrap()
+            .contains("Provider: brave | Results: 1 | Time: cached"));
+        assert_eq!(result["structuredContent"]["cached"], true);
+        assert_eq!(result["structuredContent"]["provider"], "brave");
+    }
+
+    #[test]
+    fn test_reload_config() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let server = server_with_config(&dir, "defaults:\n  num_results: 3\n");
+        let first = server.state().unwrap();
+        assert!(Arc::ptr_eq(&first, &server.state().unwrap()));
+
+        let path = dir.path().join("config.yaml");
+        std::fs::write(&path, "defaults:\n  num_results: 5\n").unwrap();
+        server.reload().unwrap();
+        let second = server.state().unwrap();
+        assert_eq!(second.config.defaults.num_results, 5);
+        assert!(Arc::ptr_eq(&first.cache, &second.cache));
+
+        // A broken config leaves the last good one in place
+        std::fs::write(&path, "defaults: [").unwrap();
+        assert!(server.reload().is_err());
+        assert!(Arc::ptr_eq(&second, &server.state().unwrap()));
+    }
+
+    #[test]
+    fn test_web_search_input_null_provider() {
+        let json = r#"{"query": "test", "provider": null}"#;
+        let input: WebSearchInput = serde_json::from_str(json).unwrap();
+        assert!(input.provider.is_none());
+    }
+
+    #[test]
+    fn test_fetch_url_input_null_format() {
+        let json = r#"{"url": "https://example.com", "format": null}"#;
+        let input: FetchUrlInput = serde_json::from_str(json).unwrap();
+        assert!(input.format.is_none());
+    }
+
+    #[test]
+    fn test_fetch_url_input_max_length_zero() {
+        let json = r#"{"url": "https://example.com", "max_length": 0}"#;
+        let input: FetchUrlInput = serde_json::from_str(json).unwrap();
+        assert_eq!(input.max_length, Some(0));
+    }
+}
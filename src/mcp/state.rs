@@ -0,0 +1,158 @@
+//! Long-lived server state
+//!
+//! The server loads its configuration once and keeps the provider registry,
+//! the search cache and the fetch client for every tool call to share. The
+//! state is rebuilt when a config file changes or the process gets SIGHUP;
+//! results cached so far survive the reload unless the cache settings
+//! themselves changed.
+
+use super::McpServer;
+use crate::cache::SearchCache;
+use crate::config::{config_chain, load_config, Config, LoadOptions};
+use crate::error::Result;
+use crate::fetch::{FetchOptions, Fetcher, NetworkPolicy};
+use crate::providers::{build_registry, ProviderRegistry};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+/// How often config files are checked for changes
+const CONFIG_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Everything a tool call needs that is built from the configuration
+pub(super) struct ServerState {
+    pub config: Config,
+    pub registry: ProviderRegistry,
+    pub cache: Arc<SearchCache>,
+    /// Fetches URLs from clients, so internal networks are blocked unless the
+    /// config allows them
+    pub fetcher: Fetcher,
+}
+
+impl ServerState {
+    /// Load the configuration and build what it describes, keeping the
+    /// previous state's cache when the cache settings are unchanged
+    pub fn load(options: &LoadOptions, previous: Option<&ServerState>) -> Result<Self> {
+        let config = load_config(options)?;
+        let registry = build_registry(&config)?;
+        let cache = match previous {
+            Some(previous) if previous.config.cache == config.cache => Arc::clone(&previous.cache),
+            _ => Arc::new(SearchCache::new(config.cache.clone())),
+        };
+        let fetch_options =
+            FetchOptions::new().with_policy(NetworkPolicy::from_config(&config.fetch.policy, true));
+        let fetcher = Fetcher::with_network(fetch_options, &config.network)?;
+
+        Ok(Self {
+            config,
+            registry,
+            cache,
+            fetcher,
+        })
+    }
+}
+
+/// Reload the server's state whenever a config file changes or, on Unix, the
+/// process receives SIGHUP. Runs until the task is dropped.
+pub(super) async fn watch_config(server: Arc<McpServer>) {
+    #[cfg(unix)]
+    {
+        let server = Arc::clone(&server);
+        tokio::spawn(async move {
+            use tokio::signal::unix::{signal, SignalKind};
+            let Ok(mut hangup) = signal(SignalKind::hangup()) else {
+                return;
+            };
+            while hangup.recv().await.is_some() {
+                reload(&server, "SIGHUP");
+            }
+        });
+    }
+
+    let mut stamps = file_stamps(&server.load_options);
+    let mut poll = tokio::time::interval(CONFIG_POLL_INTERVAL);
+    loop {
+        poll.tick().await;
+        let current = file_stamps(&server.load_options);
+        if current != stamps {
+            stamps = current;
+            reload(&server, "config file changed");
+        }
+    }
+}
+
+fn reload(server: &McpServer, reason: &str) {
+    match server.reload() {
+        Ok(()) => tracing::info!("Reloaded configuration ({})", reason),
+        Err(e) => tracing::warn!(
+            "Keeping the previous configuration; reload ({}) failed: {}",
+            reason,
+            e
+        ),
+    }
+}
+
+/// Modification time and size of every file configuration is read from
+fn file_stamps(options: &LoadOptions) -> Vec<(PathBuf, Option<(SystemTime, u64)>)> {
+    let files: Vec<PathBuf> = match config_chain(options) {
+        Ok(chain) => chain.into_iter().filter_map(|(_, path)| path).collect(),
+        // An unreadable user config still changes when it is fixed
+        Err(_) => options.user_config_path().into_iter().collect(),
+    };
+    files
+        .into_iter()
+        .map(|path| {
+            let stamp = std::fs::metadata(&path)
+                .and_then(|meta| Ok((meta.modified()?, meta.len())))
+                .ok();
+            (path, stamp)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn options(dir: &TempDir) -> LoadOptions {
+        LoadOptions {
+            config_file: Some(dir.path().join("config.yaml")),
+            profile: None,
+        }
+    }
+
+    #[test]
+    fn test_reload_keeps_cache() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("config.yaml");
+        std::fs::write(&path, "defaults:\n  num_results: 3\n").unwrap();
+        let first = ServerState::load(&options(&dir), None).unwrap();
+        assert_eq!(first.config.defaults.num_results, 3);
+
+        std::fs::write(&path, "defaults:\n  num_results: 7\n").unwrap();
+        let second = ServerState::load(&options(&dir), Some(&first)).unwrap();
+        assert_eq!(second.config.defaults.num_results, 7);
+        assert!(Arc::ptr_eq(&first.cache, &second.cache));
+
+        std::fs::write(&path, "cache:\n  ttl_seconds: 60\n").unwrap();
+        let third = ServerState::load(&options(&dir), Some(&second)).unwrap();
+        assert!(!Arc::ptr_eq(&second.cache, &third.cache));
+    }
+
+    #[test]
+    fn test_file_stamps_change() {
+        let dir = TempDir::new().unwrap();
+        let before = file_stamps(&options(&dir));
+        assert!(before
+            .iter()
+            .any(|(path, stamp)| path.ends_with("config.yaml") && stamp.is_none()));
+
+        std::fs::write(
+            dir.path().join("config.yaml"),
+            "defaults:\n  num_results: 3\n",
+        )
+        .unwrap();
+        assert_ne!(before, file_stamps(&options(&dir)));
+    }
+}
@@ -0,0 +1,447 @@
+//! stdio transport: one JSON-RPC message per line on stdin and stdout
+//!
+//! Each request is handled in its own task, so a slow `fetch_url` doesn't
+//! hold up other calls (`McpServer` caps how many tool calls run at once).
+//! Replies go through a single writer task, which writes each one as soon as
+//! it is ready; clients match them to requests by id. Notifications are
+//! handled as soon as they are read, so `notifications/cancelled` can abort a
+//! call that is running or waiting for a slot.
+
+use super::McpServer;
+use crate::error::{Result, SearchError};
+use std::sync::Arc;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+use tokio::task::JoinSet;
+
+/// Serve requests from `reader` until it reaches EOF
+///
+/// At EOF no more requests are read, but calls already running finish and
+/// their replies are written before this returns.
+pub async fn serve<R, W>(server: Arc<McpServer>, reader: R, writer: W) -> Result<()>
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    let (replies, received) = mpsc::unbounded_channel();
+    let writer = tokio::spawn(write_replies(received, writer));
+
+    let mut lines = reader.lines();
+    let mut requests = JoinSet::new();
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if is_notification(&line) {
+            server.handle_message(None, &line).await;
+            continue;
+        }
+
+        let server = Arc::clone(&server);
+        let replies = replies.clone();
+        requests.spawn(async move {
+            // Notifications in a batch and cancelled calls get no reply
+            if let Some(reply) = server.handle_message(None, &line).await {
+                let _ = replies.send(reply);
+            }
+        });
+        // Forget requests that are done
+        while requests.try_join_next().is_some() {}
+    }
+
+    while requests.join_next().await.is_some() {}
+    drop(replies);
+    writer
+        .await
+        .map_err(|e| SearchError::api("mcp", format!("Reply writer failed: {}", e)))?
+}
+
+/// Write replies one per line as they arrive
+async fn write_replies<W>(mut received: UnboundedReceiver<String>, mut writer: W) -> Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    while let Some(reply) = received.recv().await {
+        writer.write_all(reply.as_bytes()).await?;
+        writer.write_all(b"\n").await?;
+        writer.flush().await?;
+    }
+    Ok(())
+}
+
+/// A single message without an id, which gets no reply
+fn is_notification(line: &str) -> bool {
+    serde_json::from_str::<serde_json::Value>(line).is_ok_and(|message| {
+        message.is_object() && !matches!(message.get("id"), Some(id) if !id.is_null())
+    })
+}
+
+/// Conformance tests: a client talking to the server over an in-memory pipe
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::LoadOptions;
+    use serde_json::{json, Value};
+    use std::time::Duration;
+    use tempfile::TempDir;
+    use tokio::io::{duplex, AsyncReadExt, BufReader, DuplexStream, Lines};
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio::task::JoinHandle;
+
+    struct Client {
+        writer: Option<DuplexStream>,
+        lines: Lines<BufReader<DuplexStream>>,
+        server: JoinHandle<Result<()>>,
+    }
+
+    impl Client {
+        fn start(server: McpServer) -> Self {
+            let (writer, server_reader) = duplex(64 * 1024);
+            let (server_writer, reader) = duplex(64 * 1024);
+            let server = tokio::spawn(async move {
+                serve(
+                    Arc::new(server),
+                    BufReader::new(server_reader),
+                    server_writer,
+                )
+                .await
+            });
+            Self {
+                writer: Some(writer),
+                lines: BufReader::new(reader).lines(),
+                server,
+            }
+        }
+
+        async fn send_raw(&mut self, line: &str) {
+            let writer = self.writer.as_mut().unwrap();
+            writer.write_all(line.as_bytes()).await.unwrap();
+            writer.write_all(b"\n").await.unwrap();
+        }
+
+        async fn send(&mut self, message: Value) {
+            self.send_raw(&message.to_string()).await;
+        }
+
+        async fn recv(&mut self) -> Value {
+            let line = tokio::time::timeout(Duration::from_secs(5), self.lines.next_line())
+                .await
+                .expect("server replied in time")
+                .unwrap()
+                .expect("server is still running");
+            serde_json::from_str(&line).unwrap()
+        }
+
+        async fn request(&mut self, id: u64, method: &str, params: Value) -> Value {
+            self.send(json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params}))
+                .await;
+            self.recv().await
+        }
+
+        /// Close stdin and wait for the server to exit
+        async fn close(mut self) {
+            drop(self.writer.take());
+            assert!(self.lines.next_line().await.unwrap().is_none());
+            self.server.await.unwrap().unwrap();
+        }
+    }
+
+    fn initialize_params(version: &str) -> Value {
+        json!({
+            "protocolVersion": version,
+            "capabilities": {},
+            "clientInfo": {"name": "conformance", "version": "1.0.0"}
+        })
+    }
+
+    #[tokio::test]
+    async fn test_protocol_version_negotiation() {
+        let mut client = Client::start(McpServer::new());
+
+        for version in super::super::SUPPORTED_PROTOCOL_VERSIONS {
+            let reply = client
+                .request(1, "initialize", initialize_params(version))
+                .await;
+            assert_eq!(reply["result"]["protocolVersion"], *version);
+        }
+
+        // Unknown versions get the newest one back
+        let reply = client
+            .request(2, "initialize", initialize_params("1999-01-01"))
+            .await;
+        assert_eq!(reply["result"]["protocolVersion"], "2025-06-18");
+        assert_eq!(reply["result"]["serverInfo"]["name"], "cli-web-search");
+
+        let reply = client.request(3, "initialize", json!({})).await;
+        assert_eq!(reply["id"], 3);
+        assert_eq!(reply["error"]["code"], -32602);
+
+        client.close().await;
+    }
+
+    #[tokio::test]
+    async fn test_notifications_get_no_response() {
+        let mut client = Client::start(McpServer::new());
+
+        client
+            .send(json!({"jsonrpc": "2.0", "method": "notifications/initialized"}))
+            .await;
+        client
+            .send(json!({"jsonrpc": "2.0", "method": "initialized"}))
+            .await;
+        client
+            .send(json!({"jsonrpc": "2.0", "method": "notifications/unknown", "params": {}}))
+            .await;
+        client
+            .send(
+                json!({"jsonrpc": "2.0", "method": "notifications/cancelled",
+                "params": {"requestId": 99, "reason": "not running"}}),
+            )
+            .await;
+
+        // The first reply is for the first request
+        let reply = client.request(1, "ping", json!({})).await;
+        assert_eq!(reply, json!({"jsonrpc": "2.0", "id": 1, "result": {}}));
+
+        client.close().await;
+    }
+
+    #[tokio::test]
+    async fn test_batches() {
+        let mut client = Client::start(McpServer::new());
+
+        client
+            .send(json!([
+                {"jsonrpc": "2.0", "id": 1, "method": "ping"},
+                {"jsonrpc": "2.0", "method": "notifications/initialized"},
+                {"jsonrpc": "2.0", "id": "two", "method": "tools/list"},
+                {"jsonrpc": "2.0", "id": 3, "method": "no/such/method"},
+                {"id": 4}
+            ]))
+            .await;
+        let reply = client.recv().await;
+        let replies = reply.as_array().unwrap();
+        assert_eq!(replies.len(), 4);
+        assert_eq!(replies[0]["id"], 1);
+        assert_eq!(replies[1]["id"], "two");
+        assert_eq!(replies[1]["result"]["tools"].as_array().unwrap().len(), 2);
+        assert_eq!(replies[2]["error"]["code"], -32601);
+        assert_eq!(replies[3]["id"], 4);
+        assert_eq!(replies[3]["error"]["code"], -32600);
+
+        // A batch of notifications gets no reply at all
+        client
+            .send(json!([{"jsonrpc": "2.0", "method": "notifications/initialized"}]))
+            .await;
+        let reply = client.request(5, "ping", json!({})).await;
+        assert_eq!(reply["id"], 5);
+
+        client.send(json!([])).await;
+        let reply = client.recv().await;
+        assert_eq!(reply["id"], Value::Null);
+        assert_eq!(reply["error"]["code"], -32600);
+
+        client.close().await;
+    }
+
+    #[tokio::test]
+    async fn test_malformed_messages() {
+        let mut client = Client::start(McpServer::new());
+
+        client.send_raw("{not json").await;
+        let reply = client.recv().await;
+        assert_eq!(reply["id"], Value::Null);
+        assert_eq!(reply["error"]["code"], -32700);
+
+        client.send(json!({"jsonrpc": "2.0", "id": 1})).await;
+        let reply = client.recv().await;
+        assert_eq!(reply["id"], 1);
+        assert_eq!(reply["error"]["code"], -32600);
+
+        client
+            .send(json!({"jsonrpc": "1.0", "id": 2, "method": "ping"}))
+            .await;
+        let reply = client.recv().await;
+        assert_eq!(reply["id"], 2);
+        assert_eq!(reply["error"]["code"], -32600);
+
+        client.close().await;
+    }
+
+    /// A web server that only answers when the test tells it to
+    struct SlowHost {
+        listener: TcpListener,
+        config_dir: TempDir,
+    }
+
+    impl SlowHost {
+        async fn start() -> Self {
+            let config_dir = TempDir::new().unwrap();
+            std::fs::write(
+                config_dir.path().join("config.yaml"),
+                "fetch:\n  policy:\n    block_private_networks: false\n",
+            )
+            .unwrap();
+            Self {
+                listener: TcpListener::bind("127.0.0.1:0").await.unwrap(),
+                config_dir,
+            }
+        }
+
+        /// A server whose config lets it fetch from this host
+        fn server(&self) -> McpServer {
+            McpServer::new().with_load_options(LoadOptions {
+                config_file: Some(self.config_dir.path().join("config.yaml")),
+                profile: None,
+            })
+        }
+
+        fn fetch(&self, id: u64, path: &str) -> Value {
+            let url = format!("http://{}{}", self.listener.local_addr().unwrap(), path);
+            json!({"jsonrpc": "2.0", "id": id, "method": "tools/call",
+                "params": {"name": "fetch_url", "arguments": {"url": url}}})
+        }
+
+        async fn accept(&self) -> TcpStream {
+            tokio::time::timeout(Duration::from_secs(5), self.listener.accept())
+                .await
+                .expect("fetch connected in time")
+                .unwrap()
+                .0
+        }
+
+        async fn no_connection_within(&self, wait: Duration) -> bool {
+            tokio::time::timeout(wait, self.listener.accept())
+                .await
+                .is_err()
+        }
+    }
+
+    /// Answer a fetch with a plain text page
+    async fn respond(mut socket: TcpStream, body: &str) {
+        let mut request = Vec::new();
+        let mut buf = [0u8; 1024];
+        while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+            let n = socket.read(&mut buf).await.unwrap();
+            request.extend_from_slice(&buf[..n]);
+        }
+        let response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: {}\r\n\
+             Connection: close\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        socket.write_all(response.as_bytes()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_call_is_aborted() {
+        let host = SlowHost::start().await;
+        let mut client = Client::start(host.server());
+
+        client.send(host.fetch(7, "/slow")).await;
+        let mut socket = host.accept().await;
+
+        client
+            .send(
+                json!({"jsonrpc": "2.0", "method": "notifications/cancelled",
+                "params": {"requestId": 7, "reason": "user gave up"}}),
+            )
+            .await;
+
+        // The fetch is dropped, closing its connection
+        let mut buf = [0u8; 1024];
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while socket.read(&mut buf).await.unwrap() > 0 {}
+        })
+        .await
+        .expect("cancelled fetch closed its connection");
+
+        // ...and the cancelled request gets no response
+        let reply = client.request(8, "ping", json!({})).await;
+        assert_eq!(reply["id"], 8);
+
+        client.close().await;
+    }
+
+    #[tokio::test]
+    async fn test_calls_run_concurrently_and_reply_when_done() {
+        let host = SlowHost::start().await;
+        let mut client = Client::start(host.server());
+
+        client.send(host.fetch(1, "/first")).await;
+        client.send(host.fetch(2, "/second")).await;
+
+        // Both fetches are running at once
+        let first = host.accept().await;
+        let second = host.accept().await;
+
+        // A fast call isn't held back by the slow ones before it
+        let reply = client.request(3, "ping", json!({})).await;
+        assert_eq!(reply["id"], 3);
+
+        // Replies are written as the calls finish
+        respond(second, "second page").await;
+        let reply = client.recv().await;
+        assert_eq!(reply["id"], 2);
+        assert_eq!(
+            reply["result"]["structuredContent"]["content"],
+            "second page"
+        );
+
+        respond(first, "first page").await;
+        let reply = client.recv().await;
+        assert_eq!(reply["id"], 1);
+        assert!(reply["result"]["content"][0]["text"]
+            .as_str()
+            .unwrap()
+            .ends_with("first page"));
+
+        client.close().await;
+    }
+
+    #[tokio::test]
+    async fn test_max_in_flight() {
+        let host = SlowHost::start().await;
+        let mut client = Client::start(host.server().with_max_in_flight(1));
+
+        client.send(host.fetch(1, "/first")).await;
+        client.send(host.fetch(2, "/second")).await;
+        client.send(host.fetch(3, "/third")).await;
+        let first = host.accept().await;
+        assert!(host.no_connection_within(Duration::from_millis(300)).await);
+
+        // A call waiting for a slot can be cancelled
+        client
+            .send(
+                json!({"jsonrpc": "2.0", "method": "notifications/cancelled",
+                "params": {"requestId": 2}}),
+            )
+            .await;
+
+        respond(first, "first page").await;
+        assert_eq!(client.recv().await["id"], 1);
+        respond(host.accept().await, "third page").await;
+        assert_eq!(client.recv().await["id"], 3);
+
+        client.close().await;
+    }
+
+    #[tokio::test]
+    async fn test_eof_waits_for_outstanding_calls() {
+        let host = SlowHost::start().await;
+        let mut client = Client::start(host.server());
+
+        client.send(host.fetch(1, "/page")).await;
+        let socket = host.accept().await;
+
+        // Closing stdin doesn't abandon the running call
+        drop(client.writer.take());
+        respond(socket, "page").await;
+        let reply = client.recv().await;
+        assert_eq!(reply["id"], 1);
+        assert_eq!(reply["result"]["structuredContent"]["content"], "page");
+
+        client.close().await;
+    }
+}
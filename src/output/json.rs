@@ -140,6 +140,17 @@ mod tests {
         assert!(output.contains("search_time_ms"));
     }
 
+    #[test]
+    fn test_json_cached() {
+        let response = SearchResponse::new("test".to_string(), "brave".to_string(), vec![], 0);
+        let formatter = JsonFormatter::new();
+        assert!(!formatter.format(&response).contains("cached"));
+
+        let parsed: serde_json::Value =
+            serde_json::from_str(&formatter.format(&response.with_cached(true))).unwrap();
+        assert_eq!(parsed["cached"], true);
+    }
+
     #[test]
     fn test_json_parseable() {
         let response = SearchResponse::new(
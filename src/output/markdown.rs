@@ -28,11 +28,14 @@ impl OutputFormatter for MarkdownFormatter {
         ));
 
         // Metadata line
+        let timing = if response.metadata.cached {
+            "cached".to_string()
+        } else {
+            format!("{}ms", response.metadata.search_time_ms)
+        };
         output.push_str(&format!(
-            "*Provider: {} | Results: {} | Time: {}ms*\n\n",
-            response.metadata.provider,
-            response.metadata.total_results,
-            response.metadata.search_time_ms
+            "*Provider: {} | Results: {} | Time: {}*\n\n",
+            response.metadata.provider, response.metadata.total_results, timing
         ));
 
         output.push_str("---\n\n");
@@ -9,13 +9,22 @@ pub use self::markdown::MarkdownFormatter;
 pub use self::text::TextFormatter;
 
 use crate::cli::OutputFormat;
-use crate::providers::SearchResult;
+use crate::error::SearchError;
+use crate::providers::{Attempt, SearchResult};
 use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
 use serde::Serialize;
 
+/// Version of the JSON output format, raised on any change that could break
+/// a consumer (a field removed, renamed or retyped); new fields don't count
+pub const OUTPUT_FORMAT_VERSION: u32 = 1;
+
 /// Metadata about the search
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, JsonSchema)]
 pub struct SearchMetadata {
+    /// JSON output format version (see `OUTPUT_FORMAT_VERSION`)
+    pub format_version: u32,
+
     /// The original query
     pub query: String,
 
@@ -30,10 +39,18 @@ pub struct SearchMetadata {
 
     /// Search time in milliseconds
     pub search_time_ms: u64,
+
+    /// Provider attempts made, including failed and skipped ones
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub attempts: Vec<Attempt>,
+
+    /// The results came from the cache rather than the provider
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub cached: bool,
 }
 
 /// Complete search response with metadata
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, JsonSchema)]
 pub struct SearchResponse {
     /// Search metadata
     #[serde(flatten)]
@@ -52,15 +69,97 @@ impl SearchResponse {
     ) -> Self {
         Self {
             metadata: SearchMetadata {
+                format_version: OUTPUT_FORMAT_VERSION,
                 query,
                 provider,
                 timestamp: Utc::now(),
                 total_results: results.len(),
                 search_time_ms,
+                attempts: Vec::new(),
+                cached: false,
             },
             results,
         }
     }
+
+    /// Attach the provider attempts that led to this response
+    pub fn with_attempts(mut self, attempts: Vec<Attempt>) -> Self {
+        self.metadata.attempts = attempts;
+        self
+    }
+
+    /// Mark the results as served from the cache
+    pub fn with_cached(mut self, cached: bool) -> Self {
+        self.metadata.cached = cached;
+        self
+    }
+}
+
+/// Machine-readable description of a failed command
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct ErrorResponse {
+    /// JSON output format version (see `OUTPUT_FORMAT_VERSION`)
+    pub format_version: u32,
+
+    /// The error itself
+    pub error: ErrorDetail,
+
+    /// Provider attempts made before giving up
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub attempts: Vec<Attempt>,
+}
+
+/// Error fields in an `ErrorResponse`
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct ErrorDetail {
+    /// Error category (see `SearchError::kind`)
+    pub kind: String,
+
+    /// Human-readable message
+    pub message: String,
+
+    /// Process exit code for this category
+    pub exit_code: i32,
+
+    /// Provider the error came from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+
+    /// Seconds to wait before retrying
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_after: Option<u64>,
+
+    /// HTTP status of the failed response
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+
+    /// Provider-specific error code
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+}
+
+impl ErrorResponse {
+    pub fn new(error: &SearchError, attempts: Vec<Attempt>) -> Self {
+        Self {
+            format_version: OUTPUT_FORMAT_VERSION,
+            error: ErrorDetail {
+                kind: error.kind().to_string(),
+                message: error.to_string(),
+                exit_code: error.exit_code(),
+                provider: error.provider().map(str::to_string),
+                retry_after: error.retry_after(),
+                status: error.status(),
+                code: error.code().map(str::to_string),
+            },
+            attempts,
+        }
+    }
+
+    /// Render as pretty-printed JSON
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self)
+            .unwrap_or_else(|e| format!("{{\"error\": \"Failed to serialize: {}\"}}", e))
+    }
 }
 
 /// Trait for output formatters
@@ -70,11 +169,13 @@ pub trait OutputFormatter {
 }
 
 /// Get the appropriate formatter for the given output format
-pub fn get_formatter(format: &OutputFormat) -> Box<dyn OutputFormatter> {
+///
+/// With `verbose`, the text formatter also lists the provider attempts.
+pub fn get_formatter(format: &OutputFormat, verbose: bool) -> Box<dyn OutputFormatter> {
     match format {
         OutputFormat::Json => Box::new(JsonFormatter::new()),
         OutputFormat::Markdown => Box::new(MarkdownFormatter::new()),
-        OutputFormat::Text => Box::new(TextFormatter::new()),
+        OutputFormat::Text => Box::new(TextFormatter::new().with_attempts(verbose)),
     }
 }
 
@@ -95,7 +196,7 @@ mod tests {
 
     #[test]
     fn test_get_formatter_json() {
-        let formatter = get_formatter(&OutputFormat::Json);
+        let formatter = get_formatter(&OutputFormat::Json, false);
         let response = SearchResponse::new("test".to_string(), "brave".to_string(), vec![], 100);
         let output = formatter.format(&response);
         // JSON output should contain curly braces
@@ -105,7 +206,7 @@ mod tests {
 
     #[test]
     fn test_get_formatter_markdown() {
-        let formatter = get_formatter(&OutputFormat::Markdown);
+        let formatter = get_formatter(&OutputFormat::Markdown, false);
         let response = SearchResponse::new("test".to_string(), "brave".to_string(), vec![], 100);
         let output = formatter.format(&response);
         // Markdown output should start with a header
@@ -114,7 +215,7 @@ mod tests {
 
     #[test]
     fn test_get_formatter_text() {
-        let formatter = get_formatter(&OutputFormat::Text);
+        let formatter = get_formatter(&OutputFormat::Text, false);
         let response = SearchResponse::new("test".to_string(), "brave".to_string(), vec![], 100);
         let output = formatter.format(&response);
         // Text output should start with "Search:"
@@ -156,11 +257,14 @@ mod tests {
     #[test]
     fn test_search_metadata_serialization() {
         let metadata = SearchMetadata {
+            format_version: OUTPUT_FORMAT_VERSION,
             query: "test".to_string(),
             provider: "brave".to_string(),
             timestamp: Utc::now(),
             total_results: 5,
             search_time_ms: 100,
+            attempts: Vec::new(),
+            cached: false,
         };
 
         let json = serde_json::to_string(&metadata).unwrap();
@@ -168,6 +272,33 @@ mod tests {
         assert!(json.contains("\"search_time_ms\":100"));
     }
 
+    #[test]
+    fn test_search_response_attempts_serialization() {
+        let attempt: Attempt = serde_json::from_value(serde_json::json!({
+            "provider": "brave",
+            "attempt": 1,
+            "duration_ms": 120,
+            "outcome": "error",
+            "error_kind": "rate_limited",
+            "error": "Rate limited by brave"
+        }))
+        .unwrap();
+        let response = SearchResponse::new("test".to_string(), "tavily".to_string(), vec![], 300)
+            .with_attempts(vec![attempt]);
+
+        let json: serde_json::Value = serde_json::to_value(&response).unwrap();
+        assert_eq!(json["attempts"][0]["provider"], "brave");
+        assert_eq!(json["attempts"][0]["outcome"], "error");
+        assert_eq!(json["attempts"][0]["error_kind"], "rate_limited");
+
+        // Cached responses carry no attempts
+        let cached = SearchResponse::new("test".to_string(), "tavily".to_string(), vec![], 0);
+        assert!(serde_json::to_value(&cached)
+            .unwrap()
+            .get("attempts")
+            .is_none());
+    }
+
     #[test]
     fn test_search_response_empty_results() {
         let response = SearchResponse::new("empty".to_string(), "tavily".to_string(), vec![], 50);
@@ -176,6 +307,20 @@ mod tests {
         assert!(response.results.is_empty());
     }
 
+    #[test]
+    fn test_error_response_serialization() {
+        let error = SearchError::rate_limited("brave", Some(30));
+        let json: serde_json::Value =
+            serde_json::from_str(&ErrorResponse::new(&error, vec![]).to_json()).unwrap();
+
+        assert_eq!(json["error"]["kind"], "rate_limited");
+        assert_eq!(json["error"]["provider"], "brave");
+        assert_eq!(json["error"]["retry_after"], 30);
+        assert_eq!(json["error"]["exit_code"], 6);
+        assert!(json["error"].get("code").is_none());
+        assert!(json.get("attempts").is_none());
+    }
+
     #[test]
     fn test_search_response_debug() {
         let response = SearchResponse::new("test".to_string(), "brave".to_string(), vec![], 100);
@@ -187,11 +332,14 @@ mod tests {
     #[test]
     fn test_search_metadata_debug() {
         let metadata = SearchMetadata {
+            format_version: OUTPUT_FORMAT_VERSION,
             query: "test".to_string(),
             provider: "brave".to_string(),
             timestamp: Utc::now(),
             total_results: 0,
             search_time_ms: 0,
+            attempts: Vec::new(),
+            cached: false,
         };
         let debug = format!("{:?}", metadata);
         assert!(debug.contains("SearchMetadata"));
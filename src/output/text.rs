@@ -1,13 +1,24 @@
 //! Plain text output formatter
 
 use super::{OutputFormatter, SearchResponse};
+use crate::providers::{Attempt, AttemptOutcome};
 
 /// Plain text formatter for simple terminal output
-pub struct TextFormatter;
+pub struct TextFormatter {
+    show_attempts: bool,
+}
 
 impl TextFormatter {
     pub fn new() -> Self {
-        Self
+        Self {
+            show_attempts: false,
+        }
+    }
+
+    /// List provider attempts below the header
+    pub fn with_attempts(mut self, show_attempts: bool) -> Self {
+        self.show_attempts = show_attempts;
+        self
     }
 }
 
@@ -22,15 +33,29 @@ impl OutputFormatter for TextFormatter {
         let mut output = String::new();
 
         // Header
+        let timing = if response.metadata.cached {
+            ", cached".to_string()
+        } else {
+            format!(" in {}ms", response.metadata.search_time_ms)
+        };
         output.push_str(&format!(
-            "Search: \"{}\" ({} results from {} in {}ms)\n",
+            "Search: \"{}\" ({} results from {}{})\n",
             response.metadata.query,
             response.metadata.total_results,
             response.metadata.provider,
-            response.metadata.search_time_ms
+            timing
         ));
         output.push_str(&"=".repeat(60));
         output.push('\n');
+
+        if self.show_attempts && !response.metadata.attempts.is_empty() {
+            output.push_str("Attempts:\n");
+            for attempt in &response.metadata.attempts {
+                output.push_str(&format_attempt(attempt));
+            }
+            output.push_str(&"-".repeat(60));
+            output.push('\n');
+        }
         output.push('\n');
 
         // Results
@@ -58,6 +83,29 @@ impl OutputFormatter for TextFormatter {
     }
 }
 
+/// One line per attempt, e.g. `  brave #1: error (rate_limited) after 120ms`
+fn format_attempt(attempt: &Attempt) -> String {
+    let outcome = match attempt.outcome {
+        AttemptOutcome::Success => "ok",
+        AttemptOutcome::Error => "error",
+        AttemptOutcome::Skipped => "skipped",
+    };
+    let kind = attempt
+        .error_kind
+        .as_ref()
+        .map(|k| format!(" ({})", k))
+        .unwrap_or_default();
+
+    if attempt.outcome == AttemptOutcome::Skipped {
+        format!("  {}: {}{}\n", attempt.provider, outcome, kind)
+    } else {
+        format!(
+            "  {} #{}: {}{} after {}ms\n",
+            attempt.provider, attempt.attempt, outcome, kind, attempt.duration_ms
+        )
+    }
+}
+
 /// Truncate a snippet to a maximum length, adding ellipsis if needed
 fn truncate_snippet(text: &str, max_len: usize) -> String {
     // Clean up whitespace
@@ -117,6 +165,47 @@ mod tests {
         assert!(output.contains("https://www.rust-lang.org"));
     }
 
+    #[test]
+    fn test_text_formatter_attempts() {
+        let attempts = vec![
+            Attempt {
+                provider: "brave".to_string(),
+                attempt: 1,
+                duration_ms: 120,
+                outcome: AttemptOutcome::Error,
+                error_kind: Some("rate_limited".to_string()),
+                error: Some("Rate limited by brave".to_string()),
+            },
+            Attempt {
+                provider: "google".to_string(),
+                attempt: 0,
+                duration_ms: 0,
+                outcome: AttemptOutcome::Skipped,
+                error_kind: Some("quota_exceeded".to_string()),
+                error: Some("Quota exceeded for google".to_string()),
+            },
+            Attempt {
+                provider: "tavily".to_string(),
+                attempt: 1,
+                duration_ms: 80,
+                outcome: AttemptOutcome::Success,
+                error_kind: None,
+                error: None,
+            },
+        ];
+        let response = SearchResponse::new("rust".to_string(), "tavily".to_string(), vec![], 200)
+            .with_attempts(attempts);
+
+        let output = TextFormatter::new().with_attempts(true).format(&response);
+        assert!(output.contains("  brave #1: error (rate_limited) after 120ms"));
+        assert!(output.contains("  google: skipped (quota_exceeded)"));
+        assert!(output.contains("  tavily #1: ok after 80ms"));
+
+        // Attempts are only listed when verbose
+        let output = TextFormatter::new().format(&response);
+        assert!(!output.contains("Attempts:"));
+    }
+
     #[test]
     fn test_truncate_snippet() {
         let long_text = "This is a very long snippet that should be truncated to fit within the specified maximum length for better readability in the terminal output.";
@@ -137,7 +226,7 @@ mod tests {
 
     #[test]
     fn test_text_formatter_default() {
-        let formatter = TextFormatter;
+        let formatter = TextFormatter::default();
         let response = SearchResponse::new("test".to_string(), "brave".to_string(), vec![], 100);
         let output = formatter.format(&response);
         assert!(output.contains("Search:"));
@@ -216,6 +305,16 @@ mod tests {
         assert!(output.contains("12345ms"));
     }
 
+    #[test]
+    fn test_text_cached() {
+        let response = SearchResponse::new("test".to_string(), "brave".to_string(), vec![], 0)
+            .with_cached(true);
+
+        let output = TextFormatter::new().format(&response);
+
+        assert!(output.contains("(0 results from brave, cached)"));
+    }
+
     #[test]
     fn test_text_url_indentation() {
         let response = SearchResponse::new(
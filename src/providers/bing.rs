@@ -3,6 +3,7 @@
 //! Bing Web Search API provides web search results from Microsoft Bing.
 //! See: https://docs.microsoft.com/en-us/bing/search-apis/bing-web-search/
 
+use super::response::error_from_response;
 use super::{SearchOptions, SearchProvider, SearchResult};
 use crate::cli::SafeSearch;
 use crate::error::{Result, SearchError};
@@ -26,6 +27,12 @@ impl BingProvider {
             client: Client::new(),
         }
     }
+
+    /// Use a shared HTTP client (proxy, TLS and timeout settings)
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
 }
 
 #[async_trait]
@@ -80,26 +87,8 @@ impl SearchProvider for BingProvider {
             .send()
             .await?;
 
-        let status = response.status();
-        if status == 429 {
-            let retry_after = response
-                .headers()
-                .get("Retry-After")
-                .and_then(|v| v.to_str().ok())
-                .and_then(|v| v.parse().ok());
-            return Err(SearchError::rate_limited("bing", retry_after));
-        }
-
-        if status == 401 || status == 403 {
-            return Err(SearchError::invalid_api_key("bing"));
-        }
-
-        if !status.is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(SearchError::api(
-                "bing",
-                format!("HTTP {}: {}", status, error_text),
-            ));
+        if !response.status().is_success() {
+            return Err(error_from_response("bing", response).await);
         }
 
         let bing_response: BingResponse = response.json().await?;
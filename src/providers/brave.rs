@@ -1,5 +1,6 @@
 //! Brave Search API provider
 
+use super::response::error_from_response;
 use super::{SearchOptions, SearchProvider, SearchResult};
 use crate::cli::SafeSearch;
 use crate::error::{Result, SearchError};
@@ -23,6 +24,12 @@ impl BraveProvider {
             client: Client::new(),
         }
     }
+
+    /// Use a shared HTTP client (proxy, TLS and timeout settings)
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
 }
 
 #[async_trait]
@@ -74,26 +81,8 @@ impl SearchProvider for BraveProvider {
 
         let response = request.send().await?;
 
-        let status = response.status();
-        if status == 429 {
-            let retry_after = response
-                .headers()
-                .get("Retry-After")
-                .and_then(|v| v.to_str().ok())
-                .and_then(|v| v.parse().ok());
-            return Err(SearchError::rate_limited("brave", retry_after));
-        }
-
-        if status == 401 || status == 403 {
-            return Err(SearchError::invalid_api_key("brave"));
-        }
-
-        if !status.is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(SearchError::api(
-                "brave",
-                format!("HTTP {}: {}", status, error_text),
-            ));
+        if !response.status().is_success() {
+            return Err(error_from_response("brave", response).await);
         }
 
         let brave_response: BraveSearchResponse = response.json().await?;
@@ -4,6 +4,7 @@
 //! not traditional web search results. It's free and doesn't require an API key.
 //! For full web search results, DuckDuckGo doesn't provide a public API.
 
+use super::response::error_from_response;
 use super::{SearchOptions, SearchProvider, SearchResult};
 use crate::error::{Result, SearchError};
 use async_trait::async_trait;
@@ -26,6 +27,12 @@ impl DuckDuckGoProvider {
             enabled,
         }
     }
+
+    /// Use a shared HTTP client (proxy, TLS and timeout settings)
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
 }
 
 #[async_trait]
@@ -41,10 +48,10 @@ impl SearchProvider for DuckDuckGoProvider {
 
     async fn search(&self, query: &str, options: &SearchOptions) -> Result<Vec<SearchResult>> {
         if !self.is_configured() {
-            return Err(SearchError::Api {
-                provider: "duckduckgo".to_string(),
-                message: "DuckDuckGo provider is not enabled".to_string(),
-            });
+            return Err(SearchError::api(
+                "duckduckgo",
+                "DuckDuckGo provider is not enabled",
+            ));
         }
 
         let response = self
@@ -60,17 +67,8 @@ impl SearchProvider for DuckDuckGoProvider {
             .send()
             .await?;
 
-        let status = response.status();
-        if status == 429 {
-            return Err(SearchError::rate_limited("duckduckgo", None));
-        }
-
-        if !status.is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(SearchError::api(
-                "duckduckgo",
-                format!("HTTP {}: {}", status, error_text),
-            ));
+        if !response.status().is_success() {
+            return Err(error_from_response("duckduckgo", response).await);
         }
 
         let ddg_response: DdgResponse = response.json().await?;
@@ -383,7 +381,10 @@ mod tests {
 
         let error = result.unwrap_err();
         assert!(matches!(error, SearchError::Api { .. }));
-        if let SearchError::Api { provider, message } = error {
+        if let SearchError::Api {
+            provider, message, ..
+        } = error
+        {
             assert_eq!(provider, "duckduckgo");
             assert!(message.contains("not enabled"));
         }
@@ -1,5 +1,6 @@
 //! Firecrawl Search API provider
 
+use super::response::{error_from_body, error_from_response};
 use super::{SearchOptions, SearchProvider, SearchResult};
 use crate::error::{Result, SearchError};
 use async_trait::async_trait;
@@ -22,6 +23,12 @@ impl FirecrawlProvider {
             client: Client::new(),
         }
     }
+
+    /// Use a shared HTTP client (proxy, TLS and timeout settings)
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
 }
 
 #[async_trait]
@@ -87,31 +94,14 @@ impl SearchProvider for FirecrawlProvider {
             .send()
             .await?;
 
-        let status = response.status();
-        if status == 429 {
-            return Err(SearchError::rate_limited("firecrawl", None));
-        }
-
-        if status == 401 || status == 403 {
-            return Err(SearchError::invalid_api_key("firecrawl"));
-        }
-
-        if status == 408 {
-            return Err(SearchError::Timeout(options.timeout.as_secs()));
-        }
-
-        if !status.is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(SearchError::api(
-                "firecrawl",
-                format!("HTTP {}: {}", status, error_text),
-            ));
+        if !response.status().is_success() {
+            return Err(error_from_response("firecrawl", response).await);
         }
 
         let firecrawl_response: FirecrawlSearchResponse = response.json().await?;
 
         if !firecrawl_response.success {
-            return Err(SearchError::api(
+            return Err(error_from_body(
                 "firecrawl",
                 firecrawl_response
                     .warning
@@ -1,5 +1,6 @@
 //! Google Custom Search Engine (CSE) provider
 
+use super::response::error_from_response;
 use super::{SearchOptions, SearchProvider, SearchResult};
 use crate::cli::SafeSearch;
 use crate::error::{Result, SearchError};
@@ -25,6 +26,12 @@ impl GoogleProvider {
             client: Client::new(),
         }
     }
+
+    /// Use a shared HTTP client (proxy, TLS and timeout settings)
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
 }
 
 #[async_trait]
@@ -94,21 +101,8 @@ impl SearchProvider for GoogleProvider {
 
         let response = request.send().await?;
 
-        let status = response.status();
-        if status == 429 {
-            return Err(SearchError::rate_limited("google", None));
-        }
-
-        if status == 401 || status == 403 {
-            return Err(SearchError::invalid_api_key("google"));
-        }
-
-        if !status.is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(SearchError::api(
-                "google",
-                format!("HTTP {}: {}", status, error_text),
-            ));
+        if !response.status().is_success() {
+            return Err(error_from_response("google", response).await);
         }
 
         let google_response: GoogleSearchResponse = response.json().await?;
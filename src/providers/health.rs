@@ -0,0 +1,278 @@
+//! Provider health tracking and circuit breaker
+//!
+//! A provider's circuit opens after a run of consecutive failures, and it is
+//! left out of the fallback chain until the cooldown passes. The next request
+//! is then a single probe: success closes the circuit, failure re-opens it.
+//! Health records are persisted so every invocation benefits.
+
+use super::state::StateFile;
+use crate::config::{data_dir, CircuitBreakerConfig};
+use crate::error::SearchError;
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// File name for persisted health records
+const HEALTH_FILE: &str = "health.json";
+
+/// Health record for a single provider
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProviderHealth {
+    /// Failures since the last success
+    #[serde(default)]
+    pub consecutive_failures: u32,
+    /// Kind of the most recent error (see `SearchError::kind`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error_kind: Option<String>,
+    /// Message of the most recent error
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    /// When the most recent failure happened
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_failure_at: Option<DateTime<Utc>>,
+    /// Provider is skipped until this time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cooldown_until: Option<DateTime<Utc>>,
+}
+
+impl ProviderHealth {
+    /// Circuit state at the given time
+    pub fn state(&self, now: DateTime<Utc>) -> CircuitState {
+        match self.cooldown_until {
+            Some(until) if until > now => CircuitState::Open,
+            Some(_) => CircuitState::HalfOpen,
+            None => CircuitState::Closed,
+        }
+    }
+}
+
+/// Circuit breaker state for a provider
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Requests flow normally
+    Closed,
+    /// Provider is skipped until the cooldown ends
+    Open,
+    /// Cooldown ended; the next request is a probe
+    HalfOpen,
+}
+
+/// Tracks provider failures and decides which providers to skip
+#[derive(Debug, Default)]
+pub struct HealthTracker {
+    config: CircuitBreakerConfig,
+    health: Arc<StateFile<ProviderHealth>>,
+}
+
+impl HealthTracker {
+    /// Create an in-memory tracker
+    pub fn new(config: CircuitBreakerConfig) -> Self {
+        Self {
+            config,
+            health: Arc::default(),
+        }
+    }
+
+    /// Create a tracker persisting health records in the data directory
+    pub fn open(config: CircuitBreakerConfig) -> crate::error::Result<Self> {
+        Ok(Self::new(config).with_path(data_dir()?.join(HEALTH_FILE)))
+    }
+
+    /// Persist health records to a specific file
+    pub fn with_path(mut self, path: PathBuf) -> Self {
+        self.health = Arc::new(StateFile::at(path));
+        self
+    }
+
+    /// Circuit state of a provider
+    pub async fn state(&self, provider: &str) -> CircuitState {
+        if !self.config.enabled {
+            return CircuitState::Closed;
+        }
+        self.get(provider).await.state(Utc::now())
+    }
+
+    /// Whether a provider should be tried (closed or ready for a probe)
+    pub async fn allows(&self, provider: &str) -> bool {
+        self.state(provider).await != CircuitState::Open
+    }
+
+    /// Seconds until an open circuit is ready for a probe
+    pub async fn cooldown_remaining(&self, provider: &str) -> Option<u64> {
+        let until = self.get(provider).await.cooldown_until?;
+        let remaining = (until - Utc::now()).num_seconds();
+        (remaining > 0).then_some(remaining as u64)
+    }
+
+    /// Health record of a provider
+    pub async fn get(&self, provider: &str) -> ProviderHealth {
+        let provider = provider.to_string();
+        self.health
+            .blocking(move |state| state.lock().get(&provider).cloned().unwrap_or_default())
+            .await
+    }
+
+    /// Record a successful request, closing the circuit
+    pub async fn record_success(&self, provider: &str) {
+        let provider = provider.to_string();
+        self.health
+            .blocking(move |state| {
+                let mut health = state.lock();
+                if let Some(entry) = health.get_mut(&provider) {
+                    if entry.consecutive_failures == 0 && entry.cooldown_until.is_none() {
+                        return;
+                    }
+                    entry.consecutive_failures = 0;
+                    entry.cooldown_until = None;
+                    state.save(health);
+                }
+            })
+            .await
+    }
+
+    /// Record a failed request, opening the circuit once the threshold is hit
+    pub async fn record_failure(&self, provider: &str, error: &SearchError) {
+        let config = self.config.clone();
+        let provider = provider.to_string();
+        let kind = error.kind().to_string();
+        let message = error.to_string();
+        self.health
+            .blocking(move |state| {
+                let now = Utc::now();
+                let mut health = state.lock();
+                let entry = health.entry(provider.clone()).or_default();
+
+                let probing = entry.state(now) == CircuitState::HalfOpen;
+                entry.consecutive_failures += 1;
+                entry.last_error_kind = Some(kind);
+                entry.last_error = Some(message);
+                entry.last_failure_at = Some(now);
+
+                if config.enabled
+                    && (probing || entry.consecutive_failures >= config.failure_threshold)
+                {
+                    let cooldown = ChronoDuration::seconds(config.cooldown_seconds as i64);
+                    entry.cooldown_until = Some(now + cooldown);
+                    tracing::warn!(
+                        "Circuit opened for {} after {} consecutive failures",
+                        provider,
+                        entry.consecutive_failures
+                    );
+                }
+
+                state.save(health);
+            })
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tracker(failure_threshold: u32, cooldown_seconds: u64) -> HealthTracker {
+        HealthTracker::new(CircuitBreakerConfig {
+            enabled: true,
+            failure_threshold,
+            cooldown_seconds,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_circuit_opens_after_threshold() {
+        let tracker = tracker(2, 60);
+        let error = SearchError::api("brave", "HTTP 503");
+
+        tracker.record_failure("brave", &error).await;
+        assert_eq!(tracker.state("brave").await, CircuitState::Closed);
+        assert!(tracker.allows("brave").await);
+
+        tracker.record_failure("brave", &error).await;
+        assert_eq!(tracker.state("brave").await, CircuitState::Open);
+        assert!(!tracker.allows("brave").await);
+        assert!(tracker
+            .cooldown_remaining("brave")
+            .await
+            .is_some_and(|s| s <= 60));
+
+        let health = tracker.get("brave").await;
+        assert_eq!(health.consecutive_failures, 2);
+        assert_eq!(health.last_error_kind.as_deref(), Some("api"));
+    }
+
+    #[tokio::test]
+    async fn test_success_closes_circuit() {
+        let tracker = tracker(1, 0);
+        tracker
+            .record_failure("brave", &SearchError::Timeout(30))
+            .await;
+
+        // Zero cooldown: the circuit is immediately ready for a probe
+        assert_eq!(tracker.state("brave").await, CircuitState::HalfOpen);
+        assert!(tracker.allows("brave").await);
+
+        tracker.record_success("brave").await;
+        assert_eq!(tracker.state("brave").await, CircuitState::Closed);
+        assert_eq!(tracker.get("brave").await.consecutive_failures, 0);
+    }
+
+    #[tokio::test]
+    async fn test_failed_probe_reopens_circuit() {
+        let tracker = HealthTracker::new(CircuitBreakerConfig {
+            enabled: true,
+            failure_threshold: 5,
+            cooldown_seconds: 60,
+        });
+        {
+            let mut health = tracker.health.lock();
+            health.insert(
+                "brave".to_string(),
+                ProviderHealth {
+                    consecutive_failures: 5,
+                    cooldown_until: Some(Utc::now() - ChronoDuration::seconds(1)),
+                    ..Default::default()
+                },
+            );
+        }
+        assert_eq!(tracker.state("brave").await, CircuitState::HalfOpen);
+
+        tracker
+            .record_failure("brave", &SearchError::Timeout(30))
+            .await;
+        assert_eq!(tracker.state("brave").await, CircuitState::Open);
+    }
+
+    #[tokio::test]
+    async fn test_disabled_breaker_never_opens() {
+        let tracker = HealthTracker::new(CircuitBreakerConfig {
+            enabled: false,
+            ..Default::default()
+        });
+        for _ in 0..10 {
+            tracker
+                .record_failure("brave", &SearchError::Timeout(30))
+                .await;
+        }
+        assert!(tracker.allows("brave").await);
+        assert_eq!(tracker.get("brave").await.consecutive_failures, 10);
+    }
+
+    #[tokio::test]
+    async fn test_health_persisted() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(HEALTH_FILE);
+
+        let first = tracker(1, 60).with_path(path.clone());
+        first
+            .record_failure("google", &SearchError::invalid_api_key("google"))
+            .await;
+
+        let second = tracker(1, 60).with_path(path);
+        assert_eq!(second.state("google").await, CircuitState::Open);
+        assert_eq!(
+            second.get("google").await.last_error_kind.as_deref(),
+            Some("invalid_api_key")
+        );
+    }
+}
@@ -5,8 +5,13 @@ mod brave;
 mod duckduckgo;
 mod firecrawl;
 mod google;
+mod health;
+mod quota;
+mod response;
+mod retry;
 mod serpapi;
 mod serper;
+mod state;
 mod tavily;
 
 pub use bing::BingProvider;
@@ -14,25 +19,24 @@ pub use brave::BraveProvider;
 pub use duckduckgo::DuckDuckGoProvider;
 pub use firecrawl::FirecrawlProvider;
 pub use google::GoogleProvider;
+pub use health::{CircuitState, HealthTracker};
+pub use quota::RateLimiter;
+pub use retry::RetryPolicy;
 pub use serpapi::SerpApiProvider;
 pub use serper::SerperProvider;
 pub use tavily::TavilyProvider;
 
 use crate::cli::{DateRange, SafeSearch};
+use crate::config::RetryConfig;
 use crate::error::{Result, SearchError};
 use async_trait::async_trait;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::time::Duration;
-use tokio::time::sleep;
-
-/// Maximum number of retries per provider
-const MAX_RETRIES: u32 = 3;
-
-/// Base delay for exponential backoff (in milliseconds)
-const BASE_DELAY_MS: u64 = 500;
+use std::time::{Duration, Instant};
+use tokio::time::{sleep, timeout};
 
 /// A single search result
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SearchResult {
     /// Title of the result
     pub title: String,
@@ -55,6 +59,67 @@ pub struct SearchResult {
     pub source: Option<String>,
 }
 
+/// Outcome of a single provider attempt
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AttemptOutcome {
+    /// The provider returned results
+    Success,
+    /// The request failed
+    Error,
+    /// The provider was passed over without a request (e.g. quota used up)
+    Skipped,
+}
+
+/// Record of one request made (or skipped) during a search
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Attempt {
+    /// Provider name
+    pub provider: String,
+
+    /// Attempt number for this provider (1-indexed)
+    pub attempt: u32,
+
+    /// Time spent on the request in milliseconds
+    pub duration_ms: u64,
+
+    /// What happened
+    pub outcome: AttemptOutcome,
+
+    /// Error kind (see `SearchError::kind`) when the attempt failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_kind: Option<String>,
+
+    /// Error message when the attempt failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl Attempt {
+    fn new(provider: &str, attempt: u32, started: Instant, error: Option<&SearchError>) -> Self {
+        Self {
+            provider: provider.to_string(),
+            attempt,
+            duration_ms: started.elapsed().as_millis() as u64,
+            outcome: if error.is_some() {
+                AttemptOutcome::Error
+            } else {
+                AttemptOutcome::Success
+            },
+            error_kind: error.map(|e| e.kind().to_string()),
+            error: error.map(|e| e.to_string()),
+        }
+    }
+
+    fn skipped(provider: &str, error: &SearchError) -> Self {
+        Self {
+            outcome: AttemptOutcome::Skipped,
+            duration_ms: 0,
+            ..Self::new(provider, 0, Instant::now(), Some(error))
+        }
+    }
+}
+
 /// Search options passed to providers
 #[derive(Debug, Clone, Default)]
 pub struct SearchOptions {
@@ -104,6 +169,16 @@ impl SearchOptions {
         self
     }
 
+    pub fn with_include_domains(mut self, domains: Option<Vec<String>>) -> Self {
+        self.include_domains = domains;
+        self
+    }
+
+    pub fn with_exclude_domains(mut self, domains: Option<Vec<String>>) -> Self {
+        self.exclude_domains = domains;
+        self
+    }
+
     pub fn with_timeout(mut self, timeout: Duration) -> Self {
         self.timeout = timeout;
         self
@@ -130,6 +205,10 @@ pub trait SearchProvider: Send + Sync {
 pub struct ProviderRegistry {
     providers: Vec<Box<dyn SearchProvider>>,
     fallback_order: Vec<String>,
+    default_provider: Option<String>,
+    limiter: RateLimiter,
+    health: HealthTracker,
+    retry: RetryConfig,
 }
 
 impl ProviderRegistry {
@@ -138,6 +217,10 @@ impl ProviderRegistry {
         Self {
             providers: Vec::new(),
             fallback_order: Vec::new(),
+            default_provider: None,
+            limiter: RateLimiter::default(),
+            health: HealthTracker::default(),
+            retry: RetryConfig::default(),
         }
     }
 
@@ -151,6 +234,36 @@ impl ProviderRegistry {
         self.fallback_order = order;
     }
 
+    /// Set the provider tried first when a search doesn't ask for one
+    pub fn set_default_provider(&mut self, provider: Option<String>) {
+        self.default_provider = provider;
+    }
+
+    /// Set the rate limiter used for every provider request
+    pub fn set_rate_limiter(&mut self, limiter: RateLimiter) {
+        self.limiter = limiter;
+    }
+
+    /// Get the rate limiter (for usage reporting)
+    pub fn rate_limiter(&self) -> &RateLimiter {
+        &self.limiter
+    }
+
+    /// Set the retry policy and overall deadline
+    pub fn set_retry_config(&mut self, retry: RetryConfig) {
+        self.retry = retry;
+    }
+
+    /// Set the health tracker deciding which providers to skip
+    pub fn set_health_tracker(&mut self, health: HealthTracker) {
+        self.health = health;
+    }
+
+    /// Get the health tracker (for status reporting)
+    pub fn health(&self) -> &HealthTracker {
+        &self.health
+    }
+
     /// Get a provider by name
     pub fn get(&self, name: &str) -> Option<&dyn SearchProvider> {
         self.providers
@@ -168,9 +281,9 @@ impl ProviderRegistry {
             .collect()
     }
 
-    /// Get providers in fallback order
+    /// Get configured providers in fallback order
     pub fn providers_in_order(&self) -> Vec<&dyn SearchProvider> {
-        let mut result = Vec::new();
+        let mut result: Vec<&dyn SearchProvider> = Vec::new();
 
         // First add providers in fallback order
         for name in &self.fallback_order {
@@ -191,106 +304,186 @@ impl ProviderRegistry {
         result
     }
 
-    /// Execute search with fallback and retry logic
+    /// Execute search with fallback and retry logic, recording every attempt
+    ///
+    /// `attempts` is filled in whether or not the search succeeds, so callers
+    /// can report what was tried alongside an error.
     pub async fn search_with_fallback(
         &self,
         query: &str,
         options: &SearchOptions,
         preferred_provider: Option<&str>,
+        attempts: &mut Vec<Attempt>,
     ) -> Result<(Vec<SearchResult>, &str)> {
         let mut providers = self.providers_in_order();
+        if providers.is_empty() {
+            return Err(SearchError::NoProvidersConfigured);
+        }
 
-        // If a preferred provider is specified, try it first
-        if let Some(preferred) = preferred_provider {
+        // Try the requested provider first, else the default provider
+        if let Some(preferred) = preferred_provider.or(self.default_provider.as_deref()) {
             if let Some(pos) = providers.iter().position(|p| p.name() == preferred) {
                 let provider = providers.remove(pos);
                 providers.insert(0, provider);
             }
         }
 
-        if providers.is_empty() {
-            return Err(SearchError::NoProvidersConfigured);
-        }
-
-        let mut last_error = String::new();
+        let deadline = self
+            .retry
+            .deadline_seconds
+            .map(|secs| (Instant::now() + Duration::from_secs(secs), secs));
+        let mut errors = Vec::new();
 
         for provider in providers {
+            // Skip providers cooling down after repeated failures
+            if !self.health.allows(provider.name()).await {
+                let e = SearchError::CircuitOpen {
+                    provider: provider.name().to_string(),
+                    retry_after: self.health.cooldown_remaining(provider.name()).await,
+                };
+                tracing::info!("Skipping {}: {}", provider.name(), e);
+                attempts.push(Attempt::skipped(provider.name(), &e));
+                errors.push(e);
+                continue;
+            }
+
+            if let Some((at, secs)) = deadline {
+                if Instant::now() >= at {
+                    tracing::warn!("Search deadline of {}s reached", secs);
+                    return Err(SearchError::Timeout(secs));
+                }
+            }
+
+            // Skip providers whose quota is used up instead of burning retries
+            if let Err(e) = self.limiter.check(provider.name()).await {
+                tracing::info!("Skipping {}: {}", provider.name(), e);
+                attempts.push(Attempt::skipped(provider.name(), &e));
+                errors.push(e);
+                continue;
+            }
+
             // Try each provider with retries
-            match self.search_with_retry(provider, query, options).await {
+            match self
+                .search_with_retry(provider, query, options, deadline, attempts)
+                .await
+            {
                 Ok(results) => return Ok((results, provider.name())),
                 Err(e) => {
-                    last_error = e.to_string();
                     tracing::warn!("Provider {} failed: {}", provider.name(), e);
-                    // Continue to next provider on rate limit or API errors
-                    match &e {
-                        SearchError::RateLimited { .. }
-                        | SearchError::Api { .. }
-                        | SearchError::Network(_) => continue,
-                        // For other errors, stop trying
-                        _ => return Err(e),
+                    // A rejected query would fail the same way everywhere
+                    if !e.should_fall_back() {
+                        return Err(e);
                     }
+                    errors.push(e);
                 }
             }
         }
 
-        Err(SearchError::AllProvidersFailed(last_error))
+        Err(SearchError::AllProvidersFailed(errors))
     }
 
-    /// Execute search with exponential backoff retry
+    /// Execute search with backoff retry according to the provider's retry policy
     async fn search_with_retry(
         &self,
         provider: &dyn SearchProvider,
         query: &str,
         options: &SearchOptions,
+        deadline: Option<(Instant, u64)>,
+        attempts: &mut Vec<Attempt>,
     ) -> Result<Vec<SearchResult>> {
-        let mut last_error = None;
+        let policy = RetryPolicy::for_provider(&self.retry, provider.name());
 
-        for attempt in 0..MAX_RETRIES {
-            match provider.search(query, options).await {
-                Ok(results) => return Ok(results),
-                Err(e) => {
-                    // Only retry on transient errors
-                    let should_retry = matches!(
-                        &e,
-                        SearchError::Network(_) | SearchError::RateLimited { .. }
-                    );
+        // After a cooldown, a single probe decides whether the circuit closes
+        let max_attempts = if self.health.state(provider.name()).await == CircuitState::HalfOpen {
+            1
+        } else {
+            policy.max_attempts
+        };
 
-                    if !should_retry || attempt == MAX_RETRIES - 1 {
-                        return Err(e);
+        let mut attempt = 0;
+        loop {
+            if let Err(e) = self.limiter.acquire(provider.name()).await {
+                attempts.push(Attempt::skipped(provider.name(), &e));
+                return Err(e);
+            }
+            let started = Instant::now();
+
+            // Never let a single request run past the overall deadline
+            let result = match deadline {
+                Some((at, secs)) => {
+                    let remaining = at.saturating_duration_since(Instant::now());
+                    match timeout(remaining, provider.search(query, options)).await {
+                        Ok(result) => result,
+                        Err(_) => {
+                            // Our deadline cut the request short, which says
+                            // nothing about the provider's health
+                            let e = SearchError::Timeout(secs);
+                            attempts.push(Attempt::new(
+                                provider.name(),
+                                attempt + 1,
+                                started,
+                                Some(&e),
+                            ));
+                            return Err(e);
+                        }
                     }
+                }
+                None => provider.search(query, options).await,
+            };
+
+            let e = match result {
+                Ok(results) => {
+                    attempts.push(Attempt::new(provider.name(), attempt + 1, started, None));
+                    self.health.record_success(provider.name()).await;
+                    return Ok(results);
+                }
+                Err(e) => e,
+            };
+            attempts.push(Attempt::new(
+                provider.name(),
+                attempt + 1,
+                started,
+                Some(&e),
+            ));
+            // A rejected query says nothing about the provider's health
+            if e.should_fall_back() {
+                self.health.record_failure(provider.name(), &e).await;
+            }
 
-                    // Calculate backoff delay with exponential increase
-                    let delay_ms = BASE_DELAY_MS * 2u64.pow(attempt);
+            attempt += 1;
+            if attempt >= max_attempts
+                || !policy.is_retryable(&e)
+                || !self.health.allows(provider.name()).await
+            {
+                return Err(e);
+            }
 
-                    // Check if we got a Retry-After header for rate limiting
-                    let delay = if let SearchError::RateLimited {
-                        retry_after: Some(secs),
-                        ..
-                    } = &e
-                    {
-                        Duration::from_secs(*secs)
-                    } else {
-                        Duration::from_millis(delay_ms)
-                    };
-
-                    tracing::debug!(
-                        "Provider {} attempt {} failed, retrying in {:?}: {}",
+            let delay = match policy.delay(attempt - 1, &e) {
+                Some(delay) => delay,
+                None => {
+                    tracing::info!(
+                        "Provider {} asked to wait longer than {:?}, not retrying",
                         provider.name(),
-                        attempt + 1,
-                        delay,
-                        e
+                        policy.max_retry_after
                     );
-
-                    last_error = Some(e);
-                    sleep(delay).await;
+                    return Err(e);
+                }
+            };
+            if let Some((at, _)) = deadline {
+                if Instant::now() + delay >= at {
+                    return Err(e);
                 }
             }
-        }
 
-        Err(last_error.unwrap_or_else(|| SearchError::Api {
-            provider: provider.name().to_string(),
-            message: "Unknown error after retries".to_string(),
-        }))
+            tracing::debug!(
+                "Provider {} attempt {} failed, retrying in {:?}: {}",
+                provider.name(),
+                attempt,
+                delay,
+                e
+            );
+            sleep(delay).await;
+        }
     }
 
     /// List all providers with their status
@@ -319,81 +512,169 @@ pub struct ProviderStatus {
 }
 
 /// Build a provider registry from configuration
-pub fn build_registry(config: &crate::config::Config) -> ProviderRegistry {
+pub fn build_registry(config: &crate::config::Config) -> Result<ProviderRegistry> {
+    // Secret references are only resolved once providers are needed
+    let mut config = config.clone();
+    crate::config::resolve_secrets(&mut config)?;
+
     let mut registry = ProviderRegistry::new();
+    let client = crate::http::build_client(&config.network)?;
 
     // Register Brave provider if configured
     if let Some(ref brave_config) = config.providers.brave {
         if brave_config.enabled {
-            registry.register(Box::new(BraveProvider::new(brave_config.api_key.clone())));
+            registry.register(Box::new(
+                BraveProvider::new(brave_config.api_key.clone()).with_client(client.clone()),
+            ));
         }
     }
 
     // Register Google provider if configured
     if let Some(ref google_config) = config.providers.google {
         if google_config.enabled {
-            registry.register(Box::new(GoogleProvider::new(
-                google_config.api_key.clone(),
-                google_config.cx.clone(),
-            )));
+            registry.register(Box::new(
+                GoogleProvider::new(google_config.api_key.clone(), google_config.cx.clone())
+                    .with_client(client.clone()),
+            ));
         }
     }
 
     // Register DuckDuckGo provider if configured
     if let Some(ref ddg_config) = config.providers.duckduckgo {
         if ddg_config.enabled {
-            registry.register(Box::new(DuckDuckGoProvider::new(true)));
+            registry.register(Box::new(
+                DuckDuckGoProvider::new(true).with_client(client.clone()),
+            ));
         }
     }
 
     // Register Tavily provider if configured
     if let Some(ref tavily_config) = config.providers.tavily {
         if tavily_config.enabled {
-            registry.register(Box::new(TavilyProvider::new(tavily_config.api_key.clone())));
+            registry.register(Box::new(
+                TavilyProvider::new(tavily_config.api_key.clone()).with_client(client.clone()),
+            ));
         }
     }
 
     // Register Serper provider if configured
     if let Some(ref serper_config) = config.providers.serper {
         if serper_config.enabled {
-            registry.register(Box::new(SerperProvider::new(serper_config.api_key.clone())));
+            registry.register(Box::new(
+                SerperProvider::new(serper_config.api_key.clone()).with_client(client.clone()),
+            ));
         }
     }
 
     // Register Firecrawl provider if configured
     if let Some(ref firecrawl_config) = config.providers.firecrawl {
         if firecrawl_config.enabled {
-            registry.register(Box::new(FirecrawlProvider::new(
-                firecrawl_config.api_key.clone(),
-            )));
+            registry.register(Box::new(
+                FirecrawlProvider::new(firecrawl_config.api_key.clone())
+                    .with_client(client.clone()),
+            ));
         }
     }
 
     // Register SerpAPI provider if configured
     if let Some(ref serpapi_config) = config.providers.serpapi {
         if serpapi_config.enabled {
-            registry.register(Box::new(SerpApiProvider::new(
-                serpapi_config.api_key.clone(),
-            )));
+            registry.register(Box::new(
+                SerpApiProvider::new(serpapi_config.api_key.clone()).with_client(client.clone()),
+            ));
         }
     }
 
     // Register Bing provider if configured
     if let Some(ref bing_config) = config.providers.bing {
         if bing_config.enabled {
-            registry.register(Box::new(BingProvider::new(bing_config.api_key.clone())));
+            registry.register(Box::new(
+                BingProvider::new(bing_config.api_key.clone()).with_client(client.clone()),
+            ));
         }
     }
 
     // Set fallback order
     registry.set_fallback_order(config.fallback_order.clone());
+    registry.set_default_provider(config.effective_default_provider());
+    registry.set_rate_limiter(RateLimiter::open(config.rate_limits.clone())?);
+    registry.set_health_tracker(HealthTracker::open(config.circuit_breaker.clone())?);
+    registry.set_retry_config(config.retry.clone());
 
-    registry
+    Ok(registry)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// Provider returning canned results, counting calls
+    struct StubProvider {
+        name: &'static str,
+        calls: Arc<AtomicUsize>,
+        /// HTTP status returned instead of results
+        fail_status: Option<u16>,
+        /// Provider error code sent with `fail_status`
+        fail_code: Option<&'static str>,
+        delay: Duration,
+    }
+
+    impl StubProvider {
+        fn new(name: &'static str) -> (Self, Arc<AtomicUsize>) {
+            let calls = Arc::new(AtomicUsize::new(0));
+            (
+                Self {
+                    name,
+                    calls: Arc::clone(&calls),
+                    fail_status: None,
+                    fail_code: None,
+                    delay: Duration::ZERO,
+                },
+                calls,
+            )
+        }
+
+        fn failing(name: &'static str) -> (Self, Arc<AtomicUsize>) {
+            let (mut provider, calls) = Self::new(name);
+            provider.fail_status = Some(500);
+            (provider, calls)
+        }
+    }
+
+    #[async_trait]
+    impl SearchProvider for StubProvider {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        async fn search(&self, query: &str, _options: &SearchOptions) -> Result<Vec<SearchResult>> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            sleep(self.delay).await;
+            if let Some(status) = self.fail_status {
+                let code = self.fail_code.map(String::from);
+                return Err(SearchError::http(self.name, status, code, "Stub failure"));
+            }
+            Ok(vec![SearchResult {
+                title: format!("{} result", query),
+                url: "https://example.com".to_string(),
+                snippet: String::new(),
+                position: 1,
+                published_date: None,
+                source: Some(self.name.to_string()),
+            }])
+        }
+
+        async fn validate_api_key(&self) -> Result<bool> {
+            Ok(true)
+        }
+
+        fn is_configured(&self) -> bool {
+            true
+        }
+    }
 
     #[test]
     fn test_search_result_serialization() {
@@ -432,6 +713,21 @@ mod tests {
         assert_eq!(options.timeout, Duration::from_secs(30));
     }
 
+    #[test]
+    fn test_search_options_with_domains() {
+        let options = SearchOptions::new()
+            .with_include_domains(Some(vec!["rust-lang.org".to_string()]))
+            .with_exclude_domains(Some(vec!["example.com".to_string()]));
+        assert_eq!(
+            options.include_domains,
+            Some(vec!["rust-lang.org".to_string()])
+        );
+        assert_eq!(
+            options.exclude_domains,
+            Some(vec!["example.com".to_string()])
+        );
+    }
+
     #[test]
     fn test_search_options_with_date_range() {
         let options = SearchOptions::new().with_date_range(Some(DateRange::Week));
@@ -521,6 +817,279 @@ mod tests {
         assert_eq!(providers[2].name(), "google");
     }
 
+    #[tokio::test]
+    async fn test_search_with_fallback_skips_exhausted_provider() {
+        let (brave, brave_calls) = StubProvider::new("brave");
+        let (tavily, tavily_calls) = StubProvider::new("tavily");
+
+        let mut registry = ProviderRegistry::new();
+        registry.register(Box::new(brave));
+        registry.register(Box::new(tavily));
+        registry.set_rate_limiter(RateLimiter::new(HashMap::from([(
+            "brave".to_string(),
+            crate::config::RateLimitConfig {
+                per_day: Some(1),
+                ..Default::default()
+            },
+        )])));
+
+        let options = SearchOptions::new();
+        let (_, provider) = registry
+            .search_with_fallback("rust", &options, None, &mut Vec::new())
+            .await
+            .unwrap();
+        assert_eq!(provider, "brave");
+
+        // Brave's daily quota is now used up, so it is skipped without a request
+        let (_, provider) = registry
+            .search_with_fallback("rust", &options, None, &mut Vec::new())
+            .await
+            .unwrap();
+        assert_eq!(provider, "tavily");
+        assert_eq!(brave_calls.load(Ordering::SeqCst), 1);
+        assert_eq!(tavily_calls.load(Ordering::SeqCst), 1);
+        assert_eq!(registry.rate_limiter().usage().await["brave"].day_count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_search_with_fallback_skips_open_circuit() {
+        let (brave, brave_calls) = StubProvider::failing("brave");
+        let (tavily, tavily_calls) = StubProvider::new("tavily");
+
+        let mut registry = ProviderRegistry::new();
+        registry.register(Box::new(brave));
+        registry.register(Box::new(tavily));
+        registry.set_health_tracker(HealthTracker::new(crate::config::CircuitBreakerConfig {
+            enabled: true,
+            failure_threshold: 2,
+            cooldown_seconds: 60,
+        }));
+
+        let options = SearchOptions::new();
+        for _ in 0..3 {
+            let (_, provider) = registry
+                .search_with_fallback("rust", &options, None, &mut Vec::new())
+                .await
+                .unwrap();
+            assert_eq!(provider, "tavily");
+        }
+
+        // The circuit opened after two failures, so the third search skipped brave
+        assert_eq!(brave_calls.load(Ordering::SeqCst), 2);
+        assert_eq!(tavily_calls.load(Ordering::SeqCst), 3);
+        assert_eq!(registry.health().state("brave").await, CircuitState::Open);
+
+        let mut attempts = Vec::new();
+        registry
+            .search_with_fallback("rust", &options, None, &mut attempts)
+            .await
+            .unwrap();
+        assert_eq!(attempts[0].outcome, AttemptOutcome::Skipped);
+        assert_eq!(attempts[0].error_kind.as_deref(), Some("circuit_open"));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_query_stops_fallback() {
+        let (mut brave, brave_calls) = StubProvider::new("brave");
+        brave.fail_status = Some(422);
+        brave.fail_code = Some("VALIDATION");
+        let (tavily, tavily_calls) = StubProvider::new("tavily");
+
+        let mut registry = ProviderRegistry::new();
+        registry.register(Box::new(brave));
+        registry.register(Box::new(tavily));
+
+        let result = registry
+            .search_with_fallback("rust", &SearchOptions::new(), None, &mut Vec::new())
+            .await;
+        assert!(matches!(
+            result,
+            Err(SearchError::Api {
+                status: Some(422),
+                ..
+            })
+        ));
+        // Neither retried nor counted against the provider's health
+        assert_eq!(brave_calls.load(Ordering::SeqCst), 1);
+        assert_eq!(tavily_calls.load(Ordering::SeqCst), 0);
+        assert_eq!(registry.health().get("brave").await.consecutive_failures, 0);
+    }
+
+    #[tokio::test]
+    async fn test_other_client_errors_fall_back() {
+        let (mut google, google_calls) = StubProvider::new("google");
+        google.fail_status = Some(404);
+        let (tavily, tavily_calls) = StubProvider::new("tavily");
+
+        let mut registry = ProviderRegistry::new();
+        registry.register(Box::new(google));
+        registry.register(Box::new(tavily));
+
+        let (_, provider) = registry
+            .search_with_fallback("rust", &SearchOptions::new(), None, &mut Vec::new())
+            .await
+            .unwrap();
+        assert_eq!(provider, "tavily");
+        assert_eq!(google_calls.load(Ordering::SeqCst), 1);
+        assert_eq!(tavily_calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_search_with_fallback_records_attempts() {
+        let (brave, _) = StubProvider::failing("brave");
+        let (tavily, _) = StubProvider::new("tavily");
+
+        let mut registry = ProviderRegistry::new();
+        registry.register(Box::new(brave));
+        registry.register(Box::new(tavily));
+        registry
+            .set_retry_config(serde_yaml::from_str("base_delay_ms: 1\nmax_attempts: 2").unwrap());
+
+        let mut attempts = Vec::new();
+        registry
+            .search_with_fallback("rust", &SearchOptions::new(), None, &mut attempts)
+            .await
+            .unwrap();
+
+        let summary: Vec<_> = attempts
+            .iter()
+            .map(|a| (a.provider.as_str(), a.attempt, a.outcome))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("brave", 1, AttemptOutcome::Error),
+                ("brave", 2, AttemptOutcome::Error),
+                ("tavily", 1, AttemptOutcome::Success),
+            ]
+        );
+        assert_eq!(attempts[0].error_kind.as_deref(), Some("server_error"));
+        assert!(attempts[2].error.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_default_provider_is_tried_first() {
+        let (brave, brave_calls) = StubProvider::new("brave");
+        let (tavily, _) = StubProvider::new("tavily");
+
+        let mut registry = ProviderRegistry::new();
+        registry.register(Box::new(brave));
+        registry.register(Box::new(tavily));
+        registry.set_fallback_order(vec!["brave".to_string(), "tavily".to_string()]);
+        registry.set_default_provider(Some("tavily".to_string()));
+
+        let (_, provider) = registry
+            .search_with_fallback("rust", &SearchOptions::new(), None, &mut Vec::new())
+            .await
+            .unwrap();
+        assert_eq!(provider, "tavily");
+        assert_eq!(brave_calls.load(Ordering::SeqCst), 0);
+
+        // An explicitly requested provider still wins
+        let (_, provider) = registry
+            .search_with_fallback(
+                "rust",
+                &SearchOptions::new(),
+                Some("brave"),
+                &mut Vec::new(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(provider, "brave");
+    }
+
+    #[tokio::test]
+    async fn test_search_with_retry_uses_retry_config() {
+        let (brave, brave_calls) = StubProvider::failing("brave");
+        let (tavily, tavily_calls) = StubProvider::new("tavily");
+
+        let mut registry = ProviderRegistry::new();
+        registry.register(Box::new(brave));
+        registry.register(Box::new(tavily));
+        registry.set_retry_config(
+            serde_yaml::from_str(
+                "retry_on: [server_error]\nbase_delay_ms: 1\nproviders:\n  brave:\n    max_attempts: 2",
+            )
+            .unwrap(),
+        );
+
+        let (_, provider) = registry
+            .search_with_fallback("rust", &SearchOptions::new(), None, &mut Vec::new())
+            .await
+            .unwrap();
+        assert_eq!(provider, "tavily");
+        assert_eq!(brave_calls.load(Ordering::SeqCst), 2);
+        assert_eq!(tavily_calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_search_with_fallback_deadline() {
+        let (mut brave, _) = StubProvider::new("brave");
+        brave.delay = Duration::from_secs(5);
+        let (tavily, tavily_calls) = StubProvider::new("tavily");
+
+        let mut registry = ProviderRegistry::new();
+        registry.register(Box::new(brave));
+        registry.register(Box::new(tavily));
+        registry.set_retry_config(RetryConfig {
+            deadline_seconds: Some(1),
+            ..Default::default()
+        });
+        registry.set_health_tracker(HealthTracker::new(crate::config::CircuitBreakerConfig {
+            enabled: true,
+            failure_threshold: 1,
+            cooldown_seconds: 60,
+        }));
+
+        let start = Instant::now();
+        let mut attempts = Vec::new();
+        let result = registry
+            .search_with_fallback("rust", &SearchOptions::new(), None, &mut attempts)
+            .await;
+        assert!(matches!(result, Err(SearchError::Timeout(1))));
+        assert!(start.elapsed() < Duration::from_secs(3));
+        assert_eq!(tavily_calls.load(Ordering::SeqCst), 0);
+        assert_eq!(attempts.len(), 1);
+
+        // Running out of time is not the provider's failure
+        assert_eq!(registry.health().get("brave").await.consecutive_failures, 0);
+        assert_eq!(registry.health().state("brave").await, CircuitState::Closed);
+    }
+
+    #[tokio::test]
+    async fn test_search_with_fallback_all_circuits_open() {
+        let (brave, _) = StubProvider::failing("brave");
+
+        let mut registry = ProviderRegistry::new();
+        registry.register(Box::new(brave));
+        registry.set_health_tracker(HealthTracker::new(crate::config::CircuitBreakerConfig {
+            enabled: true,
+            failure_threshold: 1,
+            cooldown_seconds: 60,
+        }));
+
+        let options = SearchOptions::new();
+        assert!(registry
+            .search_with_fallback("rust", &options, None, &mut Vec::new())
+            .await
+            .is_err());
+        match registry
+            .search_with_fallback("rust", &options, None, &mut Vec::new())
+            .await
+        {
+            Err(SearchError::AllProvidersFailed(errors)) => {
+                assert!(matches!(
+                    errors.as_slice(),
+                    [SearchError::CircuitOpen {
+                        retry_after: Some(_),
+                        ..
+                    }]
+                ));
+            }
+            _ => panic!("Expected AllProvidersFailed"),
+        }
+    }
+
     #[test]
     fn test_provider_registry_providers_in_order_with_unconfigured() {
         let mut registry = ProviderRegistry::new();
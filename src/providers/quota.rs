@@ -0,0 +1,370 @@
+//! Client-side rate limits and usage tracking for providers
+//!
+//! Each provider can have a requests-per-second token bucket plus daily and
+//! monthly quotas. Usage counters are persisted so quotas hold across
+//! invocations; days and months roll over at midnight UTC.
+
+use super::state::StateFile;
+use crate::config::{data_dir, RateLimitConfig};
+use crate::error::{Result, SearchError};
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::sleep;
+
+/// File name for persisted usage counters
+const USAGE_FILE: &str = "usage.json";
+
+/// Request counters for a single provider
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProviderUsage {
+    /// UTC day the daily counter applies to (YYYY-MM-DD)
+    #[serde(default)]
+    pub day: String,
+    /// Requests made on `day`
+    #[serde(default)]
+    pub day_count: u64,
+    /// UTC month the monthly counter applies to (YYYY-MM)
+    #[serde(default)]
+    pub month: String,
+    /// Requests made in `month`
+    #[serde(default)]
+    pub month_count: u64,
+    /// Requests made since tracking started
+    #[serde(default)]
+    pub total: u64,
+    /// Remaining per-second tokens
+    #[serde(default)]
+    tokens: f64,
+    /// When the token bucket was last refilled (Unix milliseconds)
+    #[serde(default)]
+    refilled_at_ms: i64,
+}
+
+impl ProviderUsage {
+    /// Reset counters whose period has ended
+    fn roll_over(&mut self, now: DateTime<Utc>) {
+        let day = now.format("%Y-%m-%d").to_string();
+        if self.day != day {
+            self.day = day;
+            self.day_count = 0;
+        }
+        let month = now.format("%Y-%m").to_string();
+        if self.month != month {
+            self.month = month;
+            self.month_count = 0;
+        }
+    }
+
+    /// Seconds until the exhausted quota resets, if any quota is exhausted
+    fn exhausted(&self, limit: &RateLimitConfig, now: DateTime<Utc>) -> Option<u64> {
+        if limit.per_month.is_some_and(|max| self.month_count >= max) {
+            return Some(seconds_until(next_month(now), now));
+        }
+        if limit.per_day.is_some_and(|max| self.day_count >= max) {
+            return Some(seconds_until(next_day(now), now));
+        }
+        None
+    }
+
+    /// Take a per-second token, or return how long to wait for one
+    fn take_token(&mut self, limit: &RateLimitConfig, now: DateTime<Utc>) -> Option<Duration> {
+        let rate = match limit.per_second {
+            Some(rate) if rate > 0.0 => rate,
+            _ => return None,
+        };
+        let capacity = rate.max(1.0);
+        let now_ms = now.timestamp_millis();
+
+        if self.refilled_at_ms == 0 {
+            self.tokens = capacity;
+        } else {
+            let elapsed = (now_ms - self.refilled_at_ms).max(0) as f64 / 1000.0;
+            self.tokens = (self.tokens + elapsed * rate).min(capacity);
+        }
+        self.refilled_at_ms = now_ms;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            None
+        } else {
+            Some(Duration::from_secs_f64((1.0 - self.tokens) / rate))
+        }
+    }
+
+    fn record(&mut self) {
+        self.day_count += 1;
+        self.month_count += 1;
+        self.total += 1;
+    }
+}
+
+/// Enforces rate limits and tracks request counts per provider
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    limits: HashMap<String, RateLimitConfig>,
+    usage: Arc<StateFile<ProviderUsage>>,
+}
+
+impl RateLimiter {
+    /// Create an in-memory limiter
+    pub fn new(limits: HashMap<String, RateLimitConfig>) -> Self {
+        Self {
+            limits,
+            usage: Arc::default(),
+        }
+    }
+
+    /// Create a limiter persisting counters in the data directory
+    pub fn open(limits: HashMap<String, RateLimitConfig>) -> Result<Self> {
+        Ok(Self::new(limits).with_path(data_dir()?.join(USAGE_FILE)))
+    }
+
+    /// Persist counters to a specific file
+    pub fn with_path(mut self, path: PathBuf) -> Self {
+        self.usage = Arc::new(StateFile::at(path));
+        self
+    }
+
+    /// Configured limits for a provider
+    pub fn limits_for(&self, provider: &str) -> RateLimitConfig {
+        self.limits.get(provider).cloned().unwrap_or_default()
+    }
+
+    /// Check whether a provider's daily or monthly quota is used up
+    pub async fn check(&self, provider: &str) -> Result<()> {
+        let limit = self.limits_for(provider);
+        let provider = provider.to_string();
+        self.usage
+            .blocking(move |state| {
+                let now = Utc::now();
+                let mut usage = state.lock();
+                let entry = usage.entry(provider.clone()).or_default();
+                entry.roll_over(now);
+                match entry.exhausted(&limit, now) {
+                    Some(reset_after) => {
+                        Err(SearchError::quota_exceeded(&provider, Some(reset_after)))
+                    }
+                    None => Ok(()),
+                }
+            })
+            .await
+    }
+
+    /// Wait for a request slot and record the request
+    ///
+    /// Sleeps while the per-second bucket is empty and fails with
+    /// `QuotaExceeded` when the daily or monthly quota is used up.
+    pub async fn acquire(&self, provider: &str) -> Result<()> {
+        let limit = self.limits_for(provider);
+
+        loop {
+            let limit = limit.clone();
+            let name = provider.to_string();
+            let wait = self
+                .usage
+                .blocking(move |state| {
+                    let now = Utc::now();
+                    let mut usage = state.lock();
+                    let entry = usage.entry(name.clone()).or_default();
+                    entry.roll_over(now);
+
+                    if let Some(reset_after) = entry.exhausted(&limit, now) {
+                        return Err(SearchError::quota_exceeded(&name, Some(reset_after)));
+                    }
+
+                    let wait = entry.take_token(&limit, now);
+                    if wait.is_none() {
+                        entry.record();
+                    }
+                    state.save(usage);
+                    Ok(wait)
+                })
+                .await?;
+
+            match wait {
+                Some(delay) => {
+                    tracing::debug!("Rate limiting {} for {:?}", provider, delay);
+                    sleep(delay).await;
+                }
+                None => return Ok(()),
+            }
+        }
+    }
+
+    /// Current counters for all providers that have been used
+    pub async fn usage(&self) -> HashMap<String, ProviderUsage> {
+        self.usage
+            .blocking(|state| {
+                let now = Utc::now();
+                let mut usage = state.lock();
+                for entry in usage.values_mut() {
+                    entry.roll_over(now);
+                }
+                usage.clone()
+            })
+            .await
+    }
+}
+
+fn next_day(now: DateTime<Utc>) -> DateTime<Utc> {
+    (now.date_naive() + ChronoDuration::days(1))
+        .and_hms_opt(0, 0, 0)
+        .map(|dt| dt.and_utc())
+        .unwrap_or(now)
+}
+
+fn next_month(now: DateTime<Utc>) -> DateTime<Utc> {
+    let (year, month) = if now.month() == 12 {
+        (now.year() + 1, 1)
+    } else {
+        (now.year(), now.month() + 1)
+    };
+    NaiveDate::from_ymd_opt(year, month, 1)
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .map(|dt| dt.and_utc())
+        .unwrap_or(now)
+}
+
+fn seconds_until(reset: DateTime<Utc>, now: DateTime<Utc>) -> u64 {
+    (reset - now).num_seconds().max(0) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use tokio::time::timeout;
+
+    fn limiter(provider: &str, limit: RateLimitConfig) -> RateLimiter {
+        RateLimiter::new(HashMap::from([(provider.to_string(), limit)]))
+    }
+
+    #[tokio::test]
+    async fn test_daily_quota_exhausted() {
+        let limiter = limiter(
+            "google",
+            RateLimitConfig {
+                per_day: Some(2),
+                ..Default::default()
+            },
+        );
+
+        assert!(limiter.acquire("google").await.is_ok());
+        assert!(limiter.check("google").await.is_ok());
+        assert!(limiter.acquire("google").await.is_ok());
+
+        assert!(matches!(
+            limiter.check("google").await,
+            Err(SearchError::QuotaExceeded { .. })
+        ));
+        assert!(matches!(
+            limiter.acquire("google").await,
+            Err(SearchError::QuotaExceeded { .. })
+        ));
+        assert_eq!(limiter.usage().await["google"].day_count, 2);
+
+        // Other providers are unaffected
+        assert!(limiter.acquire("brave").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_per_second_bucket_waits() {
+        let limiter = limiter(
+            "brave",
+            RateLimitConfig {
+                per_second: Some(20.0),
+                ..Default::default()
+            },
+        );
+
+        let start = std::time::Instant::now();
+        for _ in 0..22 {
+            limiter.acquire("brave").await.unwrap();
+        }
+        // 20 tokens are available up front, the rest refill at 20/s
+        assert!(start.elapsed() >= Duration::from_millis(90));
+        assert_eq!(limiter.usage().await["brave"].total, 22);
+    }
+
+    #[test]
+    fn test_counters_roll_over() {
+        let mut usage = ProviderUsage {
+            day: "2024-01-31".to_string(),
+            day_count: 5,
+            month: "2024-01".to_string(),
+            month_count: 50,
+            ..Default::default()
+        };
+        usage.roll_over(Utc.with_ymd_and_hms(2024, 2, 1, 0, 0, 1).unwrap());
+        assert_eq!(usage.day_count, 0);
+        assert_eq!(usage.month_count, 0);
+        assert_eq!(usage.month, "2024-02");
+    }
+
+    #[test]
+    fn test_reset_after() {
+        let now = Utc.with_ymd_and_hms(2024, 12, 31, 23, 0, 0).unwrap();
+        assert_eq!(seconds_until(next_day(now), now), 3600);
+        assert_eq!(seconds_until(next_month(now), now), 3600);
+
+        let usage = ProviderUsage {
+            month_count: 10,
+            ..Default::default()
+        };
+        let limit = RateLimitConfig {
+            per_month: Some(10),
+            ..Default::default()
+        };
+        assert_eq!(usage.exhausted(&limit, now), Some(3600));
+    }
+
+    #[tokio::test]
+    async fn test_usage_persisted() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(USAGE_FILE);
+
+        let limits = HashMap::from([(
+            "serpapi".to_string(),
+            RateLimitConfig {
+                per_month: Some(1),
+                ..Default::default()
+            },
+        )]);
+
+        let first = RateLimiter::new(limits.clone()).with_path(path.clone());
+        first.acquire("serpapi").await.unwrap();
+
+        // A later invocation sees the stored counters
+        let second = RateLimiter::new(limits).with_path(path);
+        assert_eq!(second.usage().await["serpapi"].month_count, 1);
+        assert!(matches!(
+            second.check("serpapi").await,
+            Err(SearchError::QuotaExceeded { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_waiting_for_lock_keeps_runtime_running() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(USAGE_FILE);
+        let limiter = RateLimiter::new(HashMap::new()).with_path(path.clone());
+
+        // Another invocation holds the state file
+        let other = StateFile::<ProviderUsage>::at(path);
+        let held = other.lock();
+
+        let acquire = limiter.acquire("brave");
+        tokio::pin!(acquire);
+        assert!(timeout(Duration::from_millis(100), &mut acquire)
+            .await
+            .is_err());
+
+        drop(held);
+        acquire.await.unwrap();
+        assert_eq!(limiter.usage().await["brave"].total, 1);
+    }
+}
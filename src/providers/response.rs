@@ -0,0 +1,249 @@
+//! Mapping of failed provider responses to `SearchError`
+//!
+//! Every provider funnels non-success responses through here so that the
+//! fallback and retry logic sees the same classification regardless of which
+//! API produced the error.
+
+use crate::error::SearchError;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::{Response, StatusCode};
+use serde_json::Value;
+
+/// Statuses some providers use for an exhausted plan or credit balance
+const QUOTA_STATUSES: &[u16] = &[402, 432, 433];
+
+/// Provider error codes that mean the quota is used up
+const QUOTA_CODES: &[&str] = &[
+    "dailyLimitExceeded",
+    "quotaExceeded",
+    "QUOTA_LIMITED",
+    "insufficient_quota",
+];
+
+/// Message fragments that mean the quota is used up
+const QUOTA_MESSAGES: &[&str] = &[
+    "quota",
+    "credits",
+    "usage limit",
+    "plan limit",
+    "run out of searches",
+];
+
+/// Longest error body kept in a message
+const MAX_MESSAGE_LEN: usize = 500;
+
+/// Convert a non-success response into a classified error
+pub async fn error_from_response(provider: &str, response: Response) -> SearchError {
+    let status = response.status();
+    let retry_after = parse_retry_after(response.headers());
+    let body = response.text().await.unwrap_or_default();
+    classify(provider, status, retry_after, &body)
+}
+
+/// Classify an error reported in the body of an otherwise successful response
+pub fn error_from_body(provider: &str, message: impl Into<String>) -> SearchError {
+    let message = message.into();
+    if is_quota_message(&message) {
+        return SearchError::quota_exceeded(provider, None);
+    }
+    SearchError::api(provider, message)
+}
+
+/// Classify a failed response from its status, `Retry-After` and body
+pub fn classify(
+    provider: &str,
+    status: StatusCode,
+    retry_after: Option<u64>,
+    body: &str,
+) -> SearchError {
+    let (code, message) = parse_body(body);
+    let status = status.as_u16();
+    let is_quota = QUOTA_STATUSES.contains(&status)
+        || code.as_deref().is_some_and(|c| QUOTA_CODES.contains(&c))
+        || (matches!(status, 400 | 403 | 429) && message.as_deref().is_some_and(is_quota_message));
+
+    match status {
+        _ if is_quota => SearchError::quota_exceeded(provider, retry_after),
+        429 => SearchError::rate_limited(provider, retry_after),
+        401 | 403 => SearchError::invalid_api_key(provider),
+        _ => {
+            let message = message.unwrap_or_else(|| {
+                StatusCode::from_u16(status)
+                    .ok()
+                    .and_then(|s| s.canonical_reason())
+                    .unwrap_or("Unknown error")
+                    .to_string()
+            });
+            SearchError::http(provider, status, code, message)
+        }
+    }
+}
+
+/// `Retry-After` in seconds (HTTP dates are not supported)
+fn parse_retry_after(headers: &HeaderMap) -> Option<u64> {
+    headers
+        .get(RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse().ok())
+}
+
+fn is_quota_message(message: &str) -> bool {
+    let message = message.to_lowercase();
+    QUOTA_MESSAGES.iter().any(|m| message.contains(m))
+}
+
+/// Extract an error code and message from the common JSON error shapes
+///
+/// Handles `{"error": {"code", "message"|"detail", "errors": [{"reason"}]}}`
+/// (Google, Bing, Brave), `{"error": "..."}` (SerpAPI, Firecrawl),
+/// `{"detail": {"error": "..."}}` (Tavily) and `{"message": "..."}` (Serper).
+/// Non-JSON bodies are used as the message as-is.
+fn parse_body(body: &str) -> (Option<String>, Option<String>) {
+    let body = body.trim();
+    if body.is_empty() {
+        return (None, None);
+    }
+
+    let json: Value = match serde_json::from_str(body) {
+        Ok(json) => json,
+        Err(_) => return (None, Some(truncate(body))),
+    };
+
+    let text = |v: &Value| match v {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    };
+
+    let (code, message) = match json.get("error") {
+        Some(error @ Value::Object(_)) => {
+            let code = error
+                .pointer("/errors/0/reason")
+                .and_then(text)
+                .or_else(|| error.get("code").and_then(text));
+            let message = error
+                .get("message")
+                .or_else(|| error.get("detail"))
+                .and_then(text);
+            (code, message)
+        }
+        Some(error) => (None, text(error)),
+        None => {
+            let message = json
+                .get("message")
+                .or_else(|| json.pointer("/detail/error"))
+                .or_else(|| json.get("detail"))
+                .and_then(text);
+            (json.get("code").and_then(text), message)
+        }
+    };
+
+    (code, message.or_else(|| Some(truncate(body))))
+}
+
+fn truncate(text: &str) -> String {
+    match text.char_indices().nth(MAX_MESSAGE_LEN) {
+        Some((end, _)) => format!("{}...", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+
+    fn status(code: u16) -> StatusCode {
+        StatusCode::from_u16(code).unwrap()
+    }
+
+    #[test]
+    fn test_server_error_keeps_status() {
+        let error = classify("brave", status(503), None, "");
+        assert!(matches!(
+            error,
+            SearchError::Api {
+                status: Some(503),
+                ..
+            }
+        ));
+        assert_eq!(error.kind(), ErrorKind::ServerError);
+        assert_eq!(
+            error.to_string(),
+            "API error from brave: HTTP 503: Service Unavailable"
+        );
+    }
+
+    #[test]
+    fn test_google_error_body() {
+        let body = r#"{"error": {"code": 400, "message": "Invalid Value",
+            "errors": [{"reason": "invalid", "message": "Invalid Value"}]}}"#;
+        let error = classify("google", status(400), None, body);
+        assert_eq!(error.kind(), ErrorKind::ClientError);
+        assert!(matches!(error, SearchError::Api { code: Some(ref c), .. } if c == "invalid"));
+        assert!(error.to_string().ends_with("HTTP 400: Invalid Value"));
+
+        let body = r#"{"error": {"code": 403, "message": "Daily Limit Exceeded",
+            "errors": [{"reason": "dailyLimitExceeded"}]}}"#;
+        let error = classify("google", status(403), None, body);
+        assert!(matches!(error, SearchError::QuotaExceeded { .. }));
+    }
+
+    #[test]
+    fn test_quota_statuses_and_messages() {
+        let error = classify(
+            "firecrawl",
+            status(402),
+            None,
+            r#"{"error": "Payment required"}"#,
+        );
+        assert!(matches!(error, SearchError::QuotaExceeded { .. }));
+
+        let error = classify("tavily", status(432), None, "");
+        assert!(matches!(error, SearchError::QuotaExceeded { .. }));
+
+        let body = r#"{"message": "Not enough credits", "statusCode": 400}"#;
+        let error = classify("serper", status(400), None, body);
+        assert!(matches!(error, SearchError::QuotaExceeded { .. }));
+
+        assert!(matches!(
+            error_from_body("serpapi", "Your account has run out of searches."),
+            SearchError::QuotaExceeded { .. }
+        ));
+        assert_eq!(
+            error_from_body("serpapi", "Invalid engine").kind(),
+            ErrorKind::Api
+        );
+    }
+
+    #[test]
+    fn test_rate_limit_and_auth() {
+        let error = classify("brave", status(429), Some(7), "");
+        assert!(matches!(
+            error,
+            SearchError::RateLimited {
+                retry_after: Some(7),
+                ..
+            }
+        ));
+
+        let body = r#"{"error": {"code": "SUBSCRIPTION_TOKEN_INVALID", "detail": "bad token"}}"#;
+        assert!(matches!(
+            classify("brave", status(401), None, body),
+            SearchError::InvalidApiKey { .. }
+        ));
+    }
+
+    #[test]
+    fn test_plain_text_body_is_truncated() {
+        let body = "x".repeat(2000);
+        let error = classify("bing", status(500), None, &body);
+        match error {
+            SearchError::Api { message, code, .. } => {
+                assert!(code.is_none());
+                assert_eq!(message.len(), MAX_MESSAGE_LEN + 3);
+            }
+            _ => panic!("Expected Api error"),
+        }
+    }
+}
@@ -0,0 +1,193 @@
+//! Retry policy for provider requests
+//!
+//! Settings come from the `retry:` config block, with per-provider overrides
+//! taking precedence over the global values and built-in defaults.
+
+use crate::config::{RetryConfig, RetrySettings};
+use crate::error::SearchError;
+use rand::Rng;
+use std::time::Duration;
+
+/// Default attempts per provider, including the first request
+const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+
+/// Default delay before the first retry (in milliseconds)
+const DEFAULT_BASE_DELAY_MS: u64 = 500;
+
+/// Default upper bound for a single backoff delay (in milliseconds)
+const DEFAULT_MAX_DELAY_MS: u64 = 30_000;
+
+/// Default fraction of each delay that is randomized
+const DEFAULT_JITTER: f64 = 0.5;
+
+/// Default longest `Retry-After` worth waiting for (in seconds)
+const DEFAULT_MAX_RETRY_AFTER_SECONDS: u64 = 60;
+
+/// Resolved retry settings for one provider
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Attempts per provider, including the first request
+    pub max_attempts: u32,
+    /// Delay before the first retry
+    pub base_delay: Duration,
+    /// Upper bound for a single backoff delay
+    pub max_delay: Duration,
+    /// Fraction of each delay that is randomized
+    pub jitter: f64,
+    /// Longest `Retry-After` worth waiting for
+    pub max_retry_after: Duration,
+    /// Error kinds that are retried (see `SearchError::kind`); when unset,
+    /// `SearchError::is_retryable` decides
+    pub retry_on: Option<Vec<String>>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::resolve(&[])
+    }
+}
+
+impl RetryPolicy {
+    /// Resolve the policy for a provider from config
+    pub fn for_provider(config: &RetryConfig, provider: &str) -> Self {
+        match config.providers.get(provider) {
+            Some(overrides) => Self::resolve(&[overrides, &config.defaults]),
+            None => Self::resolve(&[&config.defaults]),
+        }
+    }
+
+    /// Take each setting from the first layer that has it
+    fn resolve(layers: &[&RetrySettings]) -> Self {
+        fn pick<T: Clone>(
+            layers: &[&RetrySettings],
+            f: impl Fn(&RetrySettings) -> Option<T>,
+        ) -> Option<T> {
+            layers.iter().find_map(|l| f(l))
+        }
+
+        Self {
+            max_attempts: pick(layers, |l| l.max_attempts)
+                .unwrap_or(DEFAULT_MAX_ATTEMPTS)
+                .max(1),
+            base_delay: Duration::from_millis(
+                pick(layers, |l| l.base_delay_ms).unwrap_or(DEFAULT_BASE_DELAY_MS),
+            ),
+            max_delay: Duration::from_millis(
+                pick(layers, |l| l.max_delay_ms).unwrap_or(DEFAULT_MAX_DELAY_MS),
+            ),
+            jitter: pick(layers, |l| l.jitter)
+                .unwrap_or(DEFAULT_JITTER)
+                .clamp(0.0, 1.0),
+            max_retry_after: Duration::from_secs(
+                pick(layers, |l| l.max_retry_after_seconds)
+                    .unwrap_or(DEFAULT_MAX_RETRY_AFTER_SECONDS),
+            ),
+            retry_on: pick(layers, |l| l.retry_on.clone()),
+        }
+    }
+
+    /// Whether an error is worth retrying
+    pub fn is_retryable(&self, error: &SearchError) -> bool {
+        match self.retry_on {
+            Some(ref kinds) => kinds.iter().any(|kind| kind == error.kind().as_str()),
+            None => error.is_retryable(),
+        }
+    }
+
+    /// Delay before retry number `retry` (starting at 0), or `None` when the
+    /// provider asked us to wait longer than `max_retry_after`
+    pub fn delay(&self, retry: u32, error: &SearchError) -> Option<Duration> {
+        if let SearchError::RateLimited {
+            retry_after: Some(secs),
+            ..
+        } = error
+        {
+            let wait = Duration::from_secs(*secs);
+            return (wait <= self.max_retry_after).then_some(wait);
+        }
+
+        let backoff = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max_delay);
+        let random: f64 = rand::thread_rng().gen();
+        Some(backoff.mul_f64(1.0 - self.jitter * random))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_policy() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.max_attempts, 3);
+        assert_eq!(policy.base_delay, Duration::from_millis(500));
+        assert!(policy.is_retryable(&SearchError::rate_limited("brave", None)));
+        assert!(policy.is_retryable(&SearchError::Timeout(5)));
+        assert!(policy.is_retryable(&SearchError::http("brave", 502, None, "Bad Gateway")));
+        assert!(!policy.is_retryable(&SearchError::http("brave", 400, None, "Bad Request")));
+        assert!(!policy.is_retryable(&SearchError::invalid_api_key("brave")));
+    }
+
+    #[test]
+    fn test_provider_overrides_global() {
+        let yaml = r#"
+max_attempts: 5
+base_delay_ms: 100
+retry_on: [network, api]
+providers:
+  google:
+    max_attempts: 1
+"#;
+        let config: RetryConfig = serde_yaml::from_str(yaml).unwrap();
+
+        let google = RetryPolicy::for_provider(&config, "google");
+        assert_eq!(google.max_attempts, 1);
+        assert_eq!(google.base_delay, Duration::from_millis(100));
+        assert!(google.is_retryable(&SearchError::api("google", "HTTP 500")));
+
+        let brave = RetryPolicy::for_provider(&config, "brave");
+        assert_eq!(brave.max_attempts, 5);
+        assert_eq!(brave.max_delay, Duration::from_millis(DEFAULT_MAX_DELAY_MS));
+    }
+
+    #[test]
+    fn test_backoff_is_capped_and_jittered() {
+        let policy = RetryPolicy {
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(300),
+            jitter: 0.0,
+            ..Default::default()
+        };
+        let error = SearchError::Timeout(5);
+        assert_eq!(policy.delay(0, &error), Some(Duration::from_millis(100)));
+        assert_eq!(policy.delay(1, &error), Some(Duration::from_millis(200)));
+        assert_eq!(policy.delay(5, &error), Some(Duration::from_millis(300)));
+
+        let jittered = RetryPolicy {
+            jitter: 1.0,
+            ..policy
+        };
+        for _ in 0..20 {
+            assert!(jittered.delay(1, &error).unwrap() <= Duration::from_millis(200));
+        }
+    }
+
+    #[test]
+    fn test_retry_after_cap() {
+        let policy = RetryPolicy {
+            max_retry_after: Duration::from_secs(10),
+            ..Default::default()
+        };
+        assert_eq!(
+            policy.delay(0, &SearchError::rate_limited("brave", Some(5))),
+            Some(Duration::from_secs(5))
+        );
+        assert_eq!(
+            policy.delay(0, &SearchError::rate_limited("brave", Some(3600))),
+            None
+        );
+    }
+}
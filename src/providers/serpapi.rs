@@ -3,6 +3,7 @@
 //! SerpAPI provides search results from multiple search engines (Google, Bing, Yahoo, etc.)
 //! See: https://serpapi.com/
 
+use super::response::{error_from_body, error_from_response};
 use super::{SearchOptions, SearchProvider, SearchResult};
 use crate::cli::SafeSearch;
 use crate::error::{Result, SearchError};
@@ -26,6 +27,12 @@ impl SerpApiProvider {
             client: Client::new(),
         }
     }
+
+    /// Use a shared HTTP client (proxy, TLS and timeout settings)
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
 }
 
 #[async_trait]
@@ -80,33 +87,15 @@ impl SearchProvider for SerpApiProvider {
             .send()
             .await?;
 
-        let status = response.status();
-        if status == 429 {
-            let retry_after = response
-                .headers()
-                .get("Retry-After")
-                .and_then(|v| v.to_str().ok())
-                .and_then(|v| v.parse().ok());
-            return Err(SearchError::rate_limited("serpapi", retry_after));
-        }
-
-        if status == 401 || status == 403 {
-            return Err(SearchError::invalid_api_key("serpapi"));
-        }
-
-        if !status.is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(SearchError::api(
-                "serpapi",
-                format!("HTTP {}: {}", status, error_text),
-            ));
+        if !response.status().is_success() {
+            return Err(error_from_response("serpapi", response).await);
         }
 
         let serpapi_response: SerpApiResponse = response.json().await?;
 
         // Check for API error in response
         if let Some(error) = serpapi_response.error {
-            return Err(SearchError::api("serpapi", error));
+            return Err(error_from_body("serpapi", error));
         }
 
         let results = serpapi_response
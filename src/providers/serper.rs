@@ -3,6 +3,7 @@
 //! Serper provides Google Search results via a simple API.
 //! See: https://serper.dev/
 
+use super::response::error_from_response;
 use super::{SearchOptions, SearchProvider, SearchResult};
 use crate::cli::SafeSearch;
 use crate::error::{Result, SearchError};
@@ -26,6 +27,12 @@ impl SerperProvider {
             client: Client::new(),
         }
     }
+
+    /// Use a shared HTTP client (proxy, TLS and timeout settings)
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
 }
 
 #[async_trait]
@@ -67,26 +74,8 @@ impl SearchProvider for SerperProvider {
             .send()
             .await?;
 
-        let status = response.status();
-        if status == 429 {
-            let retry_after = response
-                .headers()
-                .get("Retry-After")
-                .and_then(|v| v.to_str().ok())
-                .and_then(|v| v.parse().ok());
-            return Err(SearchError::rate_limited("serper", retry_after));
-        }
-
-        if status == 401 || status == 403 {
-            return Err(SearchError::invalid_api_key("serper"));
-        }
-
-        if !status.is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(SearchError::api(
-                "serper",
-                format!("HTTP {}: {}", status, error_text),
-            ));
+        if !response.status().is_success() {
+            return Err(error_from_response("serper", response).await);
         }
 
         let serper_response: SerperResponse = response.json().await?;
@@ -0,0 +1,189 @@
+//! Per-provider state persisted across invocations
+//!
+//! Usage counters and health records are small JSON maps keyed by provider
+//! name. Each access takes an advisory lock on a `.lock` file next to the
+//! state and re-reads it, so concurrent CLI invocations see and keep each
+//! other's updates; writes go through a per-process temporary file and a
+//! rename. Async code reaches the file through [`StateFile::blocking`], so
+//! waiting for another process's lock never stalls the runtime.
+
+use crate::error::Result;
+use fs2::FileExt;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// A provider-keyed map, optionally backed by a JSON file
+#[derive(Debug, Default)]
+pub struct StateFile<V> {
+    /// Backing file (in memory only when `None`)
+    path: Option<PathBuf>,
+    data: Mutex<HashMap<String, V>>,
+}
+
+impl<V: Serialize + DeserializeOwned> StateFile<V> {
+    /// Create a map backed by a file
+    pub fn at(path: PathBuf) -> Self {
+        Self {
+            path: Some(path),
+            data: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Lock the map, refreshing it from disk first
+    ///
+    /// Other processes using the same file wait until the guard is dropped.
+    pub fn lock(&self) -> StateGuard<'_, V> {
+        let mut data = self.data.lock().unwrap_or_else(|e| e.into_inner());
+        let mut file_lock = None;
+        if let Some(ref path) = self.path {
+            file_lock = lock_file(path);
+            if let Ok(content) = fs::read_to_string(path) {
+                match serde_json::from_str(&content) {
+                    Ok(stored) => *data = stored,
+                    Err(e) => {
+                        tracing::warn!("Ignoring corrupt state file {}: {}", path.display(), e)
+                    }
+                }
+            }
+        }
+        StateGuard {
+            data,
+            _file_lock: file_lock,
+        }
+    }
+
+    /// Run `f` on a blocking thread, where it can lock and save the map
+    ///
+    /// In-memory maps never wait on other processes, so `f` runs in place.
+    pub async fn blocking<R, F>(self: &Arc<Self>, f: F) -> R
+    where
+        F: FnOnce(&Self) -> R + Send + 'static,
+        R: Send + 'static,
+        V: Send + 'static,
+    {
+        if self.path.is_none() {
+            return f(self);
+        }
+        let state = Arc::clone(self);
+        tokio::task::spawn_blocking(move || f(&state))
+            .await
+            .unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()))
+    }
+
+    /// Write the map back to disk and release the lock
+    pub fn save(&self, data: StateGuard<'_, V>) {
+        let path = match self.path {
+            Some(ref path) => path,
+            None => return,
+        };
+
+        let result = (|| -> Result<()> {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            // The file lock keeps other processes out, the pid keeps
+            // processes that could not take it apart
+            let tmp = path.with_extension(format!("json.{}.tmp", std::process::id()));
+            fs::write(&tmp, serde_json::to_string_pretty(&*data)?)?;
+            fs::rename(&tmp, path)?;
+            Ok(())
+        })();
+
+        if let Err(e) = result {
+            tracing::warn!("Failed to save {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// A locked state map; changes are only written by [`StateFile::save`]
+pub struct StateGuard<'a, V> {
+    data: MutexGuard<'a, HashMap<String, V>>,
+    /// Released when the guard is dropped
+    _file_lock: Option<fs::File>,
+}
+
+impl<V> Deref for StateGuard<'_, V> {
+    type Target = HashMap<String, V>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.data
+    }
+}
+
+impl<V> DerefMut for StateGuard<'_, V> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.data
+    }
+}
+
+/// Take an exclusive advisory lock on the file's `.lock` companion
+///
+/// The state file itself is replaced on every save, so it cannot carry the
+/// lock. Failing to lock only costs updates from concurrent invocations.
+fn lock_file(path: &Path) -> Option<fs::File> {
+    let lock_path = path.with_extension("json.lock");
+    let result = (|| -> std::io::Result<fs::File> {
+        if let Some(parent) = lock_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)?;
+        file.lock_exclusive()?;
+        Ok(file)
+    })();
+
+    match result {
+        Ok(file) => Some(file),
+        Err(e) => {
+            tracing::debug!("Could not lock {}: {}", lock_path.display(), e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_concurrent_updates_are_kept() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("usage.json");
+
+        // Separate instances stand in for separate processes
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    let state = StateFile::<u32>::at(path);
+                    for _ in 0..25 {
+                        let mut data = state.lock();
+                        *data.entry("brave".to_string()).or_default() += 1;
+                        state.save(data);
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        let state = StateFile::<u32>::at(path);
+        assert_eq!(state.lock().get("brave"), Some(&100));
+        let leftovers: Vec<_> = fs::read_dir(dir.path())
+            .unwrap()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name().to_string_lossy().ends_with(".tmp"))
+            .collect();
+        assert!(leftovers.is_empty());
+    }
+}
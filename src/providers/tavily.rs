@@ -1,5 +1,6 @@
 //! Tavily Search API provider
 
+use super::response::error_from_response;
 use super::{SearchOptions, SearchProvider, SearchResult};
 use crate::error::{Result, SearchError};
 use async_trait::async_trait;
@@ -22,6 +23,12 @@ impl TavilyProvider {
             client: Client::new(),
         }
     }
+
+    /// Use a shared HTTP client (proxy, TLS and timeout settings)
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
 }
 
 #[async_trait]
@@ -59,21 +66,8 @@ impl SearchProvider for TavilyProvider {
             .send()
             .await?;
 
-        let status = response.status();
-        if status == 429 {
-            return Err(SearchError::rate_limited("tavily", None));
-        }
-
-        if status == 401 || status == 403 {
-            return Err(SearchError::invalid_api_key("tavily"));
-        }
-
-        if !status.is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(SearchError::api(
-                "tavily",
-                format!("HTTP {}: {}", status, error_text),
-            ));
+        if !response.status().is_success() {
+            return Err(error_from_response("tavily", response).await);
         }
 
         let tavily_response: TavilySearchResponse = response.json().await?;
@@ -0,0 +1,80 @@
+//! JSON Schemas for the config file and JSON output
+//!
+//! Output schemas pin `format_version` to `OUTPUT_FORMAT_VERSION`, so data
+//! checked against a saved schema fails once the format changes
+//! incompatibly. The config schema caps `version` at `CONFIG_VERSION` the
+//! same way.
+
+use crate::cli::SchemaKind;
+use crate::config::{Config, CONFIG_VERSION};
+use crate::fetch::FetchResponse;
+use crate::output::{ErrorResponse, SearchResponse, OUTPUT_FORMAT_VERSION};
+use crate::providers::SearchResult;
+use schemars::schema_for;
+use serde_json::{json, Value};
+
+/// The schema for one kind of document
+pub fn schema_for_kind(kind: &SchemaKind) -> Value {
+    let (schema, versioned) = match kind {
+        SchemaKind::Config => (schema_for!(Config), false),
+        SchemaKind::SearchResponse => (schema_for!(SearchResponse), true),
+        SchemaKind::SearchResult => (schema_for!(SearchResult), false),
+        SchemaKind::FetchResponse => (schema_for!(FetchResponse), true),
+        SchemaKind::ErrorResponse => (schema_for!(ErrorResponse), true),
+    };
+    let mut schema = serde_json::to_value(schema).expect("schemas serialize to JSON");
+
+    let properties = &mut schema["properties"];
+    match versioned {
+        true => properties["format_version"]["const"] = json!(OUTPUT_FORMAT_VERSION),
+        false if *kind == SchemaKind::Config => {
+            properties["version"]["maximum"] = json!(CONFIG_VERSION)
+        }
+        false => {}
+    }
+    schema
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::OutputFormatter;
+
+    #[test]
+    fn test_output_schemas_pin_format_version() {
+        let schema = schema_for_kind(&SchemaKind::SearchResponse);
+        let properties = &schema["properties"];
+        assert_eq!(
+            properties["format_version"]["const"],
+            json!(OUTPUT_FORMAT_VERSION)
+        );
+        // Metadata is flattened into the top level, as in the output
+        assert!(properties["query"].is_object());
+        assert!(properties["results"].is_object());
+
+        let response = SearchResponse::new("q".to_string(), "brave".to_string(), vec![], 1);
+        let output: Value =
+            serde_json::from_str(&crate::output::JsonFormatter::new().format(&response)).unwrap();
+        assert_eq!(output["format_version"], json!(OUTPUT_FORMAT_VERSION));
+
+        for kind in [SchemaKind::FetchResponse, SchemaKind::ErrorResponse] {
+            let schema = schema_for_kind(&kind);
+            assert_eq!(
+                schema["properties"]["format_version"]["const"],
+                json!(OUTPUT_FORMAT_VERSION)
+            );
+        }
+    }
+
+    #[test]
+    fn test_config_schema() {
+        let schema = schema_for_kind(&SchemaKind::Config);
+        assert_eq!(schema["title"], "Config");
+        assert_eq!(schema["additionalProperties"], json!(false));
+        assert_eq!(
+            schema["properties"]["version"]["maximum"],
+            json!(CONFIG_VERSION)
+        );
+        assert!(schema["definitions"]["ProvidersConfig"]["properties"]["brave"].is_object());
+    }
+}